@@ -0,0 +1,1844 @@
+// One-shot subcommands
+//
+// `todo <command> [args]` runs a single command and exits, so the app can be
+// driven from scripts, git hooks and cron. Without a command it starts the
+// interactive prompt (repl.rs), which turns its own syntax into the same
+// `Command` values and runs them through `execute`.
+//
+// --format (or --json) switches to machine-readable output; see output.rs for
+// the formats and their fields.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
+use clap::{Parser, Subcommand};
+
+use crate::due::Due;
+use crate::edit::{self, Fields};
+use crate::exchange::{self, Exchange};
+use crate::history::{self, Operation};
+use crate::journal;
+use crate::markdown::{self, Group};
+use crate::output::{self, Format};
+use crate::recurrence::Recurrence;
+use crate::render::Layout;
+use crate::reports::{self, Period};
+use crate::server;
+use crate::shelf;
+use crate::store;
+use crate::tracking::{self, Report};
+use crate::tui;
+use crate::workflow::{Status, Workflow};
+use crate::{format_local, parse_priority, tags, Priority, SaveError, Task, TaskError, TodoApp};
+
+pub const EXIT_INVALID: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+pub const EXIT_CONFLICT: i32 = 5;
+
+// The todo file when neither --file nor the config names one
+pub const DEFAULT_FILE: &str = "todos.txt";
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  2  invalid input: bad arguments, dates, rules, queries or config, or a refused change
+  3  a task was not found
+  4  the todo file could not be read or written, or is locked by another process
+  5  another todo process changed the same tasks; the change was not saved";
+
+#[derive(Parser, Debug)]
+#[command(name = "todo", version, about = "A command-line todo list manager")]
+#[command(after_help = EXIT_CODES)]
+pub struct Cli {
+    /// Todo file to read and write; a .db, .sqlite or .sqlite3 file is an SQLite database
+    /// [default: the config's file, or todos.txt]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub file: Option<String>,
+
+    /// Output format; json, ndjson and csv are stable for scripts
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub format: Format,
+
+    /// Shorthand for --format json
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Status workflow, e.g. "todo>in-progress,done; in-progress>done; done>todo"
+    #[arg(
+        long,
+        global = true,
+        env = "TODO_WORKFLOW",
+        value_name = "RULES",
+        value_parser = Workflow::parse
+    )]
+    pub workflow: Option<Workflow>,
+
+    /// Command to run; starts the interactive prompt when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    pub fn output_format(&self) -> Format {
+        if self.json {
+            Format::Json
+        } else {
+            self.format
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Add a task; #tag and +project words in the title become tags and the project
+    Add {
+        #[arg(required = true)]
+        title: Vec<String>,
+        /// high, medium or low, or another level the config enables
+        /// [default: the config's default_priority, or medium]
+        #[arg(short, long, value_parser = parse_priority)]
+        priority: Option<Priority>,
+        /// Due date, e.g. 2026-05-01, tomorrow, +3d, "fri 5pm"
+        #[arg(short, long, value_name = "WHEN")]
+        due: Option<String>,
+        /// Repeat rule, e.g. daily, weekdays, weekly:mon,fri, monthly:15, 3d
+        #[arg(short, long, value_name = "RULE")]
+        repeat: Option<String>,
+        /// Make the new task a subtask of this one
+        #[arg(long, value_name = "ID")]
+        parent: Option<u32>,
+    },
+    /// Move a task to the trash
+    #[command(visible_alias = "rm")]
+    Remove { id: u32 },
+    /// List the tasks in the trash
+    Trash,
+    /// Bring a task back from the trash or the archive
+    Restore { id: u32 },
+    /// Delete a task in the trash for good, or every task in it
+    Purge { id: Option<u32> },
+    /// Move done and cancelled tasks closed over a number of days ago to the archive
+    Archive {
+        /// Archive tasks closed more than this many days ago
+        #[arg(long, value_name = "DAYS", default_value_t = 30)]
+        older_than: u32,
+        /// Show what would be archived without archiving anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Toggle a task between done and pending, or every task a query matches
+    #[command(visible_alias = "t")]
+    Toggle {
+        /// Task id, or query words picking the tasks, e.g. `status:pending #sprint12`
+        #[arg(required = true, value_name = "ID|QUERY")]
+        target: Vec<String>,
+        /// Complete tasks even if they have open subtasks
+        #[arg(short, long)]
+        force: bool,
+        /// Show what would change without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Move a task to another status, e.g. `todo move 3 in-progress`
+    Move {
+        /// Task id, or query words picking the tasks
+        #[arg(required = true, value_name = "ID|QUERY")]
+        target: Vec<String>,
+        /// todo, in-progress, waiting, done, cancelled or one the workflow adds
+        #[arg(value_parser = Status::parse)]
+        status: Status,
+        /// Complete tasks even if they have open subtasks
+        #[arg(short, long)]
+        force: bool,
+        /// Show what would change without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Change a task's fields, e.g. `todo edit 3 --title "Ship it" -p high`, or in $EDITOR
+    Edit {
+        /// Task id, or query words picking the tasks, e.g. `todo edit -p high -- "#urgent"`
+        #[arg(required = true, value_name = "ID|QUERY")]
+        target: Vec<String>,
+        #[command(flatten)]
+        fields: Fields,
+        /// Open the task in $VISUAL or $EDITOR as text to change any field
+        #[arg(short, long)]
+        editor: bool,
+        /// Complete tasks even if they have open subtasks
+        #[arg(short, long)]
+        force: bool,
+        /// Show what would change without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Show tasks in a column per status, e.g. `todo board -- +website -cancelled`
+    Board {
+        /// Query words to filter the tasks by, as for list
+        query: Vec<String>,
+    },
+    /// Start timing work on a task, stopping any other task being timed
+    Start { id: u32 },
+    /// Stop timing the task being timed
+    Stop,
+    /// Show a report, e.g. `todo report activity --by week`; time worked by default
+    Report {
+        /// Report to show
+        #[arg(value_enum, default_value = "time")]
+        kind: reports::Kind,
+        /// First day to count, e.g. 2026-10-01 or -30d [default depends on the report]
+        #[arg(short, long, value_name = "DATE", allow_hyphen_values = true)]
+        since: Option<String>,
+        /// Count the activity report per day or per week [default: day]
+        #[arg(long, value_enum)]
+        by: Option<Period>,
+    },
+    /// Set a task's description
+    #[command(visible_alias = "description")]
+    Desc {
+        id: u32,
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Set a task's due date, or clear it with "none"
+    Due {
+        id: u32,
+        #[arg(required = true, value_name = "WHEN")]
+        when: Vec<String>,
+    },
+    /// Make a task recurring, or stop it repeating with "none"
+    Repeat { id: u32, rule: String },
+    /// Make a task a subtask of another, or top-level with "none"
+    Parent {
+        id: u32,
+        #[arg(value_name = "PARENT")]
+        parent: String,
+    },
+    /// Mark a task as waiting on another
+    Block { id: u32, blocker: u32 },
+    /// Remove a dependency added with block
+    Unblock { id: u32, blocker: u32 },
+    /// Add #tags or set the +project (quote #tags in the shell)
+    Tag {
+        id: u32,
+        #[arg(required = true, value_name = "MARKER")]
+        markers: Vec<String>,
+    },
+    /// Remove #tags or clear the +project
+    Untag {
+        id: u32,
+        #[arg(required = true, value_name = "MARKER")]
+        markers: Vec<String>,
+    },
+    /// Show tags and projects with task counts
+    Tags,
+    /// Show overdue tasks and tasks due today
+    #[command(visible_alias = "agenda")]
+    Today,
+    /// List tasks matching a query, e.g. `todo list -- "#work" -done sort:due`
+    #[command(visible_alias = "ls")]
+    List {
+        /// Query to filter by; combined with any query words. Without either, the
+        /// config's list filter applies, and its sort to queries without sort:
+        #[arg(short, long, value_name = "QUERY")]
+        filter: Option<String>,
+        /// Query words; put them after `--` when one starts with '-'
+        query: Vec<String>,
+        /// Search the archive instead
+        #[arg(short, long)]
+        archived: bool,
+        /// One line per task
+        #[arg(short, long)]
+        compact: bool,
+    },
+    /// Show statistics
+    #[command(visible_alias = "statistics")]
+    Stats {
+        /// Count archived tasks too
+        #[arg(short, long)]
+        archived: bool,
+    },
+    /// Revert the last change
+    Undo,
+    /// Reapply the last undone change
+    Redo,
+    /// List recent changes, newest first
+    Log {
+        /// How many changes to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Write every task in another app's format, e.g. `todo export todotxt -o todo.txt`
+    Export {
+        /// Format to write
+        #[arg(value_enum, value_name = "FORMAT")]
+        kind: Exchange,
+        /// File to write; prints to the terminal when omitted
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+        /// Sections of a markdown export [default: priority]
+        #[arg(long, value_enum)]
+        group: Option<Group>,
+    },
+    /// Add or update tasks from a file in another app's format; ids are added to markdown files
+    Import {
+        /// Format to read
+        #[arg(value_enum, value_name = "FORMAT")]
+        kind: Exchange,
+        /// File to read
+        path: String,
+    },
+    /// Copy everything, undo history included, to a new store, e.g. `todo migrate --to sqlite`
+    Migrate {
+        /// Kind of store to copy to
+        #[arg(long, value_enum)]
+        to: store::Kind,
+        /// Where to create it; defaults to the todo file renamed, e.g. todos.db
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Serve the tasks as a JSON HTTP API on 127.0.0.1, e.g. for a web dashboard
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Browse and edit the tasks in a full-screen terminal UI
+    Tui,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    Invalid(String),
+    NotFound(String),
+    Io(String),
+    Conflict(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Invalid(_) => EXIT_INVALID,
+            CliError::NotFound(_) => EXIT_NOT_FOUND,
+            CliError::Io(_) => EXIT_IO,
+            CliError::Conflict(_) => EXIT_CONFLICT,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Invalid(message)
+            | CliError::NotFound(message)
+            | CliError::Io(message)
+            | CliError::Conflict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<TaskError> for CliError {
+    fn from(error: TaskError) -> Self {
+        match error {
+            TaskError::NotFound(_) => CliError::NotFound(error.to_string()),
+            _ => CliError::Invalid(error.to_string()),
+        }
+    }
+}
+
+impl From<SaveError> for CliError {
+    fn from(error: SaveError) -> Self {
+        match error {
+            SaveError::Io(message) => CliError::Io(message),
+            SaveError::Conflict(message) => CliError::Conflict(message),
+        }
+    }
+}
+
+fn not_found(id: u32) -> CliError {
+    TaskError::NotFound(id).into()
+}
+
+// Runs a command on the latest saved tasks, records what it changed in the
+// undo history and saves
+pub fn execute(app: &mut TodoApp, command: Command, format: Format) -> Result<(), CliError> {
+    // Another process may have saved since this one last looked
+    if !app.dirty {
+        app.load_from_file().map_err(CliError::Io)?;
+    }
+    // The server and the terminal UI record each change they make themselves.
+    // Archiving isn't undone; restore brings archived tasks back.
+    let recorded = !matches!(
+        command,
+        Command::Undo
+            | Command::Redo
+            | Command::Serve { .. }
+            | Command::Tui
+            | Command::Archive { .. }
+    );
+    let before = recorded.then(|| app.tasks.clone());
+    let next_id = app.next_id;
+
+    let result = run(app, command.clone(), format);
+
+    if let Some(before) = before {
+        let changes = history::diff(&before, &app.tasks);
+        if !changes.is_empty() {
+            app.history.record(Operation {
+                time: Utc::now(),
+                label: label(&command, &before, app, next_id),
+                next_id: (next_id, app.next_id),
+                changes,
+            });
+        }
+    }
+    let mut renumbered = Vec::new();
+    if app.dirty {
+        renumbered = app.save_to_file()?;
+    }
+    match result? {
+        Some(write_back) => write_back.write(app, &renumbered, format),
+        None => Ok(()),
+    }
+}
+
+// Ids for the new checklist items of an imported markdown file. They're
+// written back only once the tasks are saved, so the file never points at a
+// task that wasn't.
+struct WriteBack {
+    path: String,
+    content: String,
+    ids: Vec<u32>,
+}
+
+impl WriteBack {
+    // Writes the ids the tasks were saved under, following any the save
+    // renumbered (see sync.rs)
+    fn write(
+        self,
+        app: &TodoApp,
+        renumbered: &[(u32, u32)],
+        format: Format,
+    ) -> Result<(), CliError> {
+        let saved_as = |id: &u32| {
+            renumbered
+                .iter()
+                .find(|(old, _)| old == id)
+                .map_or(*id, |(_, new)| *new)
+        };
+        let ids: Vec<u32> = self.ids.iter().map(saved_as).collect();
+        let (content, added) = markdown::add_ids(&self.content, &ids);
+        if added == 0 {
+            return Ok(());
+        }
+        journal::write_atomic(&self.path, &content)
+            .map_err(|e| CliError::Io(format!("Error writing {}: {}", self.path, e)))?;
+        if format == Format::Text {
+            let text = format!("Added ids to {} new items in {}", added, self.path);
+            say(app, "🔖", &text);
+        }
+        Ok(())
+    }
+}
+
+// A short description of a change for the history log
+fn label(command: &Command, before: &HashMap<u32, Task>, app: &TodoApp, next_id: u32) -> String {
+    let title = |id: &u32| {
+        let task = app.tasks.get(id).or_else(|| before.get(id));
+        format!("#{}: {}", id, task.map_or("", |t| t.title.as_str()))
+    };
+
+    match command {
+        Command::Add { .. } => format!("add {}", title(&next_id)),
+        Command::Remove { id } => format!("remove {}", title(id)),
+        Command::Restore { id } => format!("restore {}", title(id)),
+        Command::Toggle { target, .. } => match target_id(target) {
+            Some(id) if app.tasks.get(&id).is_some_and(|t| t.is_done()) => {
+                format!("complete {}", title(&id))
+            }
+            Some(id) => format!("reopen {}", title(&id)),
+            None => format!("toggle tasks matching {}", target.join(" ")),
+        },
+        Command::Move { target, status, .. } => match target_id(target) {
+            Some(id) => format!("move {} to {}", title(&id), status.name()),
+            None => format!(
+                "move tasks matching {} to {}",
+                target.join(" "),
+                status.name()
+            ),
+        },
+        Command::Edit { target, .. } => match target_id(target) {
+            Some(id) => format!("edit {}", title(&id)),
+            None => format!("edit tasks matching {}", target.join(" ")),
+        },
+        Command::Start { id } => format!("start {}", title(id)),
+        Command::Stop => match before.values().find(|t| t.is_timing()) {
+            Some(task) => format!("stop {}", title(&task.id)),
+            None => "stop".to_string(),
+        },
+        Command::Desc { id, .. } => format!("describe {}", title(id)),
+        Command::Due { id, .. } => format!("set due date of {}", title(id)),
+        Command::Repeat { id, .. } => format!("set repeat rule of {}", title(id)),
+        Command::Parent { id, .. } => format!("move {}", title(id)),
+        Command::Block { id, blocker } => format!("block {} on #{}", title(id), blocker),
+        Command::Unblock { id, blocker } => format!("unblock {} from #{}", title(id), blocker),
+        Command::Tag { id, .. } => format!("tag {}", title(id)),
+        Command::Untag { id, .. } => format!("untag {}", title(id)),
+        Command::Import { path, .. } => format!("import {}", path),
+        _ => format!("{:?}", command).to_lowercase(),
+    }
+}
+
+// Runs a command, returning what's left to do once its changes are saved
+fn run(app: &mut TodoApp, command: Command, format: Format) -> Result<Option<WriteBack>, CliError> {
+    let now = Local::now().naive_local();
+    let today = now.date();
+    let mut write_back = None;
+
+    match command {
+        Command::Add {
+            title,
+            priority,
+            due,
+            repeat,
+            parent,
+        } => {
+            let title = title.join(" ");
+            if tags::extract(&title).title.trim().is_empty() {
+                return Err(CliError::Invalid("A task needs a title".to_string()));
+            }
+            let due = due
+                .map(|when| Due::parse(&when, today))
+                .transpose()
+                .map_err(CliError::Invalid)?;
+            let anchor = due.map_or(today, |d| d.date);
+            let recurrence = repeat
+                .map(|rule| Recurrence::parse(&rule, anchor))
+                .transpose()
+                .map_err(CliError::Invalid)?;
+            if let Some(parent) = parent {
+                if !app.tasks.contains_key(&parent) {
+                    return Err(not_found(parent));
+                }
+            }
+            let priority = priority.unwrap_or_else(|| app.config.default_priority.clone());
+            app.config
+                .check_priority(&priority)
+                .map_err(CliError::Invalid)?;
+
+            let id = app.add_task(title, priority, due, recurrence);
+            if parent.is_some() {
+                // A brand new task can't create a cycle
+                app.set_parent(id, parent)?;
+            }
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                say(
+                    app,
+                    "✅",
+                    &format!("Added task #{}: {}", id, app.tasks[&id].title),
+                );
+            }
+        }
+        Command::Remove { id } => {
+            let removed = app.tasks.get(&id).cloned().ok_or_else(|| not_found(id))?;
+            app.remove_task(id);
+            if format != Format::Text {
+                print!("{}", output::tasks(&[&removed], format, now));
+            } else {
+                say(app, "🗑️", &format!("Moved task #{} to the trash", id));
+            }
+        }
+        Command::Trash => {
+            let tasks = app.trash.sorted();
+            if format != Format::Text {
+                print!("{}", output::tasks(&tasks, format, now));
+            } else if tasks.is_empty() {
+                println!("The trash is empty.");
+            } else {
+                println!("\n{}", app.renderer.prefixed("🗑️", "Trash:"));
+                println!("{}", app.renderer.rule());
+                let time_format = app.config.time_format();
+                for task in tasks {
+                    let removed = format_local(&task.updated_at, &time_format);
+                    println!("  [{}] {}  (removed {})", task.id, task.title, removed);
+                }
+                println!("\nUse restore <id> to bring a task back, or purge to empty the trash.");
+            }
+        }
+        Command::Restore { id } => {
+            if app.tasks.contains_key(&id) {
+                return Err(CliError::Invalid(format!(
+                    "Task #{} is already in the todo list",
+                    id
+                )));
+            }
+            let archived = app.archive.tasks.contains_key(&id);
+            app.restore_task(id)?;
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                let from = if archived { "the archive" } else { "the trash" };
+                let title = &app.tasks[&id].title;
+                say(
+                    app,
+                    "♻️",
+                    &format!("Restored task #{} from {}: {}", id, from, title),
+                );
+            }
+        }
+        Command::Purge { id } => {
+            let ids: Vec<u32> = match id {
+                Some(id) if !app.trash.tasks.contains_key(&id) => {
+                    return Err(CliError::NotFound(format!(
+                        "Task #{} is not in the trash",
+                        id
+                    )))
+                }
+                Some(id) => vec![id],
+                None => app.trash.sorted().iter().map(|t| t.id).collect(),
+            };
+            if ids.is_empty() {
+                match format {
+                    Format::Text => println!("The trash is empty."),
+                    _ => print!("{}", output::tasks(&[], format, now)),
+                }
+                return Ok(None);
+            }
+            let purged: Vec<Task> = ids.iter().map(|id| app.trash.tasks[id].clone()).collect();
+            app.purge(&ids).map_err(CliError::Io)?;
+            if format != Format::Text {
+                let purged: Vec<&Task> = purged.iter().collect();
+                print!("{}", output::tasks(&purged, format, now));
+            } else {
+                match purged.len() {
+                    1 => say(
+                        app,
+                        "🔥",
+                        &format!("Deleted task #{} for good", purged[0].id),
+                    ),
+                    n => say(app, "🔥", &format!("Deleted {} tasks for good", n)),
+                }
+            }
+        }
+        Command::Archive {
+            older_than,
+            dry_run,
+        } => {
+            let cutoff = Utc::now() - chrono::Duration::days(older_than.into());
+            let ids = app.archivable(cutoff);
+            let tasks: Vec<Task> = ids.iter().map(|id| app.tasks[id].clone()).collect();
+            if !dry_run {
+                app.archive_tasks(&ids);
+            }
+
+            if format != Format::Text {
+                let tasks: Vec<&Task> = tasks.iter().collect();
+                print!("{}", output::tasks(&tasks, format, now));
+            } else if tasks.is_empty() {
+                println!(
+                    "Nothing closed more than {} days ago to archive.",
+                    older_than
+                );
+            } else {
+                let count = match tasks.len() {
+                    1 => "1 task".to_string(),
+                    n => format!("{} tasks", n),
+                };
+                match dry_run {
+                    true => say(app, "🔍", &format!("Dry run: would archive {}:", count)),
+                    false => say(app, "📦", &format!("Archived {}:", count)),
+                }
+                for task in &tasks {
+                    println!("  [{}] {}", task.id, task.title);
+                }
+                if !dry_run {
+                    println!("Search them with list --archived.");
+                }
+            }
+        }
+        Command::Toggle {
+            target,
+            force,
+            dry_run,
+        } => {
+            let ids = targets(app, &target)?;
+            let toggle = |app: &mut TodoApp, id| Ok(app.toggle_task(id, force)?);
+            let Some(done) = each(app, &ids, dry_run, format, now, toggle)? else {
+                return Ok(None);
+            };
+            if format != Format::Text {
+                print_tasks(app, &with_next(&done), format, now);
+            } else {
+                for (id, next) in done {
+                    say(app, "🔄", &format!("Toggled task #{}", id));
+                    print_next(app, next);
+                }
+            }
+        }
+        Command::Move {
+            target,
+            status,
+            force,
+            dry_run,
+        } => {
+            let ids = targets(app, &target)?;
+            let move_task = |app: &mut TodoApp, id| Ok(app.move_task(id, status.clone(), force)?);
+            let Some(done) = each(app, &ids, dry_run, format, now, move_task)? else {
+                return Ok(None);
+            };
+            if format != Format::Text {
+                print_tasks(app, &with_next(&done), format, now);
+            } else {
+                for (id, next) in done {
+                    println!("{}", moved(app, id, &status));
+                    print_next(app, next);
+                }
+            }
+        }
+        Command::Edit {
+            target,
+            fields,
+            editor,
+            force,
+            dry_run,
+        } => {
+            let ids = targets(app, &target)?;
+            let fields = match (editor, ids.as_slice()) {
+                (false, _) if fields.is_empty() => {
+                    return Err(CliError::Invalid(
+                        "Nothing to change: give a field such as --title or --priority, \
+                         or --editor"
+                            .to_string(),
+                    ))
+                }
+                (false, _) => fields,
+                (true, _) if !fields.is_empty() => {
+                    return Err(CliError::Invalid(
+                        "--editor can't be combined with field options".to_string(),
+                    ))
+                }
+                (true, &[id]) => {
+                    let text = edit::open(&edit::render(&app.tasks[&id]), id)?;
+                    let fields = edit::parse(&text, &app.tasks[&id], today).map_err(|e| {
+                        CliError::Invalid(format!("Can't read the edited task #{}: {}", id, e))
+                    })?;
+                    if fields.is_empty() && format == Format::Text {
+                        println!("No changes made to task #{}", id);
+                        return Ok(None);
+                    }
+                    fields
+                }
+                (true, _) => {
+                    return Err(CliError::Invalid(
+                        "--editor edits one task at a time; give its id".to_string(),
+                    ))
+                }
+            };
+
+            let apply = |app: &mut TodoApp, id| edit::apply(app, id, &fields, force, today);
+            let Some(done) = each(app, &ids, dry_run, format, now, apply)? else {
+                return Ok(None);
+            };
+            if format != Format::Text {
+                print_tasks(app, &with_next(&done), format, now);
+            } else {
+                for (id, next) in done {
+                    say(
+                        app,
+                        "✏️",
+                        &format!("Updated task #{}: {}", id, app.tasks[&id].title),
+                    );
+                    print_next(app, next);
+                }
+            }
+        }
+        Command::Board { query } => {
+            let filter = query.join(" ");
+            let filter = Some(filter.as_str()).filter(|f| !f.trim().is_empty());
+            let tasks = app
+                .find_tasks(filter)
+                .map_err(|e| CliError::Invalid(e.render(filter.unwrap_or(""))))?;
+            let columns = app.workflow.board(&tasks);
+            if format != Format::Text {
+                print!("{}", output::board(&columns, format, now));
+            } else {
+                print_board(app, &columns);
+            }
+        }
+        Command::Start { id } => {
+            let stopped = app.start_timer(id)?;
+            if format != Format::Text {
+                let ids: Vec<u32> = stopped.into_iter().chain([id]).collect();
+                print_tasks(app, &ids, format, now);
+            } else {
+                if let Some(stopped) = stopped {
+                    print_stopped(app, stopped);
+                }
+                say(
+                    app,
+                    "▶️",
+                    &format!("Started timing task #{}: {}", id, app.tasks[&id].title),
+                );
+            }
+        }
+        Command::Stop => {
+            let id = app
+                .stop_timer()
+                .ok_or_else(|| CliError::Invalid("No task is being timed".to_string()))?;
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                print_stopped(app, id);
+            }
+        }
+        Command::Report { kind, since, by } => {
+            if by.is_some() && kind != reports::Kind::Activity {
+                return Err(CliError::Invalid(
+                    "--by only applies to the activity report".to_string(),
+                ));
+            }
+            let by = by.unwrap_or_default();
+            let since = match since {
+                Some(since) => Some(
+                    Due::parse(&since, today)
+                        .ok()
+                        .filter(|due| due.time.is_none())
+                        .map(|due| due.date)
+                        .ok_or_else(|| {
+                            CliError::Invalid(format!("Can't understand date '{}'", since))
+                        })?,
+                ),
+                None => kind.default_since(by, today),
+            };
+            print_report(app, kind, since, by, format, today);
+        }
+        Command::Desc { id, text } => {
+            if !app.update_description(id, text.join(" ")) {
+                return Err(not_found(id));
+            }
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                say(app, "📝", &format!("Updated description for task #{}", id));
+            }
+        }
+        Command::Due { id, when } => {
+            let when = when.join(" ");
+            let due = if when.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                Some(Due::parse(&when, today).map_err(CliError::Invalid)?)
+            };
+
+            if !app.set_due(id, due) {
+                return Err(not_found(id));
+            }
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else if let Some(due) = due {
+                say(app, "📅", &format!("Task #{} is due {}", id, due));
+            } else {
+                say(app, "📅", &format!("Cleared due date for task #{}", id));
+            }
+        }
+        Command::Repeat { id, rule } => {
+            let task = app.tasks.get(&id).ok_or_else(|| not_found(id))?;
+            let anchor = task.due.map_or(today, |d| d.date);
+            let recurrence = if rule.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                Some(Recurrence::parse(&rule, anchor).map_err(CliError::Invalid)?)
+            };
+
+            app.set_recurrence(id, recurrence.clone());
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                match recurrence {
+                    Some(rule) => say(
+                        app,
+                        "🔁",
+                        &format!("Task #{} repeats {}", id, rule.describe()),
+                    ),
+                    None => say(app, "🔁", &format!("Task #{} no longer repeats", id)),
+                }
+            }
+        }
+        Command::Parent { id, parent } => {
+            let parent = if parent.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                let parent = parent
+                    .parse::<u32>()
+                    .map_err(|_| CliError::Invalid(format!("Invalid task ID '{}'", parent)))?;
+                Some(parent)
+            };
+
+            app.set_parent(id, parent)?;
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                match parent {
+                    Some(parent) => say(
+                        app,
+                        "📂",
+                        &format!("Task #{} is now a subtask of #{}", id, parent),
+                    ),
+                    None => say(app, "📂", &format!("Task #{} is now a top-level task", id)),
+                }
+            }
+        }
+        Command::Block { id, blocker } => {
+            app.add_blocker(id, blocker)?;
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                say(
+                    app,
+                    "⛔",
+                    &format!("Task #{} is blocked by #{}", id, blocker),
+                );
+            }
+        }
+        Command::Unblock { id, blocker } => {
+            app.remove_blocker(id, blocker)?;
+            if format != Format::Text {
+                print_tasks(app, &[id], format, now);
+            } else {
+                say(
+                    app,
+                    "✅",
+                    &format!("Task #{} no longer waits on #{}", id, blocker),
+                );
+            }
+        }
+        Command::Tag { id, markers } => update_tags(app, id, &markers, true, format, now)?,
+        Command::Untag { id, markers } => update_tags(app, id, &markers, false, format, now)?,
+        Command::Tags => print_tags(app, format),
+        Command::Today => {
+            if format != Format::Text {
+                let (overdue, due_today) = app.agenda(now);
+                let tasks: Vec<&Task> = overdue.into_iter().chain(due_today).collect();
+                print!("{}", output::tasks(&tasks, format, now));
+            } else {
+                app.show_agenda();
+            }
+        }
+        Command::List {
+            filter,
+            query,
+            archived,
+            compact,
+        } => {
+            let layout = match compact {
+                true => Layout::Compact,
+                false => app.renderer.layout,
+            };
+            let filter: Vec<String> = filter.into_iter().chain(query).collect();
+            let filter = filter.join(" ");
+            let filter = Some(filter.as_str()).filter(|f| !f.trim().is_empty());
+            // The config's default filter and sort are for the todo list
+            let listed = app.config.list_query(filter);
+            let filter = if archived { filter } else { listed.as_deref() };
+
+            let result = if archived {
+                app.find_archived(filter)
+                    .map(|tasks| print_archived(app, &tasks, format, now, layout))
+            } else if format != Format::Text {
+                app.find_tasks(filter)
+                    .map(|tasks| print!("{}", output::tasks(&tasks, format, now)))
+            } else {
+                app.list_tasks(filter, layout)
+            };
+            result.map_err(|e| CliError::Invalid(e.render(filter.unwrap_or(""))))?;
+        }
+        Command::Stats { archived } => print_stats(app, archived, format, now),
+        Command::Undo | Command::Redo => {
+            let undoing = command == Command::Undo;
+            let operation = if undoing { app.undo() } else { app.redo() };
+            let operation = operation.map_err(CliError::Invalid)?;
+
+            if format != Format::Text {
+                let ids: Vec<u32> = operation
+                    .changes
+                    .iter()
+                    .map(|change| change.id)
+                    .filter(|id| app.tasks.contains_key(id))
+                    .collect();
+                print_tasks(app, &ids, format, now);
+            } else if undoing {
+                say(app, "↩️", &format!("Undid: {}", operation.label));
+            } else {
+                say(app, "↪️", &format!("Redid: {}", operation.label));
+            }
+        }
+        Command::Export {
+            kind,
+            output,
+            group,
+        } => {
+            if group.is_some() && kind != Exchange::Markdown {
+                return Err(CliError::Invalid(
+                    "--group only applies to markdown exports".to_string(),
+                ));
+            }
+            let mut tasks: Vec<&Task> = app.tasks.values().collect();
+            tasks.sort_by_key(|t| t.id);
+            let content = exchange::export(kind, &tasks, group.unwrap_or_default());
+            match output {
+                Some(path) => {
+                    fs::write(&path, content)
+                        .map_err(|e| CliError::Io(format!("Error writing {}: {}", path, e)))?;
+                    say(
+                        app,
+                        "📤",
+                        &format!("Exported {} tasks to {}", tasks.len(), path),
+                    );
+                }
+                None => print!("{}", content),
+            }
+        }
+        Command::Import { kind, path } => {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| CliError::Io(format!("Error reading {}: {}", path, e)))?;
+            let imported = exchange::parse(kind, &content)
+                .map_err(|e| CliError::Invalid(format!("Can't import {} {}", path, e)))?;
+            let summary = exchange::apply(app, imported);
+            for warning in &summary.warnings {
+                eprintln!("{}", app.renderer.prefixed("⚠️", &warning.to_string()));
+            }
+
+            if format != Format::Text {
+                let ids = summary.added.iter().chain(&summary.updated);
+                let tasks: Vec<&Task> = ids.map(|id| &app.tasks[id]).collect();
+                print!("{}", output::tasks(&tasks, format, now));
+            } else {
+                say(
+                    app,
+                    "📥",
+                    &format!(
+                        "Imported {}: {} added, {} updated, {} unchanged",
+                        path,
+                        summary.added.len(),
+                        summary.updated.len(),
+                        summary.unchanged
+                    ),
+                );
+            }
+            // New checklist items get their ids written back so they match next time
+            if kind == Exchange::Markdown {
+                write_back = Some(WriteBack {
+                    path,
+                    content,
+                    ids: summary.ids,
+                });
+            }
+        }
+        Command::Migrate { to, output } => {
+            let target = output.unwrap_or_else(|| to.rename(app.file_path()));
+            if store::Kind::of(&target) != to {
+                let problem = match to {
+                    store::Kind::Sqlite => "needs a .db, .sqlite or .sqlite3 name",
+                    store::Kind::File => "can't end in .db, .sqlite or .sqlite3",
+                };
+                let kind = match to {
+                    store::Kind::Sqlite => "An SQLite database",
+                    store::Kind::File => "A todo file",
+                };
+                return Err(CliError::Invalid(format!(
+                    "{} {}: {}",
+                    kind, problem, target
+                )));
+            }
+            if store::open(&target).exists() {
+                return Err(CliError::Invalid(format!("{} already exists", target)));
+            }
+
+            store::copy(&app.tasks, &target).map_err(CliError::Io)?;
+            for (shelved, name) in [(&app.trash, shelf::TRASH), (&app.archive, shelf::ARCHIVE)] {
+                if !shelved.tasks.is_empty() {
+                    let path = shelf::path(&target, name);
+                    store::copy(&shelved.tasks, &path).map_err(CliError::Io)?;
+                }
+            }
+            let history = app.history_path();
+            if Path::new(&history).exists() {
+                let copied = format!("{}.history", target);
+                fs::copy(&history, &copied)
+                    .map_err(|e| CliError::Io(format!("Error copying {}: {}", history, e)))?;
+            }
+
+            if format != Format::Text {
+                let mut tasks: Vec<&Task> = app.tasks.values().collect();
+                tasks.sort_by_key(|t| t.id);
+                print!("{}", output::tasks(&tasks, format, now));
+            } else {
+                say(
+                    app,
+                    "📦",
+                    &format!(
+                        "Copied {} tasks from {} to {}",
+                        app.tasks.len(),
+                        app.file_path(),
+                        target
+                    ),
+                );
+                println!("   Use --file {} to work with them there", target);
+            }
+        }
+        // Runs until stopped, saving each request's change as it goes
+        Command::Serve { port } => server::serve(app, port)?,
+        Command::Tui => tui::run(app)?,
+        Command::Log { limit } => {
+            let recent = app.history.recent(limit);
+            if format != Format::Text {
+                print!("{}", output::log(&recent, format));
+            } else if recent.is_empty() {
+                println!("No changes recorded yet.");
+            } else {
+                println!("\n{}", app.renderer.prefixed("📜", "Recent changes:"));
+                let time_format = app.config.time_format();
+                for (operation, undone) in recent {
+                    let marker = if undone { "  (undone)" } else { "" };
+                    println!(
+                        "  {}  {}{}",
+                        format_local(&operation.time, &time_format),
+                        operation.label,
+                        marker
+                    );
+                }
+                println!();
+            }
+        }
+    }
+
+    Ok(write_back)
+}
+
+// The task a lone id names, if the command was given one
+fn target_id(target: &[String]) -> Option<u32> {
+    match target {
+        [word] => word.parse().ok(),
+        _ => None,
+    }
+}
+
+// The tasks a command acts on: the one its id names, or every task its query
+// words match. Subtasks come before their parents so that completing both
+// together works.
+fn targets(app: &TodoApp, target: &[String]) -> Result<Vec<u32>, CliError> {
+    if let Some(id) = target_id(target) {
+        return match app.tasks.contains_key(&id) {
+            true => Ok(vec![id]),
+            false => Err(not_found(id)),
+        };
+    }
+
+    let filter = target.join(" ");
+    let tasks = app
+        .find_tasks(Some(&filter))
+        .map_err(|e| CliError::Invalid(e.render(&filter)))?;
+    let depth = |task: &Task| {
+        let mut depth = 0;
+        let mut parent = task.parent;
+        while let Some(id) = parent.filter(|_| depth < app.tasks.len()) {
+            depth += 1;
+            parent = app.tasks.get(&id).and_then(|t| t.parent);
+        }
+        depth
+    };
+    let mut tasks: Vec<(usize, u32)> = tasks.iter().map(|t| (depth(t), t.id)).collect();
+    tasks.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    Ok(tasks.into_iter().map(|(_, id)| id).collect())
+}
+
+// A changed task's id and the next occurrence completing it created, if any
+type Changed = (u32, Option<u32>);
+
+// Changes each task in turn, all or nothing: if one change fails, the ones
+// already made are put back. A dry run puts everything back too and shows
+// what would have changed instead. Returns None when nothing was changed.
+fn each(
+    app: &mut TodoApp,
+    ids: &[u32],
+    dry_run: bool,
+    format: Format,
+    now: NaiveDateTime,
+    mut change: impl FnMut(&mut TodoApp, u32) -> Result<Option<u32>, CliError>,
+) -> Result<Option<Vec<Changed>>, CliError> {
+    if ids.is_empty() {
+        match format {
+            Format::Text => println!("No tasks found."),
+            _ => print!("{}", output::tasks(&[], format, now)),
+        }
+        return Ok(None);
+    }
+
+    let before = app.tasks.clone();
+    let (next_id, dirty) = (app.next_id, app.dirty);
+    let result: Result<Vec<_>, CliError> = ids
+        .iter()
+        .map(|&id| change(app, id).map(|next| (id, next)))
+        .collect();
+
+    if result.is_ok() && dry_run {
+        print_preview(&before, app, format, now);
+    }
+    if result.is_err() || dry_run {
+        app.tasks = before;
+        app.next_id = next_id;
+        app.dirty = dirty;
+    }
+    Ok(Some(result?).filter(|_| !dry_run))
+}
+
+// The changed tasks' ids followed by any next occurrences created
+fn with_next(done: &[Changed]) -> Vec<u32> {
+    let ids = done.iter().map(|(id, _)| *id);
+    ids.chain(done.iter().filter_map(|(_, next)| *next))
+        .collect()
+}
+
+// What a dry run changed, before it's put back
+fn print_preview(before: &HashMap<u32, Task>, app: &TodoApp, format: Format, now: NaiveDateTime) {
+    let changes = history::diff(before, &app.tasks);
+    if format != Format::Text {
+        let tasks: Vec<&Task> = changes.iter().filter_map(|c| c.after.as_ref()).collect();
+        print!("{}", output::tasks(&tasks, format, now));
+        return;
+    }
+
+    match changes.len() {
+        0 => say(app, "🔍", "Dry run: nothing would change"),
+        1 => say(app, "🔍", "Dry run: 1 task would change; nothing was saved"),
+        n => say(
+            app,
+            "🔍",
+            &format!("Dry run: {} tasks would change; nothing was saved", n),
+        ),
+    }
+    for change in &changes {
+        let (task, what) = match (&change.before, &change.after) {
+            (Some(old), Some(new)) => {
+                let fields = edit::describe(old, new, &app.renderer);
+                match fields.is_empty() {
+                    true => (new, "updated".to_string()),
+                    false => (new, fields.join(", ")),
+                }
+            }
+            (None, Some(new)) => (new, "new task".to_string()),
+            (Some(old), None) => (old, "removed".to_string()),
+            (None, None) => continue,
+        };
+        println!("  #{} {}: {}", task.id, task.title, what);
+    }
+}
+
+fn update_tags(
+    app: &mut TodoApp,
+    id: u32,
+    markers: &[String],
+    adding: bool,
+    format: Format,
+    now: NaiveDateTime,
+) -> Result<(), CliError> {
+    let (new_tags, project) = parse_markers(markers)?;
+    let found = if adding {
+        app.tag_task(id, &new_tags, project)
+    } else {
+        app.untag_task(id, &new_tags, project.is_some())
+    };
+
+    if !found {
+        return Err(not_found(id));
+    }
+    if format != Format::Text {
+        print_tasks(app, &[id], format, now);
+    } else {
+        say(app, "🏷️", &format!("Updated tags for task #{}", id));
+    }
+    Ok(())
+}
+
+pub fn parse_markers(markers: &[String]) -> Result<(Vec<String>, Option<String>), CliError> {
+    let mut new_tags = Vec::new();
+    let mut project = None;
+    for word in markers {
+        if let Some(tag) = tags::parse_tag(word) {
+            new_tags.push(tag);
+        } else if let Some(name) = tags::parse_project(word) {
+            project = Some(name);
+        } else {
+            return Err(CliError::Invalid(format!(
+                "'{}' is not a #tag or +project",
+                word
+            )));
+        }
+    }
+    Ok((new_tags, project))
+}
+
+fn print_tags(app: &TodoApp, format: Format) {
+    let tag_counts = app.tag_counts();
+    let project_counts = app.project_counts();
+
+    if format != Format::Text {
+        print!("{}", output::tags(&tag_counts, &project_counts, format));
+        return;
+    }
+
+    if tag_counts.is_empty() && project_counts.is_empty() {
+        println!("No tags or projects yet.");
+        return;
+    }
+
+    if !tag_counts.is_empty() {
+        println!("\n{}", app.renderer.prefixed("🏷️", "Tags:"));
+        for (tag, (open, done)) in tag_counts {
+            println!("  #{:<20} {:>3} open {:>3} done", tag, open, done);
+        }
+    }
+    if !project_counts.is_empty() {
+        println!("\n{}", app.renderer.prefixed("📁", "Projects:"));
+        for (project, (open, done)) in project_counts {
+            println!("  +{:<20} {:>3} open {:>3} done", project, open, done);
+        }
+    }
+    println!();
+}
+
+fn print_stats(app: &TodoApp, archived: bool, format: Format, now: NaiveDateTime) {
+    if format != Format::Text {
+        print!("{}", output::stats(app, archived, format, now));
+        return;
+    }
+
+    let (total, completed, pending) = app.get_stats(archived);
+    let in_archive = app.archive.tasks.len();
+    println!("\n{}", app.renderer.prefixed("📊", "Statistics:"));
+    match archived {
+        true => println!(
+            "  Total tasks: {} (including {} archived)",
+            total, in_archive
+        ),
+        false => println!("  Total tasks: {}", total),
+    }
+    let marker = |emoji: &'static str| app.renderer.marker(emoji, "");
+    println!("  Completed: {}{}", completed, marker(" ✅"));
+    println!("  Pending: {}{}", pending, marker(" ⏳"));
+    for status in app.workflow.statuses() {
+        let count = app
+            .counted(archived)
+            .filter(|t| t.status == *status)
+            .count();
+        if !matches!(status, Status::Todo | Status::Done) && count > 0 {
+            let emoji = format!(" {}", status.emoji());
+            println!(
+                "    {}: {}{}",
+                app.workflow.label(status),
+                count,
+                app.renderer.marker(&emoji, "")
+            );
+        }
+    }
+    if total > 0 {
+        let completion_rate = (completed as f64 / total as f64) * 100.0;
+        println!("  Completion rate: {:.1}%", completion_rate);
+    }
+    if !archived && in_archive > 0 {
+        println!(
+            "  Archived: {}{} (not counted; add --archived)",
+            in_archive,
+            marker(" 📦")
+        );
+    }
+    println!();
+}
+
+fn print_archived(
+    app: &TodoApp,
+    tasks: &[&Task],
+    format: Format,
+    now: NaiveDateTime,
+    layout: Layout,
+) {
+    if format != Format::Text {
+        print!("{}", output::tasks(tasks, format, now));
+    } else if tasks.is_empty() {
+        println!("No archived tasks found.");
+    } else {
+        println!("\n{}", app.renderer.prefixed("📦", "Archive:"));
+        println!("{}", app.renderer.rule());
+        for task in tasks {
+            app.print_task(task, 0, now, layout);
+        }
+    }
+}
+
+// A status message, with its emoji when the terminal shows them
+fn say(app: &TodoApp, emoji: &str, text: &str) {
+    println!("{}", app.renderer.prefixed(emoji, text));
+}
+
+// How `move` reports each task it moved, e.g. "Moved task #1 to In progress"
+fn moved(app: &TodoApp, id: u32, status: &Status) -> String {
+    let text = format!("Moved task #{} to {}", id, app.workflow.label(status));
+    app.renderer.prefixed(status.emoji(), &text)
+}
+
+// The recurring task created by completing another, if any
+fn print_next(app: &TodoApp, next: Option<u32>) {
+    if let Some(next_id) = next {
+        if let Some(due) = app.tasks[&next_id].due {
+            say(
+                app,
+                "🔁",
+                &format!("Next occurrence: #{} due {}", next_id, due),
+            );
+        }
+    }
+}
+
+const BOARD_WIDTH: usize = 24;
+const MIN_BOARD_WIDTH: usize = 8;
+
+// Side-by-side columns of "#id title", sharing the terminal width between them
+// with each cell at most BOARD_WIDTH columns wide
+fn print_board(app: &TodoApp, columns: &[(Status, Vec<&Task>)]) {
+    let gaps = 3 * columns.len().saturating_sub(1);
+    let width = (app.renderer.width.saturating_sub(gaps) / columns.len().max(1))
+        .clamp(MIN_BOARD_WIDTH, BOARD_WIDTH);
+    let cell = |text: String| app.renderer.fit(&text, width);
+    let row = |cells: Vec<String>| println!("{}", cells.join(" | ").trim_end());
+
+    println!();
+    row(columns
+        .iter()
+        .map(|(status, tasks)| cell(format!("{} ({})", app.workflow.label(status), tasks.len())))
+        .collect());
+    row(columns.iter().map(|_| "-".repeat(width)).collect());
+    let height = columns
+        .iter()
+        .map(|(_, tasks)| tasks.len())
+        .max()
+        .unwrap_or(0);
+    for i in 0..height {
+        row(columns
+            .iter()
+            .map(|(_, tasks)| match tasks.get(i) {
+                Some(task) => cell(format!("#{} {}", task.id, task.title)),
+                None => cell(String::new()),
+            })
+            .collect());
+    }
+    println!();
+}
+
+fn print_stopped(app: &TodoApp, id: u32) {
+    let task = &app.tasks[&id];
+    let now = Utc::now();
+    let session = task
+        .sessions
+        .last()
+        .map_or(0, |session| session.seconds(now));
+    say(
+        app,
+        "⏹️",
+        &format!(
+            "Stopped timing task #{} after {} ({} in total)",
+            id,
+            tracking::format_duration(session),
+            tracking::format_duration(tracking::total_seconds(&task.sessions, now))
+        ),
+    );
+}
+
+fn print_report(
+    app: &TodoApp,
+    kind: reports::Kind,
+    since: Option<NaiveDate>,
+    by: Period,
+    format: Format,
+    today: NaiveDate,
+) {
+    let day = |date: NaiveDate| date.format("%a %Y-%m-%d").to_string();
+    // Reports that need a first day get one from default_since
+    let first = since.unwrap_or(today);
+    let text = format == Format::Text;
+    let tasks = app.reported();
+
+    match kind {
+        reports::Kind::Time => {
+            let report = tracking::report(&tasks, first, Utc::now());
+            if !text {
+                print!("{}", output::time_report(&report, format));
+            } else {
+                print_time_report(app, &report);
+            }
+        }
+        reports::Kind::Activity => {
+            let periods = reports::activity(&tasks, first, today, by);
+            if !text {
+                print!("{}", output::activity(&periods, by, format));
+                return;
+            }
+            println!(
+                "\n{}",
+                app.renderer.prefixed(
+                    "📈",
+                    &format!(
+                        "Tasks created and completed per {} since {}:",
+                        by.name(),
+                        day(first)
+                    )
+                )
+            );
+            println!("{}", app.renderer.rule());
+            println!("  {:<16} {:>8} {:>10}", "", "Created", "Completed");
+            for period in &periods {
+                println!(
+                    "  {:<16} {:>8} {:>10}",
+                    day(period.start),
+                    period.created,
+                    period.completed
+                );
+            }
+            let created: usize = periods.iter().map(|p| p.created).sum();
+            let completed: usize = periods.iter().map(|p| p.completed).sum();
+            println!("  {:<16} {:>8} {:>10}", "Total", created, completed);
+            println!();
+        }
+        reports::Kind::Completion => {
+            let completion = reports::completion(&tasks, since, &app.priority_levels());
+            if !text {
+                print!("{}", output::completion(&completion, since, format));
+                return;
+            }
+            let scope = since.map_or("all time".to_string(), |date| {
+                format!("since {}", day(date))
+            });
+            println!(
+                "\n{}",
+                app.renderer.prefixed(
+                    "⏲️",
+                    &format!("Average time from creation to completion ({}):", scope)
+                )
+            );
+            println!("{}", app.renderer.rule());
+            let rows = completion
+                .by_priority
+                .iter()
+                .map(|(priority, average)| (priority.to_string(), average))
+                .chain([("All", &completion.all)]);
+            for (name, average) in rows {
+                let time = average
+                    .seconds()
+                    .map_or("-".to_string(), reports::format_span);
+                println!("  {:<8} {:>4} done {:>10}", name, average.count, time);
+            }
+            println!();
+        }
+        reports::Kind::Streak => {
+            let streak = reports::streak(&tasks, today);
+            if !text {
+                print!("{}", output::streak(&streak, format));
+                return;
+            }
+            let days = |n: usize| {
+                if n == 1 {
+                    "1 day".to_string()
+                } else {
+                    format!("{} days", n)
+                }
+            };
+            println!(
+                "\n{}",
+                app.renderer
+                    .prefixed("🔥", &format!("Current streak: {}", days(streak.current)))
+            );
+            println!("   Longest streak: {}", days(streak.longest));
+            match streak.last {
+                Some(last) if last == today => println!("   Last completion: today"),
+                Some(last) => println!("   Last completion: {}", day(last)),
+                None => println!("   Nothing completed yet"),
+            }
+            println!();
+        }
+        reports::Kind::Burndown => {
+            let days = reports::burndown(&tasks, first, today);
+            if !text {
+                print!("{}", output::burndown(&days, format));
+                return;
+            }
+            let open = |i: usize| days.get(i).map_or(0, |(_, open)| *open);
+            println!(
+                "\n{}",
+                app.renderer.prefixed(
+                    "📉",
+                    &format!(
+                        "Open tasks from {} to {}: {} {} {}",
+                        day(first),
+                        day(today),
+                        open(0),
+                        app.renderer.marker("→", "->"),
+                        open(days.len().saturating_sub(1))
+                    )
+                )
+            );
+            println!("{}", app.renderer.rule());
+            print!("{}", reports::chart(&days));
+            println!();
+        }
+    }
+}
+
+fn print_time_report(app: &TodoApp, report: &Report) {
+    let since = report.since.format("%a %Y-%m-%d");
+    if report.tasks.is_empty() {
+        println!("No time tracked since {}.", since);
+        return;
+    }
+
+    println!(
+        "\n{}",
+        app.renderer.prefixed(
+            "⏱️",
+            &format!(
+                "Time tracked since {}: {}",
+                since,
+                tracking::format_duration(report.total)
+            )
+        )
+    );
+    println!("{}", app.renderer.rule());
+    println!("By task:");
+    for (task, seconds) in &report.tasks {
+        let id = format!("#{}", task.id);
+        let time = tracking::format_duration(*seconds);
+        println!("  {:<6} {:>8}  {}", id, time, task.title);
+    }
+    println!("\nBy tag:");
+    for (tag, seconds) in &report.tags {
+        let tag = tag.map_or("untagged".to_string(), |tag| format!("#{}", tag));
+        println!("  {:<20} {:>8}", tag, tracking::format_duration(*seconds));
+    }
+    println!("\nBy day:");
+    for (date, seconds) in &report.days {
+        let day = date.format("%a %Y-%m-%d").to_string();
+        println!("  {:<20} {:>8}", day, tracking::format_duration(*seconds));
+    }
+    println!();
+}
+
+fn print_tasks(app: &TodoApp, ids: &[u32], format: Format, now: NaiveDateTime) {
+    let tasks: Vec<&Task> = ids.iter().map(|id| &app.tasks[id]).collect();
+    print!("{}", output::tasks(&tasks, format, now));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_app;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from([&["todo"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_add() {
+        let cli = parse(&[
+            "add",
+            "Ship",
+            "release",
+            "--priority",
+            "high",
+            "--due",
+            "fri",
+        ]);
+        assert_eq!(
+            cli.command,
+            Some(Command::Add {
+                title: vec!["Ship".to_string(), "release".to_string()],
+                priority: Some(Priority::High),
+                due: Some("fri".to_string()),
+                repeat: None,
+                parent: None,
+            })
+        );
+        assert_eq!(cli.file, None);
+        assert_eq!(cli.output_format(), Format::Text);
+    }
+
+    #[test]
+    fn test_parse_global_flags() {
+        let cli = parse(&[
+            "list",
+            "--filter",
+            "pending",
+            "--json",
+            "--file",
+            "/tmp/x.txt",
+        ]);
+        assert_eq!(cli.output_format(), Format::Json);
+        assert_eq!(cli.file.as_deref(), Some("/tmp/x.txt"));
+        assert!(matches!(
+            cli.command,
+            Some(Command::List {
+                filter: Some(_),
+                ..
+            })
+        ));
+
+        let cli = parse(&["stats", "--format", "csv"]);
+        assert_eq!(cli.output_format(), Format::Csv);
+        assert!(parse(&[]).command.is_none());
+        assert!(Cli::try_parse_from(["todo", "add", "x", "-p", "critical"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "remove", "abc"]).is_err());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut app = temp_app("cli-exit-codes");
+        let add = parse(&["add", "Write", "docs"]).command.unwrap();
+        execute(&mut app, add, Format::Text).unwrap();
+        assert!(!app.dirty);
+
+        let untitled = parse(&["add", " ", "\t"]).command.unwrap();
+        let errors = [
+            (Command::Remove { id: 99 }, EXIT_NOT_FOUND),
+            (untitled, EXIT_INVALID),
+            (
+                Command::Due {
+                    id: 1,
+                    when: vec!["someday".to_string()],
+                },
+                EXIT_INVALID,
+            ),
+            (Command::Block { id: 1, blocker: 1 }, EXIT_INVALID),
+            (
+                Command::List {
+                    filter: Some("(".to_string()),
+                    query: vec![],
+                    archived: false,
+                    compact: false,
+                },
+                EXIT_INVALID,
+            ),
+            (
+                Command::Tag {
+                    id: 1,
+                    markers: vec!["work".to_string()],
+                },
+                EXIT_INVALID,
+            ),
+        ];
+        for (command, code) in errors {
+            assert_eq!(
+                execute(&mut app, command, Format::Text)
+                    .unwrap_err()
+                    .exit_code(),
+                code
+            );
+        }
+
+        let _ = std::fs::remove_file(app.file_path());
+        let missing = std::env::temp_dir()
+            .join("todo-missing-dir")
+            .join("todos.txt");
+        app.store = store::open(&missing.to_string_lossy());
+        let add = parse(&["add", "Lost"]).command.unwrap();
+        let error = execute(&mut app, add, Format::Text);
+        assert_eq!(error.unwrap_err().exit_code(), EXIT_IO);
+    }
+
+    #[test]
+    fn test_bulk_changes_and_dry_run() {
+        let mut app = temp_app("cli-bulk");
+        for title in ["Plan #sprint12", "Fix login #sprint12", "Write notes"] {
+            let add = parse(&["add", title]).command.unwrap();
+            execute(&mut app, add, Format::Text).unwrap();
+        }
+
+        let edit = parse(&[
+            "edit",
+            "-p",
+            "high",
+            "--tag",
+            "+web",
+            "-n",
+            "--",
+            "#sprint12",
+        ]);
+        execute(&mut app, edit.command.unwrap(), Format::Text).unwrap();
+        assert!(app.tasks.values().all(|t| t.priority == Priority::Medium));
+        assert_eq!(app.history.recent(10).len(), 3);
+
+        let toggle = parse(&["toggle", "status:pending", "#sprint12"])
+            .command
+            .unwrap();
+        execute(&mut app, toggle, Format::Text).unwrap();
+        assert!(app.tasks[&1].is_done() && app.tasks[&2].is_done());
+        assert!(!app.tasks[&3].is_done());
+        assert_eq!(
+            app.history.recent(1)[0].0.label,
+            "toggle tasks matching status:pending #sprint12"
+        );
+
+        // One task can't move, so neither does
+        let reopen = parse(&["toggle", "1"]).command.unwrap();
+        execute(&mut app, reopen, Format::Text).unwrap();
+        let move_all = parse(&["move", "#sprint12", "in-progress"])
+            .command
+            .unwrap();
+        assert!(execute(&mut app, move_all, Format::Text).is_err());
+        assert_eq!(app.tasks[&1].status, Status::Todo);
+
+        let edit = parse(&["edit", "3", "--title", "Write the notes", "-d", "none"]);
+        execute(&mut app, edit.command.unwrap(), Format::Text).unwrap();
+        assert_eq!(app.tasks[&3].title, "Write the notes");
+        let nothing = parse(&["edit", "3"]).command.unwrap();
+        assert!(execute(&mut app, nothing, Format::Text).is_err());
+    }
+
+    #[test]
+    fn test_markdown_ids_follow_the_saved_tasks() {
+        let mut app = temp_app("cli-import-race");
+        let notes = format!("{}.md", app.file_path());
+        fs::write(&notes, "- [ ] Buy milk\n").unwrap();
+
+        // Another process adds a task after this one loaded but before it saves
+        let mut other = TodoApp::new(app.file_path().to_string()).unwrap();
+        other.add_task("Call mum".to_string(), Priority::Medium, None, None);
+        other.save_to_file().unwrap();
+        app.dirty = true;
+
+        let import = parse(&["import", "markdown", &notes]).command.unwrap();
+        execute(&mut app, import, Format::Text).unwrap();
+        assert_eq!(app.tasks[&1].title, "Call mum");
+        assert_eq!(app.tasks[&2].title, "Buy milk");
+        let written = fs::read_to_string(&notes).unwrap();
+        assert_eq!(written, "- [ ] Buy milk <!-- todo:2 -->\n");
+        let _ = fs::remove_file(notes);
+    }
+
+    #[test]
+    fn test_moved_message_follows_the_style() {
+        let mut app = temp_app("cli-moved");
+        assert_eq!(
+            moved(&app, 1, &Status::InProgress),
+            "🚧 Moved task #1 to In progress"
+        );
+        app.renderer.emoji = false;
+        app.workflow
+            .set_labels(&[(Status::InProgress, "Doing".to_string())]);
+        assert_eq!(
+            moved(&app, 1, &Status::InProgress),
+            "Moved task #1 to Doing"
+        );
+    }
+}
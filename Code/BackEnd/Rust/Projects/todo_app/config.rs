@@ -0,0 +1,368 @@
+// Settings from the config file
+//
+// The config file is TOML, read from $TODO_CONFIG when that's set, otherwise
+// from $XDG_CONFIG_HOME/todo/config.toml or ~/.config/todo/config.toml. There
+// doesn't have to be one, and every setting is optional:
+//
+//   file = "~/notes/todos.txt"       todo file, as for --file; a relative
+//                                    path is relative to the config file
+//   default_priority = "medium"      priority of new tasks
+//   priorities = ["urgent", "high", "medium", "low", "someday"]
+//                                    which of the five built-in levels tasks
+//                                    can be given; high, medium and low when
+//                                    not set
+//   date_format = "%d.%m.%Y"         how dates are shown, in strftime syntax;
+//                                    timestamps add the time of day
+//   style = "plain"                  how task listings mark status, priority
+//                                    and fields: auto (whatever the terminal
+//                                    supports), emoji, plain (ASCII) or color
+//                                    (ASCII with ANSI colors); see render.rs
+//   layout = "compact"               task listings with details on lines of
+//                                    their own (full) or one line per task
+//   workflow = "todo>review; review>done,todo; done>todo"
+//                                    status workflow, as for --workflow; it
+//                                    can name statuses of its own
+//
+//   [statuses]
+//   todo = "Backlog"                 labels the board and listings show for
+//   review = "In review"             statuses, built in or the workflow's
+//
+//   [list]
+//   filter = "pending"               query `list` runs when given none
+//   sort = "due,-priority"           sort keys for a `list` query without
+//                                    a sort: of its own
+//
+// --file and --workflow (or TODO_WORKFLOW) take precedence over the file.
+//
+// There are exactly five priority levels, urgent, high, medium, low and
+// someday, in that order. Their names, emoji and colors are built in, as is
+// how they map to todo.txt letters and iCalendar priorities; the config
+// can't define levels of its own, only pick which of the five are in use.
+// A level that isn't can't be given to a task, but tasks that already have
+// it, say from an import or an older config, keep it.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::Local;
+use clap::ValueEnum;
+use toml::{Table, Value};
+
+use crate::query::Query;
+use crate::render::{Layout, Style};
+use crate::workflow::{Status, Workflow};
+use crate::{parse_priority, Priority};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub file: Option<String>,
+    pub default_priority: Priority,
+    // Most important first
+    pub priorities: Vec<Priority>,
+    pub date_format: String,
+    pub style: Style,
+    pub layout: Layout,
+    pub workflow: Option<Workflow>,
+    pub status_labels: Vec<(Status, String)>,
+    pub list_filter: Option<String>,
+    pub list_sort: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            file: None,
+            default_priority: Priority::Medium,
+            priorities: vec![Priority::High, Priority::Medium, Priority::Low],
+            date_format: "%Y-%m-%d".to_string(),
+            style: Style::default(),
+            layout: Layout::default(),
+            workflow: None,
+            status_labels: Vec::new(),
+            list_filter: None,
+            list_sort: None,
+        }
+    }
+}
+
+// Where the config file is, or would be
+pub fn location() -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    if let Some(path) = var("TODO_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("todo").join("config.toml"))
+}
+
+// The settings from the config file; the defaults when there isn't one,
+// unless TODO_CONFIG names a file that's missing
+pub fn load() -> Result<Config, String> {
+    let Some(path) = location() else {
+        return Ok(Config::default());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound && env::var_os("TODO_CONFIG").is_none() => {
+            return Ok(Config::default());
+        }
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse(&text, dir).map_err(|e| format!("Error in {}: {}", path.display(), e))
+}
+
+pub fn parse(text: &str, dir: &Path) -> Result<Config, String> {
+    let table: Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| e.message().to_string())?;
+    let mut config = Config::default();
+    for (key, value) in &table {
+        match key.as_str() {
+            "file" => config.file = Some(resolve(string(key, value)?, dir)),
+            "default_priority" => config.default_priority = priority(key, value)?,
+            "priorities" => {
+                let Value::Array(levels) = value else {
+                    return Err(format!("{} should be a list of priorities", key));
+                };
+                let mut enabled = Vec::new();
+                for level in levels {
+                    enabled.push(priority(key, level)?);
+                }
+                config.priorities = Priority::ALL
+                    .into_iter()
+                    .filter(|p| enabled.contains(p))
+                    .collect();
+                if config.priorities.is_empty() {
+                    return Err(format!("{} can't be empty", key));
+                }
+            }
+            "date_format" => {
+                let format = string(key, value)?;
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    return Err(format!("invalid {} '{}'", key, format));
+                }
+                config.date_format = format.to_string();
+            }
+            "style" => {
+                config.style = match string(key, value)? {
+                    "auto" => Style::Auto,
+                    "emoji" => Style::Emoji,
+                    "plain" | "ascii" => Style::Plain,
+                    "color" | "colour" => Style::Color,
+                    other => {
+                        return Err(format!(
+                            "unknown {} '{}' (expected auto, emoji, plain or color)",
+                            key, other
+                        ))
+                    }
+                }
+            }
+            "layout" => {
+                let layout = Layout::from_str(string(key, value)?, true);
+                config.layout =
+                    layout.map_err(|_| format!("unknown {} (expected full or compact)", key))?;
+            }
+            "workflow" => {
+                let workflow = Workflow::parse(string(key, value)?);
+                config.workflow = Some(workflow.map_err(|e| format!("{}: {}", key, e))?);
+            }
+            "statuses" => {
+                let Value::Table(statuses) = value else {
+                    return Err(format!("{} should be a table", key));
+                };
+                for (name, label) in statuses {
+                    let status = Status::parse(name).map_err(|e| format!("{}: {}", key, e))?;
+                    let label = string(&format!("{}.{}", key, name), label)?;
+                    config.status_labels.push((status, label.to_string()));
+                }
+            }
+            "list" => {
+                let Value::Table(list) = value else {
+                    return Err(format!("{} should be a table", key));
+                };
+                parse_list(list, &mut config)?;
+            }
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+    }
+    if !config.priorities.contains(&config.default_priority) {
+        return Err(format!(
+            "default_priority {} isn't one of the priorities",
+            config.default_priority.to_string().to_lowercase()
+        ));
+    }
+    Ok(config)
+}
+
+fn parse_list(list: &Table, config: &mut Config) -> Result<(), String> {
+    let today = Local::now().date_naive();
+    for (key, value) in list {
+        let query = string(key, value)?;
+        match key.as_str() {
+            "filter" => {
+                Query::parse(query, today).map_err(|e| format!("list.{}: {}", key, e.message))?;
+                config.list_filter = Some(query.to_string());
+            }
+            "sort" => {
+                Query::parse(&format!("sort:{}", query), today)
+                    .map_err(|e| format!("list.{}: {}", key, e.message))?;
+                config.list_sort = Some(query.to_string());
+            }
+            _ => return Err(format!("unknown setting 'list.{}'", key)),
+        }
+    }
+    Ok(())
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{} should be a string", key))
+}
+
+fn priority(key: &str, value: &Value) -> Result<Priority, String> {
+    parse_priority(string(key, value)?).map_err(|e| format!("{}: {}", key, e))
+}
+
+// Expands a leading ~ and makes a relative path relative to the config file
+fn resolve(path: &str, dir: &Path) -> String {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let path = match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => dir.join(path),
+    };
+    path.to_string_lossy().into_owned()
+}
+
+impl Config {
+    // "high, medium or low"
+    pub fn priority_names(&self) -> String {
+        let names: Vec<String> = self
+            .priorities
+            .iter()
+            .map(|p| p.to_string().to_lowercase())
+            .collect();
+        match names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+            _ => names.join(""),
+        }
+    }
+
+    pub fn check_priority(&self, priority: &Priority) -> Result<(), String> {
+        if self.priorities.contains(priority) {
+            return Ok(());
+        }
+        Err(format!(
+            "{} priority isn't enabled; the config allows {}",
+            priority.to_string(),
+            self.priority_names()
+        ))
+    }
+
+    // The query `list` runs: the configured filter when none is given, and
+    // the configured sort unless the query sorts itself
+    pub fn list_query(&self, query: Option<&str>) -> Option<String> {
+        let query = query.or(self.list_filter.as_deref());
+        let Some(sort) = &self.list_sort else {
+            return query.map(str::to_string);
+        };
+        let today = Local::now().date_naive();
+        match query {
+            Some(query) if Query::parse(query, today).is_ok_and(|q| q.has_sort()) => {
+                Some(query.to_string())
+            }
+            Some(query) => Some(format!("{} sort:{}", query, sort)),
+            None => Some(format!("sort:{}", sort)),
+        }
+    }
+
+    // A timestamp's local time in the date format
+    pub fn time_format(&self) -> String {
+        format!("{} %H:%M:%S", self.date_format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let text = r#"
+            file = "tasks.db"
+            default_priority = "low"
+            priorities = ["someday", "low", "high", "urgent"]
+            date_format = "%d.%m.%Y"
+            style = "color"
+            layout = "compact"
+
+            [statuses]
+            todo = "Backlog"
+
+            [list]
+            filter = "pending"
+            sort = "due,-priority"
+        "#;
+        let config = parse(text, Path::new("/home/me/.config/todo")).unwrap();
+        assert_eq!(
+            config.file.as_deref(),
+            Some("/home/me/.config/todo/tasks.db")
+        );
+        assert_eq!(config.default_priority, Priority::Low);
+        assert_eq!(
+            config.priorities,
+            [
+                Priority::Urgent,
+                Priority::High,
+                Priority::Low,
+                Priority::Someday
+            ]
+        );
+        assert_eq!(config.priority_names(), "urgent, high, low or someday");
+        assert!(config.check_priority(&Priority::Medium).is_err());
+        assert_eq!(config.time_format(), "%d.%m.%Y %H:%M:%S");
+        assert_eq!(config.style, Style::Color);
+        assert_eq!(config.layout, Layout::Compact);
+        assert_eq!(
+            config.status_labels,
+            [(Status::Todo, "Backlog".to_string())]
+        );
+
+        assert_eq!(
+            config.list_query(None).as_deref(),
+            Some("pending sort:due,-priority")
+        );
+        assert_eq!(
+            config.list_query(Some("#work sort:id")).as_deref(),
+            Some("#work sort:id")
+        );
+
+        let defaults = parse("", Path::new("")).unwrap();
+        assert_eq!(defaults.priority_names(), "high, medium or low");
+        assert_eq!(defaults.list_query(None), None);
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let dir = Path::new("");
+        let error = |text: &str| parse(text, dir).unwrap_err();
+        assert_eq!(error("colour = \"red\""), "unknown setting 'colour'");
+        assert!(error("style = \"fancy\"").contains("expected auto, emoji, plain or color"));
+        assert!(error("layout = \"grid\"").contains("expected full or compact"));
+        assert!(error("default_priority = \"urgent\"").contains("isn't one of the priorities"));
+        assert!(error("priorities = []").contains("can't be empty"));
+        assert!(error("priorities = [\"critical\"]").contains("unknown priority 'critical'"));
+        assert!(error("date_format = \"%Q\"").contains("invalid date_format"));
+        assert!(error("[list]\nsort = \"size\"").contains("unknown sort key 'size'"));
+        assert!(error("file = 3").contains("file should be a string"));
+        assert!(error("[statuses]\n\"on hold\" = \"x\"").contains("invalid status 'on hold'"));
+        assert!(error("[statuses]\ntodo = 1").contains("statuses.todo should be a string"));
+        assert!(!error("file = ").is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+// Due dates for tasks
+//
+// A due date is a calendar day with an optional time, both in the user's
+// local time zone, since "due Friday at 5pm" means the user's Friday.
+
+use std::fmt;
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Due {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+impl Due {
+    // Accepts an ISO date or a relative form ("today", "tomorrow", "+3d",
+    // "+2w", "+1m", "-7d", "fri", "next fri"), optionally followed by a
+    // time ("17:00", "5pm", "at 5:30pm"). "fri" is the first Friday after
+    // today, "next fri" the Friday of next week.
+    pub fn parse(input: &str, today: NaiveDate) -> Result<Due, String> {
+        let input = input.trim().to_lowercase();
+        let mut words: Vec<&str> = input.split_whitespace().collect();
+        if words.is_empty() {
+            return Err("missing due date".to_string());
+        }
+
+        let mut time = None;
+        if words.len() > 1 {
+            if let Some(t) = parse_time(words[words.len() - 1]) {
+                time = Some(t);
+                words.pop();
+                if words.last() == Some(&"at") {
+                    words.pop();
+                }
+            }
+        }
+
+        let date = match words.as_slice() {
+            [word] => parse_date_word(word, today),
+            ["next", day] => parse_weekday(day).and_then(|wd| in_next_week(today, wd)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("can't understand due date '{}'", input))?;
+
+        Ok(Due { date, time })
+    }
+
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        match self.time {
+            Some(time) => self.date.and_time(time) < now,
+            None => self.date < now.date(),
+        }
+    }
+
+    pub fn is_today(&self, today: NaiveDate) -> bool {
+        self.date == today
+    }
+
+    // True when the due date falls in the Monday-to-Sunday week containing today
+    pub fn is_this_week(&self, today: NaiveDate) -> bool {
+        self.date.iso_week() == today.iso_week()
+    }
+
+    // The date in the given format, the time, and how far away it is
+    pub fn describe(&self, today: NaiveDate, date_format: &str) -> String {
+        let days = (self.date - today).num_days();
+        let relative = match days {
+            0 => "today".to_string(),
+            1 => "tomorrow".to_string(),
+            -1 => "yesterday".to_string(),
+            d if d > 1 => format!("in {} days", d),
+            d => format!("{} days ago", -d),
+        };
+        let mut shown = self.date.format(date_format).to_string();
+        if let Some(time) = self.time {
+            shown += &time.format(" %H:%M").to_string();
+        }
+        format!("{} ({})", shown, relative)
+    }
+
+    pub fn to_file_format(self) -> String {
+        self.to_string()
+    }
+
+    pub fn from_file_format(s: &str) -> Result<Due, String> {
+        let invalid = || format!("invalid due date '{}'", s);
+        let (date, time) = match s.split_once(' ') {
+            Some((date, time)) => (
+                date,
+                Some(NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| invalid())?),
+            ),
+            None => (s, None),
+        };
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())?;
+        Ok(Due { date, time })
+    }
+}
+
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+        if let Some(time) = self.time {
+            write!(f, " {}", time.format("%H:%M"))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_date_word(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match word {
+        "today" | "tod" => return Some(today),
+        "tomorrow" | "tmr" | "tom" => return today.checked_add_days(Days::new(1)),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        _ => {}
+    }
+
+    let signed = word
+        .strip_prefix('+')
+        .map(|offset| (offset, true))
+        .or_else(|| word.strip_prefix('-').map(|offset| (offset, false)));
+    if let Some((offset, forward)) = signed {
+        let unit = offset.chars().last()?;
+        let amount: u32 = offset[..offset.len() - unit.len_utf8()].parse().ok()?;
+        let days = match unit {
+            'd' => Days::new(amount.into()),
+            'w' => Days::new(u64::from(amount) * 7),
+            'm' if forward => return today.checked_add_months(Months::new(amount)),
+            'm' => return today.checked_sub_months(Months::new(amount)),
+            _ => return None,
+        };
+        return if forward {
+            today.checked_add_days(days)
+        } else {
+            today.checked_sub_days(days)
+        };
+    }
+
+    if let Some(weekday) = parse_weekday(word) {
+        return Some(next_weekday(today, weekday));
+    }
+
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+}
+
+pub fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+// The first given weekday strictly after today
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    today + Days::new(ahead.into())
+}
+
+// The given weekday in the Monday-to-Sunday week after today's
+fn in_next_week(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let ahead = 7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday();
+    today.checked_add_days(Days::new(ahead.into()))
+}
+
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    if let Ok(time) = NaiveTime::parse_from_str(word, "%H:%M") {
+        return Some(time);
+    }
+
+    let (clock, pm) = if let Some(clock) = word.strip_suffix("pm") {
+        (clock, true)
+    } else {
+        (word.strip_suffix("am")?, false)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    let hour = match (hour, pm) {
+        (12, false) => 0,
+        (12, true) => 12,
+        (h, true) => h + 12,
+        (h, false) => h,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        assert_eq!(Due::parse("today", today()).unwrap().date, today());
+        assert_eq!(
+            Due::parse("tomorrow", today()).unwrap().date,
+            date(2026, 10, 15)
+        );
+        assert_eq!(Due::parse("+3d", today()).unwrap().date, date(2026, 10, 17));
+        assert_eq!(Due::parse("+2w", today()).unwrap().date, date(2026, 10, 28));
+        assert_eq!(Due::parse("+1m", today()).unwrap().date, date(2026, 11, 14));
+        assert_eq!(Due::parse("-1w", today()).unwrap().date, date(2026, 10, 7));
+    }
+
+    #[test]
+    fn test_weekdays() {
+        assert_eq!(Due::parse("fri", today()).unwrap().date, date(2026, 10, 16));
+        assert_eq!(
+            Due::parse("next fri", today()).unwrap().date,
+            date(2026, 10, 23)
+        );
+        assert_eq!(
+            Due::parse("next mon", today()).unwrap().date,
+            date(2026, 10, 19)
+        );
+        assert_eq!(
+            Due::parse("Wednesday", today()).unwrap().date,
+            date(2026, 10, 21)
+        );
+    }
+
+    #[test]
+    fn test_times() {
+        let due = Due::parse("tomorrow 5pm", today()).unwrap();
+        assert_eq!(due.time, NaiveTime::from_hms_opt(17, 0, 0));
+
+        let due = Due::parse("2026-12-01 at 09:30", today()).unwrap();
+        assert_eq!(due.date, date(2026, 12, 1));
+        assert_eq!(due.time, NaiveTime::from_hms_opt(9, 30, 0));
+
+        let due = Due::parse("fri 12am", today()).unwrap();
+        assert_eq!(due.time, NaiveTime::from_hms_opt(0, 0, 0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Due::parse("someday", today()).is_err());
+        assert!(Due::parse("+3x", today()).is_err());
+        assert!(Due::parse("+3é", today()).is_err());
+        assert!(Due::parse("tomorrow 13pm", today()).is_err());
+    }
+
+    #[test]
+    fn test_overdue() {
+        let now = today().and_hms_opt(12, 0, 0).unwrap();
+        let morning = Due::parse("today 9:00", today()).unwrap();
+        let all_day = Due::parse("today", today()).unwrap();
+        let yesterday = Due::parse("yesterday", today()).unwrap();
+
+        assert!(morning.is_overdue(now));
+        assert!(!all_day.is_overdue(now));
+        assert!(yesterday.is_overdue(now));
+    }
+
+    #[test]
+    fn test_file_format_round_trip() {
+        for input in ["2026-10-20", "2026-10-20 17:45"] {
+            let due = Due::from_file_format(input).unwrap();
+            assert_eq!(due.to_file_format(), input);
+        }
+        assert!(Due::from_file_format("2026-13-01").is_err());
+    }
+}
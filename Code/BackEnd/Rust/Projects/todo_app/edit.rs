@@ -0,0 +1,600 @@
+// Changing a task's fields
+//
+// `todo edit ID` changes the fields given as options, e.g. `--title`,
+// `--priority high` or `--due none`. With --editor it instead writes the task
+// to a temporary file as a block of text and opens $VISUAL or $EDITOR on it:
+//
+//   Title: Ship release
+//   Status: in-progress
+//   Priority: high
+//   Due: 2026-10-20 17:00
+//   Repeat: none
+//   Tags: #work #urgent
+//   Project: +website
+//   Parent: none
+//   Blocked by: #4
+//
+//   The description, over as many lines as it needs.
+//
+// Fields left out keep their value, and "none" or an empty value clears one.
+// Only the fields that were changed are saved, and a status change follows
+// the workflow as `move` does.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::NaiveDate;
+use clap::Args;
+
+use crate::cli::{self, CliError};
+use crate::due::Due;
+use crate::recurrence::Recurrence;
+use crate::render::Renderer;
+use crate::tags;
+use crate::workflow::Status;
+use crate::{format_ids, parse_priority, Priority, Task, TaskError, TodoApp};
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct Fields {
+    /// New title; #tag and +project words in it add tags and set the project
+    #[arg(long)]
+    pub title: Option<String>,
+    /// high, medium or low, or another level the config enables
+    #[arg(short, long, value_parser = parse_priority)]
+    pub priority: Option<Priority>,
+    /// New description; an empty one clears it
+    #[arg(long = "desc", value_name = "TEXT")]
+    pub description: Option<String>,
+    /// Due date, or "none"
+    #[arg(short, long, value_name = "WHEN")]
+    pub due: Option<String>,
+    /// Repeat rule, or "none"
+    #[arg(short, long, value_name = "RULE")]
+    pub repeat: Option<String>,
+    /// Parent task, or "none"
+    #[arg(long, value_name = "ID")]
+    pub parent: Option<String>,
+    /// Status to move to, following the workflow
+    #[arg(short, long, value_parser = Status::parse)]
+    pub status: Option<Status>,
+    /// Add a #tag or set the +project; can be repeated
+    #[arg(long = "tag", value_name = "MARKER")]
+    pub tag: Vec<String>,
+    /// Remove a #tag or clear the +project; can be repeated
+    #[arg(long = "untag", value_name = "MARKER")]
+    pub untag: Vec<String>,
+    /// Make the task wait on another; can be repeated
+    #[arg(long, value_name = "ID")]
+    pub block: Vec<u32>,
+    /// Stop the task waiting on another; can be repeated
+    #[arg(long, value_name = "ID")]
+    pub unblock: Vec<u32>,
+}
+
+impl Fields {
+    pub fn is_empty(&self) -> bool {
+        *self == Fields::default()
+    }
+}
+
+// "none" or nothing at all
+fn is_none(value: &str) -> bool {
+    value.trim().is_empty() || value.trim().eq_ignore_ascii_case("none")
+}
+
+fn parse_due(when: &str, today: NaiveDate) -> Result<Option<Due>, String> {
+    match is_none(when) {
+        true => Ok(None),
+        false => Due::parse(when, today).map(Some),
+    }
+}
+
+fn parse_repeat(rule: &str, anchor: NaiveDate) -> Result<Option<Recurrence>, String> {
+    match is_none(rule) {
+        true => Ok(None),
+        false => Recurrence::parse(rule, anchor).map(Some),
+    }
+}
+
+fn parse_parent(parent: &str) -> Result<Option<u32>, String> {
+    if is_none(parent) {
+        return Ok(None);
+    }
+    let id = parent.trim();
+    id.strip_prefix('#')
+        .unwrap_or(id)
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("Invalid task ID '{}'", id))
+}
+
+// Changes the given fields of a task, leaving out ones that already have the
+// new value. Returns the next occurrence created by completing it, if any.
+pub fn apply(
+    app: &mut TodoApp,
+    id: u32,
+    fields: &Fields,
+    force: bool,
+    today: NaiveDate,
+) -> Result<Option<u32>, CliError> {
+    let task = app.tasks.get(&id).ok_or(TaskError::NotFound(id))?.clone();
+    if let Some(priority) = &fields.priority {
+        app.config
+            .check_priority(priority)
+            .map_err(CliError::Invalid)?;
+    }
+
+    if let Some(title) = &fields.title {
+        if tags::extract(title).title.trim().is_empty() {
+            return Err(CliError::Invalid("A task needs a title".to_string()));
+        }
+        if *title != task.title {
+            app.rename_task(id, title);
+        }
+    }
+    if let Some(priority) = fields.priority.clone().filter(|p| *p != task.priority) {
+        app.set_priority(id, priority);
+    }
+    if let Some(description) = &fields.description {
+        if task.description.as_deref().unwrap_or("") != description.trim() {
+            app.update_description(id, description.trim().to_string());
+        }
+    }
+    if let Some(when) = &fields.due {
+        let due = parse_due(when, today).map_err(CliError::Invalid)?;
+        if due != task.due {
+            app.set_due(id, due);
+        }
+    }
+    if let Some(rule) = &fields.repeat {
+        let anchor = app.tasks[&id].due.map_or(today, |d| d.date);
+        let recurrence = parse_repeat(rule, anchor).map_err(CliError::Invalid)?;
+        if recurrence != task.recurrence {
+            app.set_recurrence(id, recurrence);
+        }
+    }
+    if let Some(parent) = &fields.parent {
+        let parent = parse_parent(parent).map_err(CliError::Invalid)?;
+        if parent != task.parent {
+            app.set_parent(id, parent)?;
+        }
+    }
+    if !fields.tag.is_empty() {
+        let (new_tags, project) = cli::parse_markers(&fields.tag)?;
+        let current = &app.tasks[&id];
+        let new = new_tags.iter().any(|tag| !current.tags.contains(tag));
+        if new
+            || project
+                .as_ref()
+                .is_some_and(|p| current.project.as_ref() != Some(p))
+        {
+            app.tag_task(id, &new_tags, project);
+        }
+    }
+    if !fields.untag.is_empty() {
+        let (old_tags, project) = cli::parse_markers(&fields.untag)?;
+        let current = &app.tasks[&id];
+        let clear_project = project.is_some() && current.project.is_some();
+        if clear_project || old_tags.iter().any(|tag| current.tags.contains(tag)) {
+            app.untag_task(id, &old_tags, clear_project);
+        }
+    }
+    for &blocker in &fields.block {
+        if !task.blocked_by.contains(&blocker) {
+            app.add_blocker(id, blocker)?;
+        }
+    }
+    for &blocker in &fields.unblock {
+        if app.tasks[&id].blocked_by.contains(&blocker) {
+            app.remove_blocker(id, blocker)?;
+        }
+    }
+    match &fields.status {
+        Some(status) => Ok(app.move_task(id, status.clone(), force)?),
+        None => Ok(None),
+    }
+}
+
+// The task as a block of text to edit
+pub fn render(task: &Task) -> String {
+    let or_none = |value: String| match value.is_empty() {
+        true => "none".to_string(),
+        false => value,
+    };
+    let tags: Vec<String> = task.tags.iter().map(|tag| format!("#{}", tag)).collect();
+    let blockers: Vec<String> = task
+        .blocked_by
+        .iter()
+        .map(|id| format!("#{}", id))
+        .collect();
+
+    let mut text = String::new();
+    let mut line = |key: &str, value: String| text.push_str(&format!("{}: {}\n", key, value));
+    line("Title", task.title.clone());
+    line("Status", task.status.name().to_string());
+    line("Priority", task.priority.to_string().to_lowercase());
+    line(
+        "Due",
+        or_none(task.due.map_or(String::new(), |d| d.to_string())),
+    );
+    line(
+        "Repeat",
+        or_none(
+            task.recurrence
+                .as_ref()
+                .map_or(String::new(), |r| r.to_file_format()),
+        ),
+    );
+    line("Tags", or_none(tags.join(" ")));
+    line(
+        "Project",
+        or_none(
+            task.project
+                .as_ref()
+                .map_or(String::new(), |p| format!("+{}", p)),
+        ),
+    );
+    line(
+        "Parent",
+        or_none(task.parent.map_or(String::new(), |p| format!("#{}", p))),
+    );
+    line("Blocked by", or_none(blockers.join(" ")));
+    text.push('\n');
+    if let Some(description) = &task.description {
+        text.push_str(description);
+        text.push('\n');
+    }
+    text
+}
+
+// Reads an edited block of text back as the fields that changed
+pub fn parse(text: &str, task: &Task, today: NaiveDate) -> Result<Fields, String> {
+    let mut fields = Fields::default();
+    let mut lines = text.lines();
+
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("expected 'Field: value', found '{}'", line.trim()))?;
+        let value = value.trim();
+
+        match key.trim().to_lowercase().as_str() {
+            "title" if value.is_empty() => return Err("a task needs a title".to_string()),
+            "title" if value != task.title => fields.title = Some(value.to_string()),
+            "title" => {}
+            "status" => {
+                let status = Status::parse(value)?;
+                fields.status = Some(status).filter(|s| *s != task.status);
+            }
+            "priority" => {
+                let priority = parse_priority(value)?;
+                fields.priority = Some(priority).filter(|p| *p != task.priority);
+            }
+            "due" => {
+                let due = parse_due(value, today)?;
+                fields.due = (due != task.due).then(|| value.to_string());
+            }
+            "repeat" => {
+                let anchor = task.due.map_or(today, |d| d.date);
+                let recurrence = parse_repeat(value, anchor)?;
+                fields.repeat = (recurrence != task.recurrence).then(|| value.to_string());
+            }
+            "tags" => {
+                let mut new_tags = Vec::new();
+                for word in value.split_whitespace().filter(|w| !is_none(w)) {
+                    let tag = tags::parse_tag(word)
+                        .or_else(|| tags::parse_tag(&format!("#{}", word)))
+                        .ok_or_else(|| format!("'{}' is not a #tag", word))?;
+                    tags::add_tag(&mut new_tags, tag);
+                }
+                let added = new_tags.iter().filter(|tag| !task.tags.contains(tag));
+                fields.tag = added.map(|tag| format!("#{}", tag)).collect();
+                let removed = task.tags.iter().filter(|tag| !new_tags.contains(tag));
+                fields.untag = removed.map(|tag| format!("#{}", tag)).collect();
+            }
+            "project" => {
+                let project = match is_none(value) {
+                    true => None,
+                    false => Some(
+                        tags::parse_project(value)
+                            .or_else(|| tags::parse_project(&format!("+{}", value)))
+                            .ok_or_else(|| format!("'{}' is not a +project", value))?,
+                    ),
+                };
+                match (project, &task.project) {
+                    (Some(new), old) if old.as_ref() != Some(&new) => {
+                        fields.tag.push(format!("+{}", new))
+                    }
+                    (None, Some(old)) => fields.untag.push(format!("+{}", old)),
+                    _ => {}
+                }
+            }
+            "parent" => {
+                let parent = parse_parent(value)?;
+                fields.parent = (parent != task.parent).then(|| value.to_string());
+            }
+            "blocked by" => {
+                let mut blockers = Vec::new();
+                let words = value.split(|c: char| c.is_whitespace() || c == ',');
+                for word in words.filter(|w| !is_none(w)) {
+                    blockers.push(parse_parent(word)?.unwrap_or_default());
+                }
+                fields.block = blockers
+                    .iter()
+                    .copied()
+                    .filter(|id| !task.blocked_by.contains(id))
+                    .collect();
+                fields.unblock = task
+                    .blocked_by
+                    .iter()
+                    .copied()
+                    .filter(|id| !blockers.contains(id))
+                    .collect();
+            }
+            _ => return Err(format!("unknown field '{}'", key.trim())),
+        }
+    }
+
+    let description = lines.collect::<Vec<&str>>().join("\n");
+    if description.trim() != task.description.as_deref().unwrap_or("") {
+        fields.description = Some(description.trim().to_string());
+    }
+    Ok(fields)
+}
+
+// Opens $VISUAL or $EDITOR on the text and returns what was saved
+pub fn open(text: &str, id: u32) -> Result<String, CliError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = create_temp(text, id)?;
+
+    // Through the shell, so editors given with arguments like "code --wait" work
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(CliError::Invalid(format!(
+                "{} exited with {}; task #{} was not changed",
+                editor, status, id
+            )))
+        }
+        Err(e) => return Err(CliError::Io(format!("Can't run {}: {}", editor, e))),
+    }
+    edited.map_err(|e| CliError::Io(format!("Error reading {}: {}", path.display(), e)))
+}
+
+// Writes the text to a new file in the temporary directory. The file is
+// created rather than opened, so a file or link someone else put there in
+// advance is never written through, and on Unix only the user can read it.
+fn create_temp(text: &str, id: u32) -> Result<PathBuf, CliError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut attempts = 0;
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let name = format!("todo-{}-task-{}-{:08x}.txt", process::id(), id, nanos);
+        let path = env::temp_dir().join(name);
+        let error = |e| CliError::Io(format!("Error writing {}: {}", path.display(), e));
+        match options.open(&path) {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(text.as_bytes()) {
+                    let _ = fs::remove_file(&path);
+                    return Err(error(e));
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && attempts < 10 => attempts += 1,
+            Err(e) => return Err(error(e)),
+        }
+    }
+}
+
+// What differs between two versions of a task, e.g. "priority: medium → high",
+// or "->" when the terminal doesn't show the arrow
+pub fn describe(before: &Task, after: &Task, renderer: &Renderer) -> Vec<String> {
+    let arrow = renderer.marker("→", "->");
+    let mut changes = Vec::new();
+    let mut field = |name: &str, old: String, new: String| {
+        if old != new {
+            let show = |value: String| match value.is_empty() {
+                true => "none".to_string(),
+                false => value,
+            };
+            changes.push(format!("{}: {} {} {}", name, show(old), arrow, show(new)));
+        }
+    };
+    let due = |task: &Task| task.due.map_or(String::new(), |d| d.to_string());
+    let repeat = |task: &Task| {
+        let rule = task.recurrence.as_ref();
+        rule.map_or(String::new(), |r| r.to_file_format())
+    };
+    let tags = |task: &Task| {
+        let tags: Vec<String> = task.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        tags.join(" ")
+    };
+    let project = |task: &Task| {
+        task.project
+            .as_ref()
+            .map_or(String::new(), |p| format!("+{}", p))
+    };
+    let parent = |task: &Task| task.parent.map_or(String::new(), |p| format!("#{}", p));
+
+    field("title", before.title.clone(), after.title.clone());
+    field(
+        "status",
+        before.status.name().into(),
+        after.status.name().into(),
+    );
+    let priority = |task: &Task| task.priority.to_string().to_lowercase();
+    field("priority", priority(before), priority(after));
+    field("due", due(before), due(after));
+    field("repeat", repeat(before), repeat(after));
+    field("tags", tags(before), tags(after));
+    field("project", project(before), project(after));
+    field("parent", parent(before), parent(after));
+    field(
+        "blocked by",
+        format_ids(&before.blocked_by),
+        format_ids(&after.blocked_by),
+    );
+    if before.description != after.description {
+        changes.push(match after.description {
+            Some(_) => "new description".to_string(),
+            None => "description cleared".to_string(),
+        });
+    }
+    if before.is_timing() && !after.is_timing() {
+        changes.push("timer stopped".to_string());
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_app;
+
+    #[test]
+    fn test_render_and_parse() {
+        let mut app = temp_app("edit-render");
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let id = app.add_task(
+            "Ship release #work +website".into(),
+            Priority::Medium,
+            None,
+            None,
+        );
+        let task = app.tasks[&id].clone();
+
+        let text = render(&task);
+        assert!(text.starts_with("Title: Ship release\nStatus: todo\nPriority: medium\n"));
+        assert!(text.contains("Tags: #work\nProject: +website\nParent: none\n"));
+        assert!(parse(&text, &task, today).unwrap().is_empty());
+
+        let edited = text
+            .replace("Priority: medium", "priority: HIGH")
+            .replace("Tags: #work", "Tags: #urgent home")
+            .replace("Project: +website", "Project: none")
+            .replace("Due: none", "Due: 2026-10-20 17:00")
+            + "Check the changelog\nfirst.\n";
+        let fields = parse(&edited, &task, today).unwrap();
+        assert_eq!(
+            fields,
+            Fields {
+                priority: Some(Priority::High),
+                description: Some("Check the changelog\nfirst.".to_string()),
+                due: Some("2026-10-20 17:00".to_string()),
+                tag: vec!["#home".to_string(), "#urgent".to_string()],
+                untag: vec!["#work".to_string(), "+website".to_string()],
+                ..Fields::default()
+            }
+        );
+
+        assert!(parse("Title:\n", &task, today).is_err());
+        assert!(parse("Colour: red\n", &task, today).is_err());
+        assert!(parse("Due: someday\n", &task, today).is_err());
+    }
+
+    #[test]
+    fn test_apply_changes_only_what_differs() {
+        let mut app = temp_app("edit-apply");
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let a = app.add_task("Write docs #work".into(), Priority::Medium, None, None);
+        let b = app.add_task("Review".into(), Priority::High, None, None);
+        let (before_a, before_b) = (app.tasks[&a].clone(), app.tasks[&b].clone());
+
+        let fields = Fields {
+            title: Some("Write the docs".to_string()),
+            priority: Some(Priority::High),
+            due: Some("2026-10-20".to_string()),
+            parent: Some("#2".to_string()),
+            tag: vec!["#work".to_string(), "+site".to_string()],
+            status: Some(Status::InProgress),
+            ..Fields::default()
+        };
+        apply(&mut app, a, &fields, false, today).unwrap();
+        let task = &app.tasks[&a];
+        assert_eq!(task.title, "Write the docs");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due.unwrap().to_string(), "2026-10-20");
+        assert_eq!(
+            (task.parent, task.project.as_deref()),
+            (Some(b), Some("site"))
+        );
+        assert_eq!(task.status, Status::InProgress);
+        assert!(describe(&before_b, &app.tasks[&b], &app.renderer).is_empty());
+
+        let priority = Fields {
+            priority: Some(Priority::High),
+            ..Fields::default()
+        };
+        apply(&mut app, b, &priority, false, today).unwrap();
+        assert_eq!(app.tasks[&b].updated_at, before_b.updated_at);
+
+        let changes = describe(&before_a, &app.tasks[&a], &app.renderer);
+        assert!(changes.contains(&"title: Write docs → Write the docs".to_string()));
+        assert!(changes.contains(&"priority: medium → high".to_string()));
+        assert!(changes.contains(&"status: todo → in-progress".to_string()));
+        app.renderer.emoji = false;
+        let changes = describe(&before_a, &app.tasks[&a], &app.renderer);
+        assert!(changes.contains(&"priority: medium -> high".to_string()));
+
+        let untitled = Fields {
+            title: Some(" \n ".to_string()),
+            ..Fields::default()
+        };
+        assert!(apply(&mut app, a, &untitled, false, today).is_err());
+        let markers = Fields {
+            title: Some("#work +site".to_string()),
+            ..Fields::default()
+        };
+        apply(&mut app, a, &markers, false, today).unwrap();
+        assert_eq!(app.tasks[&a].title, "#work +site");
+
+        let bad = Fields {
+            parent: Some("99".to_string()),
+            ..Fields::default()
+        };
+        assert_eq!(
+            apply(&mut app, a, &bad, false, today).unwrap_err(),
+            CliError::NotFound("Task #99 not found".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_temp_file_is_new_and_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = create_temp("Title: Ship release\n", 7).unwrap();
+        let second = create_temp("", 7).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "Title: Ship release\n");
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = fs::remove_file(first);
+        let _ = fs::remove_file(second);
+    }
+}
@@ -0,0 +1,319 @@
+// Importing and exporting other apps' formats
+//
+// `todo export <format> [-o FILE]` writes every task in another app's format
+// and `todo import <format> FILE` reads them back:
+//
+//   todotxt   the todo.txt convention (todotxt.rs)
+//   ical      iCalendar VTODO components (ical.rs)
+//   markdown  `- [ ]` checklists in a Markdown document (markdown.rs)
+//
+// Each format carries our task ids, so importing a file exported earlier
+// updates those tasks rather than adding copies. An imported task with the id
+// of an existing task replaces it (or is skipped if nothing changed), and any
+// other task is added, keeping its id when it has one. Formats that identify
+// tasks by UID give each task one made from its id, and a task first
+// imported with another app's UID keeps it, so importing from that app again
+// matches the same task. Parent and blocker
+// links are checked once every task is in; links to missing tasks or that
+// would form a cycle are dropped with a warning. Fields a format can't hold,
+// such as repeat rules in a Markdown checklist, keep their current values when
+// a task is updated. No format holds tracked time, so that is always kept.
+// Formats that only tell open tasks from closed ones keep a task's status
+// while it stays on the same side, so an in-progress task that's still
+// unchecked stays in progress.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use clap::ValueEnum;
+
+use crate::markdown::{self, Group};
+use crate::record::ParseError;
+use crate::{ical, todotxt, Task, TodoApp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Exchange {
+    /// todo.txt, one task per line
+    Todotxt,
+    /// iCalendar VTODO components (.ics)
+    Ical,
+    /// Markdown checklists, from any .md file
+    Markdown,
+}
+
+// A task read from another format, with the id it carried if any
+pub struct Imported {
+    pub id: Option<u32>,
+    pub task: Task,
+    // Links to other tasks by UID, for formats that refer to tasks that way
+    pub parent_uid: Option<String>,
+    pub blocker_uids: Vec<String>,
+    // The parent's position among the imported tasks, for formats that nest
+    // subtasks under their parent
+    pub parent_index: Option<usize>,
+    // Task::FIELDS the format doesn't hold, which an update keeps as they were
+    pub keep: Vec<&'static str>,
+}
+
+impl Imported {
+    pub fn new(id: Option<u32>, task: Task) -> Self {
+        Imported {
+            id,
+            task,
+            parent_uid: None,
+            blocker_uids: Vec::new(),
+            parent_index: None,
+            keep: vec!["time"],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    // The id each imported task was given, in order
+    pub ids: Vec<u32>,
+    pub added: Vec<u32>,
+    pub updated: Vec<u32>,
+    pub unchanged: usize,
+    pub warnings: Vec<String>,
+}
+
+pub fn export(exchange: Exchange, tasks: &[&Task], group: Group) -> String {
+    match exchange {
+        Exchange::Todotxt => todotxt::export(tasks),
+        Exchange::Ical => ical::export(tasks),
+        Exchange::Markdown => markdown::export(tasks, group),
+    }
+}
+
+pub fn parse(exchange: Exchange, content: &str) -> Result<Vec<Imported>, ParseError> {
+    match exchange {
+        Exchange::Todotxt => todotxt::parse(content),
+        Exchange::Ical => ical::parse(content),
+        Exchange::Markdown => Ok(markdown::parse(content)),
+    }
+}
+
+const UID_PREFIX: &str = "todo-";
+const UID_SUFFIX: &str = "@todo-app";
+
+// The UID other apps know a task by: the one it was imported with, or one
+// made from its id
+pub fn uid(task: &Task) -> String {
+    task.uid.clone().unwrap_or_else(|| uid_from_id(task.id))
+}
+
+pub fn uid_from_id(id: u32) -> String {
+    format!("{}{}{}", UID_PREFIX, id, UID_SUFFIX)
+}
+
+// The id inside a UID made by `uid_from_id`
+pub fn id_from_uid(uid: &str) -> Option<u32> {
+    uid.strip_prefix(UID_PREFIX)?
+        .strip_suffix(UID_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+// The local calendar day of a timestamp, for formats that only keep dates
+pub fn local_date(time: &DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
+
+// Local midnight at the start of `date`
+pub fn start_of(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map_or(midnight.and_utc(), |time| time.with_timezone(&Utc))
+}
+
+pub fn apply(app: &mut TodoApp, mut imported: Vec<Imported>) -> Summary {
+    let mut summary = Summary::default();
+
+    // Tasks imported before under another app's UID keep their id
+    let mut uids: HashMap<String, u32> = app
+        .tasks
+        .values()
+        .filter_map(|task| Some((task.uid.clone()?, task.id)))
+        .collect();
+    for item in imported.iter_mut().filter(|item| item.id.is_none()) {
+        item.id = item
+            .task
+            .uid
+            .as_ref()
+            .and_then(|uid| uids.get(uid).copied());
+    }
+
+    let highest = imported.iter().filter_map(|item| item.id).max();
+    let mut next_id = app.next_id.max(highest.map_or(0, |id| id + 1));
+    for item in &mut imported {
+        item.task.id = item.id.unwrap_or_else(|| {
+            next_id += 1;
+            next_id - 1
+        });
+        if let Some(uid) = &item.task.uid {
+            uids.insert(uid.clone(), item.task.id);
+        }
+    }
+    summary.ids = imported.iter().map(|item| item.task.id).collect();
+
+    let resolve = |uid: &String| id_from_uid(uid).or_else(|| uids.get(uid).copied());
+    let mut links = HashMap::new();
+    for Imported {
+        mut task,
+        parent_uid,
+        blocker_uids,
+        parent_index,
+        keep,
+        ..
+    } in imported
+    {
+        if let Some(existing) = app.tasks.get(&task.id) {
+            keep_fields(&mut task, existing, &keep);
+        }
+        if let Some(index) = parent_index {
+            task.parent = summary.ids.get(index).copied();
+        }
+        if let Some(uid) = parent_uid {
+            task.parent = resolve(&uid);
+            if task.parent.is_none() {
+                summary.warnings.push(format!(
+                    "Dropped the parent of #{}: no task has UID {}",
+                    task.id, uid
+                ));
+            }
+        }
+        for uid in blocker_uids {
+            match resolve(&uid) {
+                Some(blocker) => task.blocked_by.push(blocker),
+                None => summary.warnings.push(format!(
+                    "Dropped a blocker of #{}: no task has UID {}",
+                    task.id, uid
+                )),
+            }
+        }
+        task.blocked_by.sort();
+        task.blocked_by.dedup();
+        let parent = task.parent.take();
+        let blocked_by = std::mem::take(&mut task.blocked_by);
+
+        match app.tasks.get(&task.id) {
+            Some(existing) => {
+                // Keep the exact times when the format only had their dates
+                if local_date(&existing.created_at) == local_date(&task.created_at) {
+                    task.created_at = existing.created_at;
+                }
+                if let (Some(before), Some(after)) = (existing.completed_at, task.completed_at) {
+                    if local_date(&before) == local_date(&after) {
+                        task.completed_at = Some(before);
+                    }
+                }
+
+                let mut unchanged = task.clone();
+                unchanged.parent = parent;
+                unchanged.blocked_by = blocked_by.clone();
+                unchanged.updated_at = existing.updated_at;
+                if unchanged.to_file_format() == existing.to_file_format() {
+                    summary.unchanged += 1;
+                    continue;
+                }
+                task.updated_at = Utc::now();
+                summary.updated.push(task.id);
+            }
+            None => summary.added.push(task.id),
+        }
+        links.insert(task.id, (parent, blocked_by));
+        app.tasks.insert(task.id, task);
+    }
+
+    let mut ids: Vec<&u32> = links.keys().collect();
+    ids.sort();
+    for &id in ids {
+        let (parent, blocked_by) = &links[&id];
+        let updated_at = app.tasks[&id].updated_at;
+        if let Err(e) = app.set_parent(id, *parent) {
+            summary
+                .warnings
+                .push(format!("Dropped the parent of #{}: {}", id, e));
+        }
+        for &blocker in blocked_by {
+            if let Err(e) = app.add_blocker(id, blocker) {
+                summary
+                    .warnings
+                    .push(format!("Dropped blocker #{} of #{}: {}", blocker, id, e));
+            }
+        }
+        app.tasks.get_mut(&id).unwrap().updated_at = updated_at;
+    }
+
+    if !links.is_empty() {
+        app.next_id = next_id;
+        app.dirty = true;
+    }
+    summary
+}
+
+// Copies the fields named in `fields` from the task being updated
+fn keep_fields(task: &mut Task, existing: &Task, fields: &[&str]) {
+    for &field in fields {
+        match field {
+            "priority" => task.priority = existing.priority.clone(),
+            "created" => task.created_at = existing.created_at,
+            "completed" if task.status.is_closed() && existing.status.is_closed() => {
+                task.completed_at = existing.completed_at
+            }
+            "status" if task.status.is_closed() == existing.status.is_closed() => {
+                task.status = existing.status.clone()
+            }
+            "repeat" => task.recurrence = existing.recurrence.clone(),
+            "blocked_by" => task.blocked_by = existing.blocked_by.clone(),
+            "uid" => task.uid = existing.uid.clone(),
+            "time" => task.sessions = existing.sessions.clone(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{temp_app, Priority};
+
+    fn imported(id: Option<u32>, title: &str) -> Imported {
+        Imported::new(id, Task::new(0, title.to_string(), Priority::Medium))
+    }
+
+    #[test]
+    fn test_apply_matches_ids() {
+        let mut app = temp_app("exchange-apply");
+        let a = app.add_task("Keep".to_string(), Priority::Medium, None, None);
+        let b = app.add_task("Rename".to_string(), Priority::Medium, None, None);
+        let keep = app.tasks[&a].clone();
+
+        let mut renamed = imported(Some(b), "Renamed");
+        renamed.task.created_at = app.tasks[&b].created_at;
+        let mut linked = imported(None, "New");
+        linked.task.parent = Some(b);
+        linked.task.blocked_by = vec![99];
+        let summary = apply(
+            &mut app,
+            vec![
+                Imported::new(Some(a), keep),
+                renamed,
+                linked,
+                imported(Some(10), "Ten"),
+            ],
+        );
+
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(summary.updated, vec![b]);
+        assert_eq!(summary.added, vec![11, 10]);
+        assert_eq!(app.tasks[&b].title, "Renamed");
+        assert_eq!(app.tasks[&11].parent, Some(b));
+        assert!(app.tasks[&11].blocked_by.is_empty());
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(app.next_id, 12);
+    }
+}
@@ -0,0 +1,387 @@
+// Undo history
+//
+// Every command that changes tasks is recorded as an operation holding the
+// state of each task it touched before and after the change, so it can be
+// reverted and replayed exactly. Tasks are stored as their todo file records.
+//
+// The history is kept next to the todo file in `<file>.history` so undo works
+// across restarts. The first line is {"version": 1, "position": N}, where the
+// first N operations are applied and the rest have been undone and can be
+// redone. Each following line is one operation as a JSON object. Only the
+// last MAX_OPERATIONS are kept.
+//
+// When another todo process saved its own history since this one was loaded,
+// saving keeps theirs and records the operations made here on top of it.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::journal;
+use crate::{format_timestamp, parse_timestamp, Task};
+
+const HISTORY_VERSION: u64 = 1;
+const MAX_OPERATIONS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub id: u32,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub time: DateTime<Utc>,
+    pub label: String,
+    pub next_id: (u32, u32),
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    operations: Vec<Operation>,
+    position: usize,
+    // Operations recorded since the file was last read or written, and its
+    // content at that point
+    unsaved: usize,
+    synced: String,
+}
+
+// The tasks that differ between two states of the task map, by id
+pub fn diff(before: &HashMap<u32, Task>, after: &HashMap<u32, Task>) -> Vec<Change> {
+    let ids: BTreeSet<u32> = before.keys().chain(after.keys()).copied().collect();
+    ids.into_iter()
+        .filter(|id| record(before.get(id)) != record(after.get(id)))
+        .map(|id| Change {
+            id,
+            before: before.get(&id).cloned(),
+            after: after.get(&id).cloned(),
+        })
+        .collect()
+}
+
+fn record(task: Option<&Task>) -> Option<String> {
+    task.map(|t| t.to_file_format())
+}
+
+// Puts each task back to one side of a change, after checking that every task
+// is still in the state the other side left it in
+fn apply(
+    changes: &[Change],
+    tasks: &mut HashMap<u32, Task>,
+    from: fn(&Change) -> &Option<Task>,
+    to: fn(&Change) -> &Option<Task>,
+) -> Result<(), u32> {
+    for change in changes {
+        if record(tasks.get(&change.id)) != record(from(change).as_ref()) {
+            return Err(change.id);
+        }
+    }
+    for change in changes {
+        match to(change) {
+            Some(task) => tasks.insert(change.id, task.clone()),
+            None => tasks.remove(&change.id),
+        };
+    }
+    Ok(())
+}
+
+impl History {
+    pub fn record(&mut self, operation: Operation) {
+        self.operations.truncate(self.position);
+        self.operations.push(operation);
+        if self.operations.len() > MAX_OPERATIONS {
+            self.operations.remove(0);
+        }
+        self.position = self.operations.len();
+        self.unsaved = (self.unsaved + 1).min(self.operations.len());
+    }
+
+    pub fn undo(
+        &mut self,
+        tasks: &mut HashMap<u32, Task>,
+        next_id: &mut u32,
+    ) -> Result<&Operation, String> {
+        if self.position == 0 {
+            return Err("Nothing to undo".to_string());
+        }
+        let operation = &self.operations[self.position - 1];
+        apply(&operation.changes, tasks, |c| &c.after, |c| &c.before).map_err(|id| {
+            format!(
+                "Can't undo '{}': task #{} has changed since",
+                operation.label, id
+            )
+        })?;
+        *next_id = operation.next_id.0;
+        self.position -= 1;
+        Ok(&self.operations[self.position])
+    }
+
+    pub fn redo(
+        &mut self,
+        tasks: &mut HashMap<u32, Task>,
+        next_id: &mut u32,
+    ) -> Result<&Operation, String> {
+        let operation = self
+            .operations
+            .get(self.position)
+            .ok_or_else(|| "Nothing to redo".to_string())?;
+        apply(&operation.changes, tasks, |c| &c.before, |c| &c.after).map_err(|id| {
+            format!(
+                "Can't redo '{}': task #{} has changed since",
+                operation.label, id
+            )
+        })?;
+        *next_id = operation.next_id.1;
+        self.position += 1;
+        Ok(&self.operations[self.position - 1])
+    }
+
+    // The newest `limit` operations, newest first, with whether each is undone
+    pub fn recent(&self, limit: usize) -> Vec<(&Operation, bool)> {
+        self.operations
+            .iter()
+            .enumerate()
+            .rev()
+            .take(limit)
+            .map(|(index, operation)| (operation, index >= self.position))
+            .collect()
+    }
+
+    pub fn load(path: &str) -> Result<History, String> {
+        if !Path::new(path).exists() {
+            return Ok(History::default());
+        }
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        History::parse(content)
+    }
+
+    fn parse(content: String) -> Result<History, String> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+        let header: Value = match lines.next() {
+            Some(line) => serde_json::from_str(line).map_err(|e| e.to_string())?,
+            None => return Ok(History::default()),
+        };
+        let version = header["version"].as_u64();
+        if version != Some(HISTORY_VERSION) {
+            return Err(format!("unsupported history version {:?}", version));
+        }
+
+        let operations = lines
+            .map(operation_from_json)
+            .collect::<Result<Vec<Operation>, String>>()?;
+        let position = header["position"]
+            .as_u64()
+            .map_or(operations.len(), |p| p as usize)
+            .min(operations.len());
+
+        Ok(History {
+            operations,
+            position,
+            unsaved: 0,
+            synced: content,
+        })
+    }
+
+    pub fn save(&mut self, path: &str) -> Result<(), String> {
+        let on_disk = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        if on_disk != self.synced {
+            // An unreadable history is simply replaced
+            if let Ok(latest) = History::parse(on_disk) {
+                self.rebase(latest);
+            }
+        }
+
+        let mut content =
+            json!({ "version": HISTORY_VERSION, "position": self.position }).to_string();
+        content.push('\n');
+        for operation in &self.operations {
+            content.push_str(&operation_to_json(operation).to_string());
+            content.push('\n');
+        }
+        journal::write_atomic(path, &content).map_err(|e| e.to_string())?;
+        self.unsaved = 0;
+        self.synced = content;
+        Ok(())
+    }
+
+    // Replaces the history with `latest` plus the operations recorded here
+    // since the last save
+    fn rebase(&mut self, latest: History) {
+        let start = self.operations.len() - self.unsaved;
+        let unsaved: Vec<Operation> = self.operations.drain(start..).collect();
+        *self = latest;
+        for operation in unsaved {
+            self.record(operation);
+        }
+    }
+}
+
+fn operation_to_json(operation: &Operation) -> Value {
+    let changes: Vec<Value> = operation
+        .changes
+        .iter()
+        .map(|change| {
+            json!({
+                "id": change.id,
+                "before": record(change.before.as_ref()),
+                "after": record(change.after.as_ref()),
+            })
+        })
+        .collect();
+
+    json!({
+        "time": format_timestamp(&operation.time),
+        "label": operation.label,
+        "next_id": [operation.next_id.0, operation.next_id.1],
+        "changes": changes,
+    })
+}
+
+fn operation_from_json(line: &str) -> Result<Operation, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let invalid = || format!("invalid history entry: {}", line);
+
+    let task = |side: &Value| -> Result<Option<Task>, String> {
+        match side {
+            Value::Null => Ok(None),
+            Value::String(record) => Task::from_file_format(record).map(Some),
+            _ => Err(invalid()),
+        }
+    };
+    let id = |value: &Value| -> Result<u32, String> {
+        value
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(invalid)
+    };
+
+    let changes = value["changes"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|change| {
+            Ok(Change {
+                id: id(&change["id"])?,
+                before: task(&change["before"])?,
+                after: task(&change["after"])?,
+            })
+        })
+        .collect::<Result<Vec<Change>, String>>()?;
+
+    Ok(Operation {
+        time: parse_timestamp(value["time"].as_str().ok_or_else(invalid)?)?,
+        label: value["label"].as_str().ok_or_else(invalid)?.to_string(),
+        next_id: (id(&value["next_id"][0])?, id(&value["next_id"][1])?),
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    fn operation(
+        label: &str,
+        before: &HashMap<u32, Task>,
+        after: &HashMap<u32, Task>,
+    ) -> Operation {
+        Operation {
+            time: Utc::now(),
+            label: label.to_string(),
+            next_id: (1, 2),
+            changes: diff(before, after),
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let empty = HashMap::new();
+        let mut tasks = HashMap::new();
+        tasks.insert(1, Task::new(1, "Write docs".to_string(), Priority::Low));
+
+        let mut history = History::default();
+        history.record(operation("add #1", &empty, &tasks));
+        let mut next_id = 2;
+
+        assert_eq!(
+            history.undo(&mut tasks, &mut next_id).unwrap().label,
+            "add #1"
+        );
+        assert!(tasks.is_empty());
+        assert_eq!(next_id, 1);
+        assert!(history.undo(&mut tasks, &mut next_id).is_err());
+
+        history.redo(&mut tasks, &mut next_id).unwrap();
+        assert_eq!(tasks[&1].title, "Write docs");
+        assert_eq!(next_id, 2);
+        assert!(history.redo(&mut tasks, &mut next_id).is_err());
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_tasks() {
+        let empty = HashMap::new();
+        let mut tasks = HashMap::new();
+        tasks.insert(1, Task::new(1, "Write docs".to_string(), Priority::Low));
+
+        let mut history = History::default();
+        history.record(operation("add #1", &empty, &tasks));
+        tasks.get_mut(&1).unwrap().title = "Edited elsewhere".to_string();
+
+        let mut next_id = 2;
+        let error = history.undo(&mut tasks, &mut next_id).unwrap_err();
+        assert!(error.contains("#1 has changed"));
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_new_operation_drops_redo() {
+        let empty = HashMap::new();
+        let mut tasks = HashMap::new();
+        tasks.insert(1, Task::new(1, "A".to_string(), Priority::Low));
+
+        let mut history = History::default();
+        history.record(operation("add #1", &empty, &tasks));
+        let mut next_id = 2;
+        history.undo(&mut tasks, &mut next_id).unwrap();
+        history.record(operation("other", &empty, &empty));
+
+        assert!(history.redo(&mut tasks, &mut next_id).is_err());
+        assert_eq!(history.recent(10).len(), 1);
+    }
+
+    #[test]
+    fn test_save_keeps_history_saved_elsewhere() {
+        let path = std::env::temp_dir().join(format!("todo-history-{}", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+        let empty = HashMap::new();
+
+        let mut ours = History::default();
+        let mut theirs = History::default();
+        theirs.record(operation("theirs", &empty, &empty));
+        theirs.save(&path).unwrap();
+        ours.record(operation("ours", &empty, &empty));
+        ours.save(&path).unwrap();
+
+        let labels: Vec<String> = History::load(&path)
+            .unwrap()
+            .recent(10)
+            .iter()
+            .map(|(operation, _)| operation.label.clone())
+            .collect();
+        assert_eq!(labels, vec!["ours", "theirs"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
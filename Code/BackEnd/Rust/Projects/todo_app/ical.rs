@@ -0,0 +1,591 @@
+// iCalendar to-dos
+//
+// Reads and writes VTODO components (RFC 5545), as used by calendar apps and
+// CalDAV task lists. Export writes one VCALENDAR holding a VTODO per task:
+//
+//   UID             the UID the task was imported with, or todo-ID@todo-app
+//   SUMMARY         title
+//   DESCRIPTION     description
+//   PRIORITY        1, 5 and 9 for High, Medium and Low. On import 1-4 is
+//                   High, 6-9 is Low, and 5, 0 or none is Medium.
+//   X-TODO-PRIORITY urgent or someday, which PRIORITY can't say; written as
+//                   1 and 9
+//   STATUS          NEEDS-ACTION, IN-PROCESS, COMPLETED or CANCELLED, with
+//                   the time a task was closed in COMPLETED
+//   X-TODO-STATUS   waiting or a status the workflow adds, which STATUS
+//                   can't say; written as NEEDS-ACTION
+//   CREATED         creation time; DTSTAMP and LAST-MODIFIED carry updated_at
+//   DUE             a DATE, or a local (floating) DATE-TIME for a due time
+//   RRULE           FREQ=DAILY with an optional INTERVAL, FREQ=WEEKLY with
+//                   BYDAY, or FREQ=MONTHLY with BYMONTHDAY
+//   CATEGORIES      tags
+//   X-TODO-PROJECT  project
+//   RELATED-TO      the parent's UID, or with RELTYPE=DEPENDS-ON a blocker's
+//
+// Importing matches tasks by UID: our own UIDs carry the task id, and a UID
+// from another app is kept on the task so importing that app's file again
+// updates the same task. Times in UTC or floating local time are read as
+// such, and times with a TZID are read as local time. Properties, parameters
+// and nested components (such as VALARM) not listed above are ignored, as
+// are repeat rules that can't be expressed as a `repeat` rule.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use crate::due::Due;
+use crate::exchange::{self, Imported};
+use crate::record::ParseError;
+use crate::recurrence::Recurrence;
+use crate::workflow::Status;
+use crate::{tags, Priority, Task};
+
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+const DATE_FORMAT: &str = "%Y%m%d";
+// Longest line in octets, not counting the line break
+const LINE_LIMIT: usize = 75;
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "MO"),
+    (Weekday::Tue, "TU"),
+    (Weekday::Wed, "WE"),
+    (Weekday::Thu, "TH"),
+    (Weekday::Fri, "FR"),
+    (Weekday::Sat, "SA"),
+    (Weekday::Sun, "SU"),
+];
+
+pub fn export(tasks: &[&Task]) -> String {
+    let uids: HashMap<u32, String> = tasks
+        .iter()
+        .map(|task| (task.id, exchange::uid(task)))
+        .collect();
+    let uid_of = |id: u32| {
+        uids.get(&id)
+            .cloned()
+            .unwrap_or_else(|| exchange::uid_from_id(id))
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo-app//todo//EN".to_string(),
+    ];
+    for task in tasks {
+        let utc = |time: &DateTime<Utc>| time.format(UTC_FORMAT).to_string();
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", escape(&uids[&task.id])));
+        lines.push(format!("DTSTAMP:{}", utc(&task.updated_at)));
+        lines.push(format!("CREATED:{}", utc(&task.created_at)));
+        lines.push(format!("LAST-MODIFIED:{}", utc(&task.updated_at)));
+        lines.push(format!("SUMMARY:{}", escape(&task.title)));
+        if let Some(description) = &task.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        lines.push(format!("PRIORITY:{}", priority_number(&task.priority)));
+        if matches!(task.priority, Priority::Urgent | Priority::Someday) {
+            let level = task.priority.to_string().to_lowercase();
+            lines.push(format!("X-TODO-PRIORITY:{}", level));
+        }
+        let status = match task.status {
+            Status::Todo | Status::Waiting | Status::Other(_) => "NEEDS-ACTION",
+            Status::InProgress => "IN-PROCESS",
+            Status::Done => "COMPLETED",
+            Status::Cancelled => "CANCELLED",
+        };
+        lines.push(format!("STATUS:{}", status));
+        if matches!(task.status, Status::Waiting | Status::Other(_)) {
+            lines.push(format!("X-TODO-STATUS:{}", task.status.name()));
+        }
+        if task.status.is_closed() {
+            let completed_at = task.completed_at.as_ref().unwrap_or(&task.updated_at);
+            lines.push(format!("COMPLETED:{}", utc(completed_at)));
+        }
+        if let Some(due) = task.due {
+            lines.push(match due.time {
+                Some(time) => format!("DUE:{}", due.date.and_time(time).format(LOCAL_FORMAT)),
+                None => format!("DUE;VALUE=DATE:{}", due.date.format(DATE_FORMAT)),
+            });
+        }
+        if let Some(rule) = &task.recurrence {
+            lines.push(format!("RRULE:{}", to_rrule(rule)));
+        }
+        if !task.tags.is_empty() {
+            let tags: Vec<String> = task.tags.iter().map(|tag| escape(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(project) = &task.project {
+            lines.push(format!("X-TODO-PROJECT:{}", escape(project)));
+        }
+        if let Some(parent) = task.parent {
+            lines.push(format!("RELATED-TO:{}", escape(&uid_of(parent))));
+        }
+        for &blocker in &task.blocked_by {
+            lines.push(format!(
+                "RELATED-TO;RELTYPE=DEPENDS-ON:{}",
+                escape(&uid_of(blocker))
+            ));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| format!("{}\r\n", fold(line)))
+        .collect()
+}
+
+fn priority_number(priority: &Priority) -> u32 {
+    match priority {
+        Priority::Urgent | Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low | Priority::Someday => 9,
+    }
+}
+
+fn from_priority_number(number: u32) -> Priority {
+    match number {
+        1..=4 => Priority::High,
+        6..=9 => Priority::Low,
+        _ => Priority::Medium,
+    }
+}
+
+fn to_rrule(rule: &Recurrence) -> String {
+    match rule {
+        Recurrence::Daily => "FREQ=DAILY".to_string(),
+        Recurrence::EveryNDays(n) => format!("FREQ=DAILY;INTERVAL={}", n),
+        Recurrence::Weekly(days) => {
+            let days: Vec<&str> = days.iter().map(|day| weekday_code(*day)).collect();
+            format!("FREQ=WEEKLY;BYDAY={}", days.join(","))
+        }
+        Recurrence::Monthly(day) => format!("FREQ=MONTHLY;BYMONTHDAY={}", day),
+    }
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    WEEKDAYS.iter().find(|(d, _)| *d == day).unwrap().1
+}
+
+// Rules other than the ones to_rrule writes give None. Weekly and monthly
+// rules without a day repeat on `anchor`'s weekday or day of month.
+fn from_rrule(value: &str, anchor: NaiveDate) -> Option<Recurrence> {
+    let mut parts = HashMap::new();
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        parts.insert(key.to_uppercase(), value.to_uppercase());
+    }
+    let interval: u32 = match parts.remove("INTERVAL") {
+        Some(n) => n.parse().ok().filter(|n| *n > 0)?,
+        None => 1,
+    };
+    let by_day = parts.remove("BYDAY");
+    let by_month_day = parts.remove("BYMONTHDAY");
+    let frequency = parts.remove("FREQ")?;
+    if !parts.is_empty() {
+        return None;
+    }
+
+    let rule = match (frequency.as_str(), interval, by_day, by_month_day) {
+        ("DAILY", 1, None, None) => "daily".to_string(),
+        ("DAILY", n, None, None) => format!("{}d", n),
+        ("WEEKLY", 1, Some(days), None) => {
+            let days: Option<Vec<String>> = days
+                .split(',')
+                .map(|code| {
+                    let day = WEEKDAYS.iter().find(|(_, c)| *c == code)?.0;
+                    Some(day.to_string().to_lowercase())
+                })
+                .collect();
+            format!("weekly:{}", days?.join(","))
+        }
+        ("WEEKLY", 1, None, None) => "weekly".to_string(),
+        ("MONTHLY", 1, None, Some(day)) => format!("monthly:{}", day),
+        ("MONTHLY", 1, None, None) => "monthly".to_string(),
+        _ => return None,
+    };
+    Recurrence::parse(&rule, anchor).ok()
+}
+
+// Escapes a TEXT value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\n', '\r'], "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Splits a list of TEXT values on the commas that aren't escaped
+fn split_list(text: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            ',' if !escaped => values.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    values.push(current);
+    values.iter().map(|value| unescape(value)).collect()
+}
+
+// Breaks a long line into continuation lines starting with a space, without
+// splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+// Joins continuation lines back up, keeping the number of each line's first
+// physical line
+fn unfold(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, previous))) => previous.push_str(rest),
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines.retain(|(_, line)| !line.trim().is_empty());
+    lines
+}
+
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+// "NAME;PARAM=VALUE;...:value", where parameter values may be quoted
+fn parse_property(line: &str) -> Result<Property, String> {
+    let mut quoted = false;
+    let split = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    });
+    let Some((colon, _)) = split else {
+        return Err(format!("expected NAME:VALUE, found '{}'", line));
+    };
+
+    let mut head = line[..colon].split(';');
+    let name = head.next().unwrap_or_default().to_uppercase();
+    let params = head
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Ok(Property {
+        name,
+        params,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+enum Time {
+    Date(NaiveDate),
+    // In the local time zone, for floating times and times with a TZID
+    Local(NaiveDateTime),
+    Utc(DateTime<Utc>),
+}
+
+fn parse_time(value: &str) -> Result<Time, String> {
+    let invalid = || format!("invalid date or time '{}'", value);
+    if value.ends_with(['Z', 'z']) {
+        NaiveDateTime::parse_from_str(&value.to_uppercase(), UTC_FORMAT)
+            .map(|time| Time::Utc(time.and_utc()))
+            .map_err(|_| invalid())
+    } else if value.contains(['T', 't']) {
+        NaiveDateTime::parse_from_str(&value.to_uppercase(), LOCAL_FORMAT)
+            .map(Time::Local)
+            .map_err(|_| invalid())
+    } else {
+        NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map(Time::Date)
+            .map_err(|_| invalid())
+    }
+}
+
+fn to_utc(time: Time) -> DateTime<Utc> {
+    match time {
+        Time::Date(date) => exchange::start_of(date),
+        Time::Local(time) => Local
+            .from_local_datetime(&time)
+            .earliest()
+            .map_or(time.and_utc(), |time| time.with_timezone(&Utc)),
+        Time::Utc(time) => time,
+    }
+}
+
+fn to_due(time: Time) -> Due {
+    let local = match time {
+        Time::Date(date) => return Due { date, time: None },
+        Time::Local(time) => time,
+        Time::Utc(time) => time.with_timezone(&Local).naive_local(),
+    };
+    Due {
+        date: local.date(),
+        time: Some(local.time()),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Vec<Imported>, ParseError> {
+    let lines = unfold(content);
+    let is = |line: &str, text: &str| line.trim().eq_ignore_ascii_case(text);
+    if !lines.iter().any(|(_, line)| is(line, "BEGIN:VCALENDAR")) {
+        return Err(ParseError {
+            line: 1,
+            message: "not an iCalendar file: BEGIN:VCALENDAR is missing".to_string(),
+        });
+    }
+
+    let mut imported = Vec::new();
+    let mut todo: Option<(usize, Vec<(usize, &str)>)> = None;
+    // Components nested inside the current VTODO, such as alarms
+    let mut nested = 0;
+    for (number, line) in &lines {
+        let upper = line.trim().to_uppercase();
+        match &mut todo {
+            None if upper == "BEGIN:VTODO" => todo = Some((*number, Vec::new())),
+            None => {}
+            Some(_) if upper.starts_with("BEGIN:") => nested += 1,
+            Some(_) if upper.starts_with("END:") && nested > 0 => nested -= 1,
+            Some((start, properties)) if upper == "END:VTODO" => {
+                imported.push(from_vtodo(*start, properties)?);
+                todo = None;
+            }
+            Some((_, properties)) if nested == 0 => properties.push((*number, line)),
+            Some(_) => {}
+        }
+    }
+    if let Some((start, _)) = todo {
+        return Err(ParseError {
+            line: start,
+            message: "VTODO is missing its END:VTODO".to_string(),
+        });
+    }
+    Ok(imported)
+}
+
+fn from_vtodo(start: usize, lines: &[(usize, &str)]) -> Result<Imported, ParseError> {
+    let mut properties = Vec::new();
+    for &(number, line) in lines {
+        let property = parse_property(line).map_err(|message| ParseError {
+            line: number,
+            message,
+        })?;
+        properties.push((number, property));
+    }
+    let find = |name: &str| properties.iter().find(|(_, p)| p.name == name);
+    let time = |name: &str| -> Result<Option<Time>, ParseError> {
+        find(name)
+            .map(|(number, p)| {
+                parse_time(&p.value).map_err(|message| ParseError {
+                    line: *number,
+                    message,
+                })
+            })
+            .transpose()
+    };
+
+    let title = find("SUMMARY").map(|(_, p)| unescape(&p.value));
+    let Some(title) = title.filter(|title| !title.trim().is_empty()) else {
+        return Err(ParseError {
+            line: start,
+            message: "VTODO has no SUMMARY".to_string(),
+        });
+    };
+    let priority = find("PRIORITY").map_or(Priority::Medium, |(_, p)| {
+        from_priority_number(p.value.trim().parse().unwrap_or(0))
+    });
+    // Only trusted while PRIORITY still agrees, as for X-TODO-STATUS
+    let own = find("X-TODO-PRIORITY").and_then(|(_, p)| Priority::from_str(p.value.trim()));
+    let priority = own
+        .filter(|own| matches!(own, Priority::Urgent | Priority::Someday))
+        .filter(|own| from_priority_number(priority_number(own)) == priority)
+        .unwrap_or(priority);
+    let mut task = Task::new(0, title.replace(['\n', '\r'], " "), priority);
+
+    task.description = find("DESCRIPTION")
+        .map(|(_, p)| unescape(&p.value))
+        .filter(|text| !text.is_empty());
+    task.project = find("X-TODO-PROJECT")
+        .and_then(|(_, p)| tags::parse_project(&format!("+{}", unescape(&p.value))));
+    for (_, property) in properties.iter().filter(|(_, p)| p.name == "CATEGORIES") {
+        for name in split_list(&property.value) {
+            if let Some(tag) = tags::parse_tag(&format!("#{}", name.trim().replace(' ', "-"))) {
+                tags::add_tag(&mut task.tags, tag);
+            }
+        }
+    }
+
+    let created = time("CREATED")?.or(time("DTSTAMP")?).map(to_utc);
+    let modified = time("LAST-MODIFIED")?.map(to_utc);
+    task.created_at = created.or(modified).unwrap_or(task.created_at);
+    let completed_at = time("COMPLETED")?.map(to_utc);
+    let status = find("STATUS").map(|(_, p)| p.value.trim().to_uppercase());
+    task.status = match status.as_deref() {
+        Some("CANCELLED") => Status::Cancelled,
+        Some("COMPLETED") => Status::Done,
+        _ if completed_at.is_some() => Status::Done,
+        Some("IN-PROCESS") => Status::InProgress,
+        _ => Status::Todo,
+    };
+    // Only trusted while STATUS still agrees, in case another app moved it on
+    if task.status == Status::Todo {
+        let own = find("X-TODO-STATUS").and_then(|(_, p)| Status::parse(&p.value).ok());
+        task.status = own
+            .filter(|status| !status.is_closed())
+            .unwrap_or(task.status);
+    }
+    if task.status.is_closed() {
+        task.completed_at = Some(completed_at.or(modified).unwrap_or_else(Utc::now));
+    }
+    task.updated_at = modified
+        .or(task.completed_at)
+        .unwrap_or(task.created_at)
+        .max(task.created_at);
+
+    task.due = time("DUE")?.map(to_due);
+    let anchor = task
+        .due
+        .map_or(exchange::local_date(&task.created_at), |due| due.date);
+    task.recurrence = find("RRULE").and_then(|(_, p)| from_rrule(&p.value, anchor));
+
+    let uid = find("UID").map(|(_, p)| unescape(p.value.trim()));
+    let id = uid.as_deref().and_then(exchange::id_from_uid);
+    task.uid = uid.filter(|uid| !uid.is_empty() && id.is_none());
+
+    let mut imported = Imported::new(id, task);
+    for (_, property) in properties.iter().filter(|(_, p)| p.name == "RELATED-TO") {
+        let related = unescape(property.value.trim());
+        match property.params.get("RELTYPE").map(|t| t.to_uppercase()) {
+            None => imported.parent_uid = Some(related),
+            Some(kind) if kind == "PARENT" => imported.parent_uid = Some(related),
+            Some(kind) if kind == "DEPENDS-ON" => imported.blocker_uids.push(related),
+            Some(_) => {}
+        }
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_keeps_every_field() {
+        let today = Local::now().date_naive();
+        let mut parent = Task::new(1, "Bills".to_string(), Priority::Medium);
+        parent.uid = Some("list-9@example.com".to_string());
+        let mut task = Task::new(4, "Pay rent, then; relax".to_string(), Priority::High);
+        task.set_description("100% of it\nby transfer \\ cheque".to_string());
+        task.set_recurrence(
+            Some(Recurrence::parse("weekly:mon,fri", today).unwrap()),
+            today,
+        );
+        task.due = Some(Due::parse("2026-11-02 17:30", today).unwrap());
+        task.add_tags(
+            &["home".to_string(), "money".to_string()],
+            Some("bills".to_string()),
+        );
+        task.parent = Some(1);
+        task.blocked_by = vec![2];
+        task.set_status(Status::Done);
+
+        let exported = export(&[&parent, &task]);
+        assert!(exported.contains("UID:todo-4@todo-app\r\n"));
+        assert!(exported.contains("RELATED-TO:list-9@example.com\r\n"));
+        assert!(exported.contains("RELATED-TO;RELTYPE=DEPENDS-ON:todo-2@todo-app\r\n"));
+        assert!(exported.lines().all(|line| line.len() <= LINE_LIMIT + 1));
+
+        let mut imported = parse(&exported).unwrap();
+        assert_eq!(imported.len(), 2);
+        let copy = imported.pop().unwrap();
+        assert_eq!(imported[0].id, None);
+        assert_eq!(imported[0].task.uid.as_deref(), Some("list-9@example.com"));
+        assert_eq!(copy.id, Some(4));
+        assert_eq!(copy.parent_uid.as_deref(), Some("list-9@example.com"));
+        assert_eq!(copy.blocker_uids, vec!["todo-2@todo-app"]);
+
+        let mut copy = copy.task;
+        copy.id = 4;
+        (copy.parent, copy.blocked_by) = (task.parent, task.blocked_by.clone());
+        let times = (task.created_at, task.updated_at, task.completed_at);
+        (copy.created_at, copy.updated_at, copy.completed_at) = times;
+        assert_eq!(copy.to_file_format(), task.to_file_format());
+
+        let review = Status::Other("review".to_string());
+        for status in [
+            Status::InProgress,
+            Status::Waiting,
+            Status::Cancelled,
+            review,
+        ] {
+            task.set_status(status.clone());
+            let copy = parse(&export(&[&task])).unwrap().pop().unwrap().task;
+            assert_eq!(copy.status, status);
+        }
+        for priority in [Priority::Urgent, Priority::Someday] {
+            task.priority = priority.clone();
+            let copy = parse(&export(&[&task])).unwrap().pop().unwrap().task;
+            assert_eq!(copy.priority, priority);
+        }
+        let other = export(&[&task]).replace("PRIORITY:9", "PRIORITY:5");
+        assert_eq!(parse(&other).unwrap()[0].task.priority, Priority::Medium);
+    }
+
+    #[test]
+    fn test_parse_other_apps_vtodo() {
+        let content = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\n\
+                       UID:abc-1\r\nSUMMARY:Renew pass\r\n port\r\nPRIORITY:2\r\n\
+                       DUE;TZID=Europe/Paris:20261120T090000\r\nRRULE:FREQ=YEARLY\r\n\
+                       CATEGORIES:Travel,Admin\r\nBEGIN:VALARM\r\nSUMMARY:Alarm\r\n\
+                       END:VALARM\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        let imported = parse(content).unwrap().pop().unwrap();
+        let task = imported.task;
+
+        assert_eq!(imported.id, None);
+        assert_eq!(task.uid.as_deref(), Some("abc-1"));
+        assert_eq!(task.title, "Renew passport");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due.unwrap().to_file_format(), "2026-11-20 09:00");
+        assert_eq!(task.recurrence, None);
+        assert_eq!(task.tags, vec!["admin", "travel"]);
+        assert_eq!(task.status, Status::Todo);
+
+        assert!(parse("BEGIN:VCALENDAR\nBEGIN:VTODO\nEND:VTODO\nEND:VCALENDAR").is_err());
+        assert!(parse("SUMMARY:Not a calendar").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// Crash-safe saving
+//
+// Saving doesn't rewrite the whole todo file every time. Instead each save
+// appends the tasks that changed to `<file>.journal`, one entry per line:
+// `+` and a task record for a task that was added or changed, or `-` and an
+// id for one that was removed. Loading reads the todo file and replays the
+// journal on top of it. Appends only ever add whole lines and are flushed to
+// disk before a save returns, so a crash can at worst leave an incomplete
+// last line, which replay drops.
+//
+// Once the journal holds COMPACT_AFTER entries the todo file is rewritten
+// with every task and the journal is removed. Rewrites go through
+// write_atomic: the content is written to a temporary file, flushed to disk,
+// and renamed over the old file, so the todo file is always either entirely
+// the old version or entirely the new one. A crash between the rewrite and
+// removing the journal is harmless, since replaying the journal again gives
+// the same tasks.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::record::{ParseError, FORMAT_VERSION};
+use crate::{parse_id, Task};
+
+pub const COMPACT_AFTER: usize = 100;
+const HEADER_PREFIX: &str = "# todo-app journal v";
+
+pub enum Entry {
+    Put(Box<Task>),
+    Delete(u32),
+}
+
+impl Entry {
+    pub fn encode(&self) -> String {
+        match self {
+            Entry::Put(task) => format!("+ {}", task.to_file_format()),
+            Entry::Delete(id) => format!("- {}", id),
+        }
+    }
+
+    fn decode(line: &str) -> Result<Entry, String> {
+        match line.split_once(' ') {
+            Some(("+", record)) => {
+                Task::from_file_format(record).map(|task| Entry::Put(Box::new(task)))
+            }
+            Some(("-", id)) => parse_id(id).map(Entry::Delete),
+            _ => Err("journal entry must start with '+' or '-'".to_string()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Replay {
+    pub entries: Vec<Entry>,
+    pub errors: Vec<ParseError>,
+    // A partial last line was dropped
+    pub torn: bool,
+}
+
+pub fn path(file_path: &str) -> String {
+    format!("{}.journal", file_path)
+}
+
+pub fn read(path: &str) -> Result<Replay, String> {
+    let mut replay = Replay::default();
+    if !Path::new(path).exists() {
+        return Ok(replay);
+    }
+    // Read lossily: a torn write may have split a multi-byte character
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    // Everything after the last newline was cut short by a crash
+    let complete = match content.rfind('\n') {
+        Some(end) => &content[..=end],
+        None => "",
+    };
+    replay.torn = complete.len() < content.len();
+
+    for (index, line) in complete.lines().enumerate() {
+        if let Some(version) = line.strip_prefix(HEADER_PREFIX) {
+            match version.trim().parse::<u32>() {
+                Ok(version) if version <= FORMAT_VERSION => continue,
+                _ => {
+                    return Err(format!(
+                        "journal uses format v{}, but this todo understands up to v{}",
+                        version.trim(),
+                        FORMAT_VERSION
+                    ))
+                }
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Entry::decode(line) {
+            Ok(entry) => replay.entries.push(entry),
+            Err(message) => replay.errors.push(ParseError {
+                line: index + 1,
+                message,
+            }),
+        }
+    }
+
+    Ok(replay)
+}
+
+// Appends entries as whole lines and flushes them to disk. A new journal
+// starts with a header naming the record format of its entries.
+pub fn append(path: &str, entries: &[Entry]) -> io::Result<()> {
+    let mut content = String::new();
+    if !Path::new(path).exists() {
+        content.push_str(&format!("{}{}\n", HEADER_PREFIX, FORMAT_VERSION));
+    }
+    for entry in entries {
+        content.push_str(&entry.encode());
+        content.push('\n');
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_data()
+}
+
+pub fn remove(path: &str) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => sync_parent(Path::new(path)),
+    }
+}
+
+// Replaces `path` with `content` so that readers and crashes only ever see
+// the old or the new file
+pub fn write_atomic(path: &str, content: &str) -> io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)?;
+    sync_parent(Path::new(path))
+}
+
+// Makes a rename or removal in the file's directory durable
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    fn temp_path(name: &str) -> String {
+        let file_name = format!("todo-journal-{}-{}", name, std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let path = temp_path("append");
+        let task = Task::new(4, "Water | plants".to_string(), Priority::Low);
+        append(&path, &[Entry::Put(Box::new(task))]).unwrap();
+        append(&path, &[Entry::Delete(2)]).unwrap();
+
+        let replay = read(&path).unwrap();
+        assert!(!replay.torn && replay.errors.is_empty());
+        assert!(matches!(&replay.entries[0], Entry::Put(t) if t.title == "Water | plants"));
+        assert!(matches!(replay.entries[1], Entry::Delete(2)));
+        remove(&path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_last_line_is_dropped() {
+        let path = temp_path("torn");
+        append(&path, &[Entry::Delete(1)]).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"+ id=2|title=half writ").unwrap();
+
+        let replay = read(&path).unwrap();
+        assert!(replay.torn);
+        assert_eq!(replay.entries.len(), 1);
+        assert!(replay.errors.is_empty());
+        remove(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let path = temp_path("atomic");
+        write_atomic(&path, "old\n").unwrap();
+        write_atomic(&path, "new\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+        remove(&path).unwrap();
+    }
+}
@@ -1,41 +1,124 @@
 // Todo Application Project
 // A command-line todo list manager with file persistence
 
-use std::collections::HashMap;
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use clap::Parser;
+
+mod cli;
+mod config;
+mod due;
+mod edit;
+mod exchange;
+mod history;
+mod ical;
+mod journal;
+mod markdown;
+mod output;
+mod query;
+mod record;
+mod recurrence;
+mod render;
+mod repl;
+mod reports;
+mod server;
+mod shelf;
+mod sqlite;
+mod store;
+mod sync;
+mod tags;
+mod todotxt;
+mod tracking;
+mod tui;
+mod workflow;
+
+use config::Config;
+use due::Due;
+use history::{History, Operation};
+use journal::Entry;
+use query::{Query, QueryError};
+use record::Record;
+use recurrence::Recurrence;
+use render::{Layout, Renderer, Style};
+use shelf::Shelf;
+use store::{Notice, TaskStore};
+use tracking::Session;
+use workflow::{Status, Workflow};
+
+// The five priority levels, with their names, emoji and colors fixed here.
+// The config (config.rs) only picks which are offered; urgent and someday
+// aren't unless it enables them.
 #[derive(Debug, Clone, PartialEq)]
 enum Priority {
+    Someday,
     Low,
     Medium,
     High,
+    Urgent,
 }
 
 impl Priority {
+    // Most important first
+    const ALL: [Priority; 5] = [
+        Priority::Urgent,
+        Priority::High,
+        Priority::Medium,
+        Priority::Low,
+        Priority::Someday,
+    ];
+    
     fn from_str(s: &str) -> Option<Priority> {
         match s.to_lowercase().as_str() {
+            "someday" | "s" => Some(Priority::Someday),
             "low" | "l" => Some(Priority::Low),
             "medium" | "med" | "m" => Some(Priority::Medium),
             "high" | "h" => Some(Priority::High),
+            "urgent" | "u" => Some(Priority::Urgent),
             _ => None,
         }
     }
     
     fn to_string(&self) -> &str {
         match self {
+            Priority::Someday => "Someday",
             Priority::Low => "Low",
             Priority::Medium => "Medium",
             Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+    
+    fn rank(&self) -> u8 {
+        match self {
+            Priority::Urgent => 0,
+            Priority::High => 1,
+            Priority::Medium => 2,
+            Priority::Low => 3,
+            Priority::Someday => 4,
         }
     }
     
     fn emoji(&self) -> &str {
         match self {
+            Priority::Someday => "⚪",
             Priority::Low => "🟢",
             Priority::Medium => "🟡",
             Priority::High => "🔴",
+            Priority::Urgent => "🚨",
+        }
+    }
+    
+    // SGR color for the color style
+    fn color(&self) -> &str {
+        match self {
+            Priority::Someday => "2",
+            Priority::Low => "32",
+            Priority::Medium => "33",
+            Priority::High => "31",
+            Priority::Urgent => "1;31",
         }
     }
 }
@@ -45,399 +128,1694 @@ struct Task {
     id: u32,
     title: String,
     description: Option<String>,
-    completed: bool,
+    status: Status,
     priority: Priority,
-    created_at: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    due: Option<Due>,
+    recurrence: Option<Recurrence>,
+    tags: Vec<String>,
+    project: Option<String>,
+    parent: Option<u32>,
+    blocked_by: Vec<u32>,
+    // Identifier given by another app, kept so its copy can be matched up
+    uid: Option<String>,
+    // Work sessions recorded with `start` and `stop`, oldest first
+    sessions: Vec<Session>,
 }
 
 impl Task {
     fn new(id: u32, title: String, priority: Priority) -> Self {
+        let now = Utc::now();
         Task {
             id,
             title,
             description: None,
-            completed: false,
+            status: Status::Todo,
             priority,
-            created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            due: None,
+            recurrence: None,
+            tags: Vec::new(),
+            project: None,
+            parent: None,
+            blocked_by: Vec::new(),
+            uid: None,
+            sessions: Vec::new(),
         }
     }
     
     fn set_description(&mut self, description: String) {
         self.description = Some(description);
+        self.updated_at = Utc::now();
+    }
+    
+    fn set_due(&mut self, due: Option<Due>) {
+        self.due = due;
+        self.updated_at = Utc::now();
+    }
+    
+    // A recurring task with no due date gets its first occurrence as the due date
+    fn set_recurrence(&mut self, recurrence: Option<Recurrence>, today: NaiveDate) {
+        if let (None, Some(rule)) = (&self.due, &recurrence) {
+            self.due = rule
+                .first_on_or_after(today)
+                .map(|date| Due { date, time: None });
+        }
+        self.recurrence = recurrence;
+        self.updated_at = Utc::now();
+    }
+    
+    // The follow-up task for a completed occurrence, due on the rule's next
+    // date; None when there's no date left to repeat on
+    fn next_occurrence(&self, id: u32, rule: &Recurrence, today: NaiveDate) -> Option<Task> {
+        let base = self.due.map_or(today, |due| due.date);
+        let date = rule.next_from(base, today)?;
+        let mut next = Task::new(id, self.title.clone(), self.priority.clone());
+        next.description = self.description.clone();
+        next.due = Some(Due {
+            date,
+            time: self.due.and_then(|due| due.time),
+        });
+        next.recurrence = Some(rule.clone());
+        next.tags = self.tags.clone();
+        next.project = self.project.clone();
+        Some(next)
+    }
+    
+    fn add_tags(&mut self, new_tags: &[String], project: Option<String>) {
+        for tag in new_tags {
+            tags::add_tag(&mut self.tags, tag.clone());
+        }
+        if project.is_some() {
+            self.project = project;
+        }
+        self.updated_at = Utc::now();
+    }
+    
+    fn remove_tags(&mut self, old_tags: &[String], clear_project: bool) {
+        self.tags.retain(|tag| !old_tags.contains(tag));
+        if clear_project {
+            self.project = None;
+        }
+        self.updated_at = Utc::now();
+    }
+    
+    fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        !self.status.is_closed() && self.due.is_some_and(|due| due.is_overdue(now))
     }
     
-    fn toggle_completed(&mut self) {
-        self.completed = !self.completed;
+    fn is_timing(&self) -> bool {
+        self.sessions.last().is_some_and(|session| session.is_running())
+    }
+    
+    // Ends the running work session, if there is one
+    fn stop_timer(&mut self, now: DateTime<Utc>) -> bool {
+        match self.sessions.last_mut().filter(|session| session.is_running()) {
+            Some(session) => {
+                session.stop(now);
+                true
+            }
+            None => false,
+        }
     }
     
+    fn is_done(&self) -> bool {
+        self.status == Status::Done
+    }
+    
+    // Closing a task records when; reopening it clears that
+    fn set_status(&mut self, status: Status) {
+        let now = Utc::now();
+        if !status.is_closed() {
+            self.completed_at = None;
+        } else if !self.status.is_closed() {
+            self.completed_at = Some(now);
+        }
+        self.status = status;
+        self.updated_at = now;
+    }
+    
+    // "done" is only read, from files written before tasks had a status
+    const FIELDS: [&'static str; 17] = [
+        "id", "title", "desc", "status", "priority", "created", "updated", "completed", "due",
+        "repeat", "tags", "project", "parent", "blocked_by", "uid", "time", "done",
+    ];
+
     fn to_file_format(&self) -> String {
-        format!(
-            "{}|{}|{}|{}|{}|{}",
-            self.id,
-            self.title,
-            self.description.as_deref().unwrap_or(""),
-            self.completed,
-            self.priority.to_string(),
-            self.created_at
-        )
-    }
-    
-    fn from_file_format(line: &str) -> Option<Task> {
+        let mut record = Record::new();
+        record.push("id", &self.id.to_string());
+        record.push("title", &self.title);
+        if let Some(desc) = &self.description {
+            record.push("desc", desc);
+        }
+        record.push("status", self.status.name());
+        record.push("priority", self.priority.to_string());
+        record.push("created", &format_timestamp(&self.created_at));
+        record.push("updated", &format_timestamp(&self.updated_at));
+        if let Some(completed_at) = &self.completed_at {
+            record.push("completed", &format_timestamp(completed_at));
+        }
+        if let Some(due) = &self.due {
+            record.push("due", &due.to_file_format());
+        }
+        if let Some(rule) = &self.recurrence {
+            record.push("repeat", &rule.to_file_format());
+        }
+        if !self.tags.is_empty() {
+            record.push("tags", &self.tags.join(","));
+        }
+        if let Some(project) = &self.project {
+            record.push("project", project);
+        }
+        if let Some(parent) = self.parent {
+            record.push("parent", &parent.to_string());
+        }
+        if !self.blocked_by.is_empty() {
+            let ids: Vec<String> = self.blocked_by.iter().map(|id| id.to_string()).collect();
+            record.push("blocked_by", &ids.join(","));
+        }
+        if let Some(uid) = &self.uid {
+            record.push("uid", uid);
+        }
+        if !self.sessions.is_empty() {
+            record.push("time", &tracking::encode(&self.sessions));
+        }
+        record.encode()
+    }
+    
+    fn from_file_format(line: &str) -> Result<Task, String> {
+        let record = Record::decode(line, &Task::FIELDS)?;
+        let created_at = parse_timestamp(record.require("created")?)?;
+        
+        Ok(Task {
+            id: parse_id(record.require("id")?)?,
+            title: record.require("title")?.to_string(),
+            description: record.get("desc").map(|d| d.to_string()),
+            status: match record.get("status") {
+                Some(status) => Status::parse(status)?,
+                None => parse_done(record.require("done")?)?,
+            },
+            priority: parse_priority(record.require("priority")?)?,
+            created_at,
+            // Format v2 files predate these fields
+            updated_at: record
+                .get("updated")
+                .map(parse_timestamp)
+                .transpose()?
+                .unwrap_or(created_at),
+            completed_at: record.get("completed").map(parse_timestamp).transpose()?,
+            due: record.get("due").map(Due::from_file_format).transpose()?,
+            recurrence: record
+                .get("repeat")
+                .map(Recurrence::from_file_format)
+                .transpose()?,
+            tags: record.get("tags").map(parse_tags).transpose()?.unwrap_or_default(),
+            project: record.get("project").map(|p| p.to_string()),
+            parent: record.get("parent").map(parse_id).transpose()?,
+            blocked_by: match record.get("blocked_by") {
+                Some(ids) => ids.split(',').map(parse_id).collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            },
+            uid: record.get("uid").map(|uid| uid.to_string()),
+            sessions: record.get("time").map(tracking::decode).transpose()?.unwrap_or_default(),
+        })
+    }
+    
+    // Reads the original unescaped `id|title|description|completed|priority|created_at` layout
+    fn from_legacy_format(line: &str) -> Result<Task, String> {
         let parts: Vec<&str> = line.split('|').collect();
         if parts.len() != 6 {
-            return None;
+            return Err(format!("expected 6 fields, found {}", parts.len()));
         }
         
-        let id = parts[0].parse().ok()?;
-        let title = parts[1].to_string();
         let description = if parts[2].is_empty() {
             None
         } else {
             Some(parts[2].to_string())
         };
-        let completed = parts[3].parse().ok()?;
-        let priority = Priority::from_str(parts[4])?;
-        let created_at = parts[5].to_string();
+        let created_at = parse_timestamp(parts[5])?;
         
-        Some(Task {
-            id,
-            title,
+        Ok(Task {
+            id: parse_id(parts[0])?,
+            title: parts[1].to_string(),
             description,
-            completed,
-            priority,
+            status: parse_done(parts[3])?,
+            priority: parse_priority(parts[4])?,
             created_at,
+            updated_at: created_at,
+            completed_at: None,
+            due: None,
+            recurrence: None,
+            tags: Vec::new(),
+            project: None,
+            parent: None,
+            blocked_by: Vec::new(),
+            uid: None,
+            sessions: Vec::new(),
         })
     }
 }
 
+fn parse_id(s: &str) -> Result<u32, String> {
+    s.parse().map_err(|_| format!("invalid id '{}'", s))
+}
+
+// The done flag of older files, as a status
+fn parse_done(s: &str) -> Result<Status, String> {
+    match s.parse() {
+        Ok(true) => Ok(Status::Done),
+        Ok(false) => Ok(Status::Todo),
+        Err(_) => Err(format!("invalid done flag '{}' (expected true or false)", s)),
+    }
+}
+
+fn parse_priority(s: &str) -> Result<Priority, String> {
+    Priority::from_str(s).ok_or_else(|| format!("unknown priority '{}'", s))
+}
+
+fn parse_tags(s: &str) -> Result<Vec<String>, String> {
+    let mut parsed = Vec::new();
+    for tag in s.split(',') {
+        let tag = tags::parse_tag(&format!("#{}", tag))
+            .ok_or_else(|| format!("invalid tag '{}'", tag))?;
+        tags::add_tag(&mut parsed, tag);
+    }
+    Ok(parsed)
+}
+
+// Timestamps are stored as RFC 3339 in UTC. Older files used "%Y-%m-%d %H:%M:%S",
+// which we read back as UTC.
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").map(|dt| dt.and_utc()))
+        .map_err(|_| format!("invalid timestamp '{}'", s))
+}
+
+fn format_timestamp(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// In local time, e.g. with Config::time_format
+fn format_local(dt: &DateTime<Utc>, format: &str) -> String {
+    dt.with_timezone(&Local).format(format).to_string()
+}
+
+// Overdue tasks first, then by priority (High -> Medium -> Low), then by ID
+fn compare_tasks(a: &Task, b: &Task, now: NaiveDateTime) -> Ordering {
+    b.is_overdue(now)
+        .cmp(&a.is_overdue(now))
+        .then(a.priority.rank().cmp(&b.priority.rank()))
+        .then(a.id.cmp(&b.id))
+}
+
+fn sort_tasks(tasks: &mut [&Task], now: NaiveDateTime) {
+    tasks.sort_by(|a, b| compare_tasks(a, b, now));
+}
+
+fn format_ids(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| format!("#{}", id))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TaskError {
+    NotFound(u32),
+    OpenSubtasks(u32, Vec<u32>),
+    Cycle(String),
+    // Time can't be tracked on a closed task
+    Closed(u32, Status),
+    AlreadyTiming(u32),
+    // A move the workflow doesn't allow
+    Transition(String),
+    // A recurring task's rule has no date left to repeat on
+    NoNextOccurrence(u32),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskError::NotFound(id) => write!(f, "Task #{} not found", id),
+            TaskError::OpenSubtasks(id, open) => write!(
+                f,
+                "Task #{} has open subtasks ({}); finish them first or use --force",
+                id,
+                format_ids(open)
+            ),
+            TaskError::Cycle(message) => write!(f, "{}", message),
+            TaskError::Closed(id, status) => write!(
+                f,
+                "Task #{} is {}; reopen it to track time on it",
+                id,
+                status.name()
+            ),
+            TaskError::AlreadyTiming(id) => write!(f, "Task #{} is already being timed", id),
+            TaskError::Transition(message) => write!(f, "{}", message),
+            TaskError::NoNextOccurrence(id) => {
+                write!(f, "Task #{} has no date left to repeat on", id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SaveError {
+    Io(String),
+    // Another process changed the same tasks; the latest ones were reloaded
+    Conflict(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(message) | SaveError::Conflict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for SaveError {
+    fn from(message: String) -> Self {
+        SaveError::Io(message)
+    }
+}
+
+// Changes are kept in memory and marked dirty; whoever runs a command calls
+// save_to_file afterwards so a failed save can be reported.
 struct TodoApp {
     tasks: HashMap<u32, Task>,
     next_id: u32,
-    file_path: String,
+    store: Box<dyn TaskStore>,
+    dirty: bool,
+    history: History,
+    // Task records as last read from or written to the store
+    saved: HashMap<u32, String>,
+    workflow: Workflow,
+    config: Config,
+    renderer: Renderer,
+    // Removed and archived tasks, and the tasks this change archives (shelf.rs)
+    trash: Shelf,
+    archive: Shelf,
+    archiving: HashSet<u32>,
 }
 
 impl TodoApp {
-    fn new(file_path: String) -> Self {
+    // The app for a file, warning about anything odd in it the way the renderer shows them
+    fn open(file_path: String, renderer: Renderer) -> Result<Self, String> {
         let mut app = TodoApp {
             tasks: HashMap::new(),
             next_id: 1,
-            file_path,
+            store: store::open(&file_path),
+            dirty: false,
+            history: History::default(),
+            saved: HashMap::new(),
+            workflow: Workflow::default(),
+            config: Config::default(),
+            renderer,
+            trash: Shelf::new(&file_path, shelf::TRASH),
+            archive: Shelf::new(&file_path, shelf::ARCHIVE),
+            archiving: HashSet::new(),
         };
-        app.load_from_file();
-        app
+        app.load_from_file()?;
+        Ok(app)
     }
     
-    fn add_task(&mut self, title: String, priority: Priority) -> u32 {
-        let task = Task::new(self.next_id, title, priority);
+    #[cfg(test)]
+    fn new(file_path: String) -> Result<Self, String> {
+        TodoApp::open(file_path, Renderer::default())
+    }
+    
+    fn add_task(
+        &mut self,
+        title: String,
+        priority: Priority,
+        due: Option<Due>,
+        recurrence: Option<Recurrence>,
+    ) -> u32 {
+        let extracted = tags::extract(&title);
+        let mut task = Task::new(self.next_id, extracted.title, priority);
+        task.tags = extracted.tags;
+        task.project = extracted.project;
+        task.due = due;
+        task.set_recurrence(recurrence, Local::now().date_naive());
         let id = task.id;
         self.tasks.insert(id, task);
         self.next_id += 1;
-        self.save_to_file();
+        self.dirty = true;
         id
     }
     
+    // Subtasks of a removed task move up to its parent, and anything it was
+    // blocking is unblocked
     fn remove_task(&mut self, id: u32) -> bool {
-        if self.tasks.remove(&id).is_some() {
-            self.save_to_file();
+        if let Some(removed) = self.tasks.remove(&id) {
+            for task in self.tasks.values_mut() {
+                if task.parent == Some(id) {
+                    task.parent = removed.parent;
+                }
+                task.blocked_by.retain(|&blocker| blocker != id);
+            }
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+    
+    fn children(&self, id: u32) -> Vec<u32> {
+        let mut children: Vec<u32> = self
+            .tasks
+            .values()
+            .filter(|t| t.parent == Some(id))
+            .map(|t| t.id)
+            .collect();
+        children.sort();
+        children
+    }
+    
+    // Open subtasks at any depth
+    fn open_descendants(&self, id: u32) -> Vec<u32> {
+        let mut open = Vec::new();
+        let mut stack = self.children(id);
+        let mut seen = HashSet::new();
+        while let Some(child) = stack.pop() {
+            if !seen.insert(child) {
+                continue;
+            }
+            if !self.tasks[&child].status.is_closed() {
+                open.push(child);
+            }
+            stack.extend(self.children(child));
+        }
+        open.sort();
+        open
+    }
+    
+    // Blockers that still exist and aren't closed
+    fn open_blockers(&self, task: &Task) -> Vec<u32> {
+        task.blocked_by
+            .iter()
+            .copied()
+            .filter(|id| self.tasks.get(id).is_some_and(|t| !t.status.is_closed()))
+            .collect()
+    }
+    
+    fn is_ancestor(&self, ancestor: u32, id: u32) -> bool {
+        let mut seen = HashSet::new();
+        let mut current = self.tasks.get(&id).and_then(|t| t.parent);
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+            if !seen.insert(parent) {
+                break;
+            }
+            current = self.tasks.get(&parent).and_then(|t| t.parent);
+        }
+        false
+    }
+    
+    // True if `from` waits on `target`, directly or through other blockers
+    fn depends_on(&self, from: u32, target: u32) -> bool {
+        let mut stack = vec![from];
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == target {
+                return true;
+            }
+            if seen.insert(id) {
+                if let Some(task) = self.tasks.get(&id) {
+                    stack.extend(&task.blocked_by);
+                }
+            }
+        }
+        false
+    }
+    
+    fn set_parent(&mut self, id: u32, parent: Option<u32>) -> Result<(), TaskError> {
+        if !self.tasks.contains_key(&id) {
+            return Err(TaskError::NotFound(id));
+        }
+        if let Some(parent) = parent {
+            if !self.tasks.contains_key(&parent) {
+                return Err(TaskError::NotFound(parent));
+            }
+            if parent == id || self.is_ancestor(id, parent) {
+                return Err(TaskError::Cycle(format!(
+                    "Task #{} can't be a subtask of #{}: #{} is already below #{}",
+                    id, parent, parent, id
+                )));
+            }
+        }
+        
+        let task = self.tasks.get_mut(&id).unwrap();
+        task.parent = parent;
+        task.updated_at = Utc::now();
+        self.dirty = true;
+        Ok(())
+    }
+    
+    fn add_blocker(&mut self, id: u32, blocker: u32) -> Result<(), TaskError> {
+        for task_id in [id, blocker] {
+            if !self.tasks.contains_key(&task_id) {
+                return Err(TaskError::NotFound(task_id));
+            }
+        }
+        if self.depends_on(blocker, id) {
+            return Err(TaskError::Cycle(format!(
+                "Task #{} can't be blocked by #{}: #{} already waits on #{}",
+                id, blocker, blocker, id
+            )));
+        }
+        
+        let task = self.tasks.get_mut(&id).unwrap();
+        if let Err(pos) = task.blocked_by.binary_search(&blocker) {
+            task.blocked_by.insert(pos, blocker);
+            task.updated_at = Utc::now();
+        }
+        self.dirty = true;
+        Ok(())
+    }
+    
+    fn remove_blocker(&mut self, id: u32, blocker: u32) -> Result<(), TaskError> {
+        let task = self.tasks.get_mut(&id).ok_or(TaskError::NotFound(id))?;
+        task.blocked_by.retain(|&b| b != blocker);
+        task.updated_at = Utc::now();
+        self.dirty = true;
+        Ok(())
+    }
+    
+    // Completes an open task or reopens a closed one; see set_status
+    fn toggle_task(&mut self, id: u32, force: bool) -> Result<Option<u32>, TaskError> {
+        let task = self.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+        let status = if task.status.is_closed() { Status::Todo } else { Status::Done };
+        self.set_status(id, status, force)
+    }
+    
+    // Changes a task's status along the workflow's transitions
+    fn move_task(
+        &mut self,
+        id: u32,
+        status: Status,
+        force: bool,
+    ) -> Result<Option<u32>, TaskError> {
+        let from = &self.tasks.get(&id).ok_or(TaskError::NotFound(id))?.status;
+        if *from != status && !self.workflow.allows(from, &status) {
+            return Err(TaskError::Transition(self.workflow.refusal(id, from, &status)));
+        }
+        self.set_status(id, status, force)
+    }
+    
+    // Completing a task with open subtasks fails unless `force` is set.
+    // Completing a recurring task moves its rule onto a newly created next
+    // occurrence, whose id is returned. Closing a task stops its timer.
+    fn set_status(
+        &mut self,
+        id: u32,
+        status: Status,
+        force: bool,
+    ) -> Result<Option<u32>, TaskError> {
+        let task = self.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+        if task.status == status {
+            return Ok(None);
+        }
+        if status == Status::Done && !force {
+            let open = self.open_descendants(id);
+            if !open.is_empty() {
+                return Err(TaskError::OpenSubtasks(id, open));
+            }
+        }
+        let next = match (&task.recurrence, &status) {
+            (Some(rule), Status::Done) => Some(
+                task.next_occurrence(self.next_id, rule, Local::now().date_naive())
+                    .ok_or(TaskError::NoNextOccurrence(id))?,
+            ),
+            _ => None,
+        };
+        
+        let task = self.tasks.get_mut(&id).unwrap();
+        if status.is_closed() {
+            task.stop_timer(Utc::now());
+        }
+        task.set_status(status);
+        if next.is_some() {
+            task.recurrence = None;
+        }
+        
+        let next_id = next.map(|task| {
+            let id = task.id;
+            self.tasks.insert(id, task);
+            self.next_id += 1;
+            id
+        });
+        
+        self.dirty = true;
+        Ok(next_id)
+    }
+    
+    // Starts a work session on a task. Only one runs at a time, so any other
+    // running session is stopped first and its task's id returned.
+    fn start_timer(&mut self, id: u32) -> Result<Option<u32>, TaskError> {
+        let task = self.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+        if task.status.is_closed() {
+            return Err(TaskError::Closed(id, task.status.clone()));
+        }
+        if task.is_timing() {
+            return Err(TaskError::AlreadyTiming(id));
+        }
+        
+        let now = Utc::now();
+        let stopped = self.stop_timer();
+        self.tasks.get_mut(&id).unwrap().sessions.push(Session::start(now));
+        self.dirty = true;
+        Ok(stopped)
+    }
+    
+    // Stops the running work session and returns its task's id
+    fn stop_timer(&mut self) -> Option<u32> {
+        let now = Utc::now();
+        let mut stopped = None;
+        for task in self.tasks.values_mut() {
+            if task.stop_timer(now) {
+                stopped = Some(task.id);
+            }
+        }
+        if stopped.is_some() {
+            self.dirty = true;
+        }
+        stopped
+    }
+    
+    // #tag and +project words in the new title become tags and the project,
+    // as they do for add_task
+    fn rename_task(&mut self, id: u32, title: &str) -> bool {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            let extracted = tags::extract(title);
+            task.add_tags(&extracted.tags, extracted.project);
+            task.title = extracted.title;
+            self.dirty = true;
             true
         } else {
             false
         }
     }
     
-    fn toggle_task(&mut self, id: u32) -> bool {
+    fn set_priority(&mut self, id: u32, priority: Priority) -> bool {
         if let Some(task) = self.tasks.get_mut(&id) {
-            task.toggle_completed();
-            self.save_to_file();
+            task.priority = priority;
+            task.updated_at = Utc::now();
+            self.dirty = true;
             true
         } else {
             false
         }
     }
     
+    // A blank description clears it
     fn update_description(&mut self, id: u32, description: String) -> bool {
         if let Some(task) = self.tasks.get_mut(&id) {
-            task.set_description(description);
-            self.save_to_file();
+            if description.trim().is_empty() {
+                task.description = None;
+                task.updated_at = Utc::now();
+            } else {
+                task.set_description(description);
+            }
+            self.dirty = true;
             true
         } else {
             false
         }
     }
     
-    fn list_tasks(&self, filter: Option<&str>) {
-        let mut tasks: Vec<&Task> = self.tasks.values().collect();
-        
-        // Apply filter
-        match filter {
-            Some("completed") => tasks.retain(|t| t.completed),
-            Some("pending") => tasks.retain(|t| !t.completed),
-            Some("high") => tasks.retain(|t| matches!(t.priority, Priority::High)),
-            Some("medium") => tasks.retain(|t| matches!(t.priority, Priority::Medium)),
-            Some("low") => tasks.retain(|t| matches!(t.priority, Priority::Low)),
-            _ => {}
+    fn set_recurrence(&mut self, id: u32, recurrence: Option<Recurrence>) -> bool {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.set_recurrence(recurrence, Local::now().date_naive());
+            self.dirty = true;
+            true
+        } else {
+            false
         }
-        
-        // Sort by priority (High -> Medium -> Low) then by ID
-        tasks.sort_by(|a, b| {
-            let priority_order = |p: &Priority| match p {
-                Priority::High => 0,
-                Priority::Medium => 1,
-                Priority::Low => 2,
-            };
-            
-            priority_order(&a.priority)
-                .cmp(&priority_order(&b.priority))
-                .then(a.id.cmp(&b.id))
-        });
+    }
+    
+    fn set_due(&mut self, id: u32, due: Option<Due>) -> bool {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.set_due(due);
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+    
+    fn tag_task(&mut self, id: u32, tags: &[String], project: Option<String>) -> bool {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.add_tags(tags, project);
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+    
+    fn untag_task(&mut self, id: u32, tags: &[String], clear_project: bool) -> bool {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.remove_tags(tags, clear_project);
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+    
+    // Brings a task back from the trash or the archive. Its parent and
+    // blockers are kept if they're still in the todo list.
+    fn restore_task(&mut self, id: u32) -> Result<(), TaskError> {
+        let shelved = self.trash.tasks.get(&id).or_else(|| self.archive.tasks.get(&id));
+        let mut task = shelved.cloned().ok_or(TaskError::NotFound(id))?;
+        task.parent = task.parent.filter(|parent| self.tasks.contains_key(parent));
+        task.blocked_by.retain(|blocker| self.tasks.contains_key(blocker));
+        task.updated_at = Utc::now();
+        self.tasks.insert(id, task);
+        self.dirty = true;
+        Ok(())
+    }
+    
+    // Closed tasks finished before `cutoff`, which archive_tasks would move
+    fn archivable(&self, cutoff: DateTime<Utc>) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .tasks
+            .values()
+            .filter(|t| t.status.is_closed() && t.completed_at.unwrap_or(t.updated_at) < cutoff)
+            .map(|t| t.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    // Moves tasks to the archive when the change is saved
+    fn archive_tasks(&mut self, ids: &[u32]) {
+        for &id in ids {
+            if self.remove_task(id) {
+                self.archiving.insert(id);
+            }
+        }
+    }
+    
+    // Deletes tasks in the trash for good
+    fn purge(&mut self, ids: &[u32]) -> Result<(), String> {
+        let _lock = sync::lock(self.file_path())?;
+        let notices = self.trash.reload()?;
+        self.notify(notices);
+        self.trash.write(ids.iter().map(|&id| Entry::Delete(id)).collect())
+    }
+    
+    // Tasks matching a query (see query.rs), sorted and limited as it asks.
+    // Without a sort: clause the usual list order applies.
+    fn find_tasks(&self, filter: Option<&str>) -> Result<Vec<&Task>, QueryError> {
+        search(&self.tasks, filter)
+    }
+    
+    // Archived tasks matching a query, as for find_tasks
+    fn find_archived(&self, filter: Option<&str>) -> Result<Vec<&Task>, QueryError> {
+        search(&self.archive.tasks, filter)
+    }
+    
+    fn list_tasks(&self, filter: Option<&str>, layout: Layout) -> Result<(), QueryError> {
+        let now = Local::now().naive_local();
+        let tasks = self.find_tasks(filter)?;
         
         if tasks.is_empty() {
             println!("No tasks found.");
-            return;
+            return Ok(());
         }
         
-        println!("\n📋 Todo List:");
-        println!("{:-<80}", "");
+        println!("\n{}", self.renderer.prefixed("📋", "Todo List:"));
+        println!("{}", self.renderer.rule());
         
-        for task in tasks {
-            let status = if task.completed { "✅" } else { "⏳" };
-            let priority_emoji = task.priority.emoji();
-            
-            println!(
-                "{} {} [{}] {} - {}",
-                status, priority_emoji, task.id, task.title, task.priority.to_string()
-            );
-            
-            if let Some(desc) = &task.description {
-                println!("    📝 {}", desc);
+        // Subtasks are printed under their parent when both are listed
+        let listed: HashSet<u32> = tasks.iter().map(|t| t.id).collect();
+        let mut printed = HashSet::new();
+        for task in &tasks {
+            if task.parent.is_none_or(|parent| !listed.contains(&parent)) {
+                self.print_tree(task, &tasks, 0, now, layout, &mut printed);
+            }
+        }
+        // Anything left over sits in a parent cycle from a hand-edited file
+        for task in &tasks {
+            if !printed.contains(&task.id) {
+                self.print_tree(task, &tasks, 0, now, layout, &mut printed);
             }
-            
-            println!("    🕒 Created: {}", task.created_at);
-            println!();
         }
+        Ok(())
     }
     
-    fn get_stats(&self) -> (usize, usize, usize) {
-        let total = self.tasks.len();
-        let completed = self.tasks.values().filter(|t| t.completed).count();
-        let pending = total - completed;
-        (total, completed, pending)
+    fn print_tree(
+        &self,
+        task: &Task,
+        listed: &[&Task],
+        depth: usize,
+        now: NaiveDateTime,
+        layout: Layout,
+        printed: &mut HashSet<u32>,
+    ) {
+        if !printed.insert(task.id) {
+            return;
+        }
+        self.print_task(task, depth, now, layout);
+        for child in listed.iter().filter(|t| t.parent == Some(task.id)) {
+            self.print_tree(child, listed, depth + 1, now, layout, printed);
+        }
     }
     
-    fn save_to_file(&self) {
-        let content: String = self
+    fn print_task(&self, task: &Task, depth: usize, now: NaiveDateTime, layout: Layout) {
+        for line in self.renderer.task(self, task, depth, now, layout) {
+            println!("{}", line);
+        }
+    }
+    
+    // Pending tasks that are overdue, and those due later today
+    fn agenda(&self, now: NaiveDateTime) -> (Vec<&Task>, Vec<&Task>) {
+        let today = now.date();
+        let mut overdue: Vec<&Task> = self
+            .tasks
+            .values()
+            .filter(|t| t.is_overdue(now))
+            .collect();
+        let mut due_today: Vec<&Task> = self
             .tasks
             .values()
-            .map(|task| task.to_file_format())
-            .collect::<Vec<String>>()
-            .join("\n");
+            .filter(|t| !t.status.is_closed() && !t.is_overdue(now))
+            .filter(|t| t.due.is_some_and(|d| d.is_today(today)))
+            .collect();
+        sort_tasks(&mut overdue, now);
+        sort_tasks(&mut due_today, now);
+        (overdue, due_today)
+    }
+    
+    fn show_agenda(&self) {
+        let now = Local::now().naive_local();
+        let today = now.date();
+        let (overdue, due_today) = self.agenda(now);
+        
+        let heading = format!("Agenda for {}:", today.format("%A, %Y-%m-%d"));
+        println!("\n{}", self.renderer.prefixed("📆", &heading));
+        println!("{}", self.renderer.rule());
+        
+        if overdue.is_empty() && due_today.is_empty() {
+            println!("Nothing due today.{}\n", self.renderer.marker(" 🎉", ""));
+            return;
+        }
         
-        if let Err(e) = fs::write(&self.file_path, content) {
-            eprintln!("Error saving to file: {}", e);
+        if !overdue.is_empty() {
+            println!("{}\n", self.renderer.prefixed("⚠️", "Overdue:"));
+            for task in overdue {
+                self.print_task(task, 0, now, self.renderer.layout);
+            }
+        }
+        
+        if !due_today.is_empty() {
+            println!("{}\n", self.renderer.prefixed("📅", "Due today:"));
+            for task in due_today {
+                self.print_task(task, 0, now, self.renderer.layout);
+            }
         }
     }
     
-    fn load_from_file(&mut self) {
-        if !Path::new(&self.file_path).exists() {
-            return;
+    // The enabled priorities and any other that a task still has, most
+    // important first
+    fn priority_levels(&self) -> Vec<Priority> {
+        let tasks = || self.tasks.values().chain(self.archive.tasks.values());
+        Priority::ALL
+            .into_iter()
+            .filter(|p| self.config.priorities.contains(p) || tasks().any(|t| t.priority == *p))
+            .collect()
+    }
+    
+    // (open, closed) task counts per tag
+    fn tag_counts(&self) -> BTreeMap<&str, (usize, usize)> {
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for task in self.tasks.values() {
+            for tag in &task.tags {
+                let entry = counts.entry(tag).or_default();
+                if task.status.is_closed() {
+                    entry.1 += 1;
+                } else {
+                    entry.0 += 1;
+                }
+            }
+        }
+        counts
+    }
+    
+    // (open, closed) task counts per project
+    fn project_counts(&self) -> BTreeMap<&str, (usize, usize)> {
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for task in self.tasks.values() {
+            if let Some(project) = &task.project {
+                let entry = counts.entry(project).or_default();
+                if task.status.is_closed() {
+                    entry.1 += 1;
+                } else {
+                    entry.0 += 1;
+                }
+            }
+        }
+        counts
+    }
+    
+    // The tasks stats count: the current ones, and archived ones if asked for
+    fn counted(&self, archived: bool) -> impl Iterator<Item = &Task> {
+        let archive = self.archive.tasks.values().filter(move |_| archived);
+        self.tasks.values().chain(archive)
+    }
+    
+    // The tasks reports count: archived ones keep their tracked time and
+    // completions
+    fn reported(&self) -> Vec<&Task> {
+        self.counted(true).collect()
+    }
+    
+    fn get_stats(&self, archived: bool) -> (usize, usize, usize) {
+        let total = self.counted(archived).count();
+        let completed = self.counted(archived).filter(|t| t.is_done()).count();
+        let pending = self.counted(archived).filter(|t| !t.status.is_closed()).count();
+        (total, completed, pending)
+    }
+    
+    // Writes what changed since the last save to the store. Changes another
+    // process saved in the meantime are merged in first (see sync.rs); if
+    // they can't be, the latest tasks are reloaded instead. Returns the
+    // (old, new) ids of tasks the merge renumbered.
+    fn save_to_file(&mut self) -> Result<Vec<(u32, u32)>, SaveError> {
+        let _lock = sync::lock(self.file_path())?;
+        let loaded = self.store.read()?;
+        self.notify(loaded.notices);
+        let stored = loaded.tasks;
+        let stored_records = records(&stored);
+        let mut renumbered = Vec::new();
+        
+        if stored_records != self.saved {
+            let merged = match sync::merge(&self.saved, &self.tasks, &stored) {
+                Ok(merged) => merged,
+                Err(conflicts) => {
+                    self.reload()?;
+                    let (tasks, were) = match conflicts.len() {
+                        1 => ("Task", "was"),
+                        _ => ("Tasks", "were"),
+                    };
+                    return Err(SaveError::Conflict(format!(
+                        "{} {} {} also changed by another todo process, so this change wasn't \
+                         saved. The latest tasks were reloaded from {}; please try again.",
+                        tasks,
+                        format_ids(&conflicts),
+                        were,
+                        self.file_path()
+                    )));
+                }
+            };
+            self.tasks = merged.tasks;
+            self.next_id = self.next_id.max(self.tasks.keys().max().map_or(1, |id| id + 1));
+            self.saved = stored_records;
+            eprintln!(
+                "{}",
+                self.renderer.prefixed(
+                    "🔄",
+                    "Merged changes saved by another todo process in the meantime"
+                )
+            );
+            for (old, new) in &merged.renumbered {
+                eprintln!("   It also added a task #{}, so yours is now #{}", old, new);
+            }
+            renumbered = merged.renumbered;
         }
         
-        match fs::read_to_string(&self.file_path) {
-            Ok(content) => {
-                for line in content.lines() {
-                    if let Some(task) = Task::from_file_format(line) {
-                        if task.id >= self.next_id {
-                            self.next_id = task.id + 1;
-                        }
-                        self.tasks.insert(task.id, task);
+        let records = self.records();
+        let mut ids: Vec<u32> = records.keys().chain(self.saved.keys()).copied().collect();
+        ids.sort();
+        ids.dedup();
+        let changes: Vec<Entry> = ids
+            .into_iter()
+            .filter(|id| records.get(id) != self.saved.get(id))
+            .map(|id| match self.tasks.get(&id) {
+                Some(task) => Entry::Put(Box::new(task.clone())),
+                None => Entry::Delete(id),
+            })
+            .collect();
+        self.store.write(&changes, &self.tasks)?;
+        self.shelve(&changes)?;
+        self.saved = records;
+        
+        self.history
+            .save(&self.history_path())
+            .map_err(|e| format!("Error saving {}: {}", self.history_path(), e))?;
+        self.dirty = false;
+        Ok(renumbered)
+    }
+    
+    // Moves the tasks a save deleted to the trash, or to the archive if they
+    // were archived, and takes the ones it put back out of both
+    fn shelve(&mut self, changes: &[Entry]) -> Result<(), String> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let notices = [self.trash.reload()?, self.archive.reload()?].concat();
+        self.notify(notices);
+        
+        let (mut trashed, mut archived) = (Vec::new(), Vec::new());
+        for change in changes {
+            match change {
+                Entry::Put(task) => {
+                    if self.trash.tasks.contains_key(&task.id) {
+                        trashed.push(Entry::Delete(task.id));
+                    }
+                    if self.archive.tasks.contains_key(&task.id) {
+                        archived.push(Entry::Delete(task.id));
+                    }
+                }
+                Entry::Delete(id) => {
+                    let saved = self.saved.get(id).map(|record| Task::from_file_format(record));
+                    let Some(Ok(mut task)) = saved else {
+                        continue;
+                    };
+                    if self.archiving.remove(id) {
+                        archived.push(Entry::Put(Box::new(task)));
+                    } else {
+                        task.updated_at = Utc::now();
+                        trashed.push(Entry::Put(Box::new(task)));
                     }
                 }
             }
+        }
+        self.trash.write(trashed)?;
+        self.archive.write(archived)
+    }
+    
+    fn records(&self) -> HashMap<u32, String> {
+        records(&self.tasks)
+    }
+    
+    fn file_path(&self) -> &str {
+        self.store.path()
+    }
+    
+    fn history_path(&self) -> String {
+        format!("{}.history", self.file_path())
+    }
+    
+    // Reverts the last recorded operation and returns it
+    fn undo(&mut self) -> Result<Operation, String> {
+        let operation = self.history.undo(&mut self.tasks, &mut self.next_id)?.clone();
+        self.dirty = true;
+        Ok(operation)
+    }
+    
+    fn redo(&mut self) -> Result<Operation, String> {
+        let operation = self.history.redo(&mut self.tasks, &mut self.next_id)?.clone();
+        self.dirty = true;
+        Ok(operation)
+    }
+    
+    // Makes a change outside of cli::execute the way it makes one: `f` changes
+    // the tasks and returns a label for the undo history, and the change is
+    // recorded and saved. A change that fails part way is undone rather than
+    // saved.
+    fn change<T, E: From<SaveError>>(
+        &mut self,
+        f: impl FnOnce(&mut TodoApp) -> Result<(T, String), E>,
+    ) -> Result<T, E> {
+        let before = self.tasks.clone();
+        let next_id = self.next_id;
+        
+        let (value, label) = match f(self) {
+            Ok(result) => result,
             Err(e) => {
-                eprintln!("Error loading from file: {}", e);
+                self.tasks = before;
+                self.next_id = next_id;
+                self.dirty = false;
+                return Err(e);
             }
+        };
+        
+        let changes = history::diff(&before, &self.tasks);
+        if !changes.is_empty() {
+            self.history.record(Operation {
+                time: Utc::now(),
+                label,
+                next_id: (next_id, self.next_id),
+                changes,
+            });
+        }
+        if self.dirty {
+            self.save_to_file()?;
         }
+        Ok(value)
+    }
+    
+    // Loads the tasks and undo history as currently saved, replacing anything
+    // in memory
+    fn load_from_file(&mut self) -> Result<(), String> {
+        // There's nothing to lock until the first save creates the file
+        let _lock = match self.store.exists() {
+            true => Some(sync::lock(self.file_path())?),
+            false => None,
+        };
+        self.reload()
+    }
+    
+    // Tells the user what the stores had to fix while reading them
+    fn notify(&self, notices: Vec<Notice>) {
+        for notice in notices {
+            let line = match notice {
+                Notice::Warning(text) => self.renderer.prefixed("⚠️", &text),
+                Notice::Upgraded(text) => self.renderer.prefixed("🔧", &text),
+            };
+            eprintln!("{}", line);
+        }
+    }
+    
+    // load_from_file for callers already holding the lock
+    fn reload(&mut self) -> Result<(), String> {
+        // Losing the undo history shouldn't stop the todo list from loading
+        self.history = History::load(&self.history_path()).unwrap_or_else(|e| {
+            let warning = format!("Ignoring undo history in {}: {}", self.history_path(), e);
+            eprintln!("{}", self.renderer.prefixed("⚠️", &warning));
+            History::default()
+        });
+        
+        let loaded = self.store.read()?;
+        self.tasks = loaded.tasks;
+        let shelved = [self.trash.reload()?, self.archive.reload()?].concat();
+        self.notify([loaded.notices, shelved].concat());
+        self.archiving.clear();
+        // New tasks don't take the id of one that may be restored
+        let ids = self.tasks.keys().chain(self.trash.tasks.keys());
+        self.next_id = ids.chain(self.archive.tasks.keys()).max().map_or(1, |id| id + 1);
+        self.saved = self.records();
+        self.dirty = false;
+        Ok(())
     }
 }
 
-fn print_help() {
-    println!("\n📚 Todo App Commands:");
-    println!("{:-<50}", "");
-    println!("  add <title> [priority]     - Add a new task");
-    println!("  remove <id>               - Remove a task");
-    println!("  toggle <id>               - Toggle task completion");
-    println!("  desc <id> <description>   - Add description to task");
-    println!("  list [filter]             - List tasks");
-    println!("    Filters: all, completed, pending, high, medium, low");
-    println!("  stats                     - Show statistics");
-    println!("  help                      - Show this help");
-    println!("  quit                      - Exit application");
-    println!("\nPriorities: high, medium, low (default: medium)");
-    println!();
+// The tasks matching a query, as for TodoApp::find_tasks
+fn search<'a>(
+    tasks: &'a HashMap<u32, Task>,
+    filter: Option<&str>,
+) -> Result<Vec<&'a Task>, QueryError> {
+    let now = Local::now().naive_local();
+    let query = match filter {
+        Some(filter) => Query::parse(filter, now.date())?,
+        None => Query::default(),
+    };
+    
+    let mut found: Vec<&Task> = tasks
+        .values()
+        .filter(|t| query.matches(t, tasks, now))
+        .collect();
+    
+    if query.has_sort() {
+        found.sort_by(|a, b| query.compare(a, b).then(a.id.cmp(&b.id)));
+    } else {
+        sort_tasks(&mut found, now);
+    }
+    if let Some(limit) = query.limit {
+        found.truncate(limit);
+    }
+    
+    Ok(found)
+}
+
+// Every task's file record, by id
+fn records(tasks: &HashMap<u32, Task>) -> HashMap<u32, String> {
+    tasks
+        .iter()
+        .map(|(id, task)| (*id, task.to_file_format()))
+        .collect()
 }
 
 fn main() {
-    let file_path = "todos.txt".to_string();
-    let mut app = TodoApp::new(file_path);
-    
-    println!("📝 Welcome to Rust Todo App!");
-    println!("Type 'help' for commands or 'quit' to exit.");
-    
-    loop {
-        print!("todo> ");
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let input = input.trim();
-                if input.is_empty() {
-                    continue;
-                }
-                
-                let parts: Vec<&str> = input.split_whitespace().collect();
-                if parts.is_empty() {
-                    continue;
-                }
-                
-                match parts[0].to_lowercase().as_str() {
-                    "quit" | "exit" | "q" => {
-                        println!("Goodbye! 👋");
-                        break;
-                    }
-                    "help" | "h" => {
-                        print_help();
-                    }
-                    "add" => {
-                        if parts.len() < 2 {
-                            println!("Usage: add <title> [priority]");
-                            continue;
-                        }
-                        
-                        let title = parts[1..].join(" ");
-                        let (title, priority) = if let Some(last_word) = parts.last() {
-                            if let Some(p) = Priority::from_str(last_word) {
-                                let title_parts = &parts[1..parts.len()-1];
-                                if title_parts.is_empty() {
-                                    (title, Priority::Medium)
-                                } else {
-                                    (title_parts.join(" "), p)
-                                }
-                            } else {
-                                (title, Priority::Medium)
-                            }
-                        } else {
-                            (title, Priority::Medium)
-                        };
-                        
-                        let id = app.add_task(title.clone(), priority);
-                        println!("✅ Added task #{}: {}", id, title);
-                    }
-                    "remove" | "rm" => {
-                        if parts.len() != 2 {
-                            println!("Usage: remove <id>");
-                            continue;
-                        }
-                        
-                        match parts[1].parse::<u32>() {
-                            Ok(id) => {
-                                if app.remove_task(id) {
-                                    println!("🗑️ Removed task #{}", id);
-                                } else {
-                                    println!("❌ Task #{} not found", id);
-                                }
-                            }
-                            Err(_) => println!("❌ Invalid task ID"),
-                        }
-                    }
-                    "toggle" | "t" => {
-                        if parts.len() != 2 {
-                            println!("Usage: toggle <id>");
-                            continue;
-                        }
-                        
-                        match parts[1].parse::<u32>() {
-                            Ok(id) => {
-                                if app.toggle_task(id) {
-                                    println!("🔄 Toggled task #{}", id);
-                                } else {
-                                    println!("❌ Task #{} not found", id);
-                                }
-                            }
-                            Err(_) => println!("❌ Invalid task ID"),
-                        }
-                    }
-                    "desc" | "description" => {
-                        if parts.len() < 3 {
-                            println!("Usage: desc <id> <description>");
-                            continue;
-                        }
-                        
-                        match parts[1].parse::<u32>() {
-                            Ok(id) => {
-                                let description = parts[2..].join(" ");
-                                if app.update_description(id, description.clone()) {
-                                    println!("📝 Updated description for task #{}", id);
-                                } else {
-                                    println!("❌ Task #{} not found", id);
-                                }
-                            }
-                            Err(_) => println!("❌ Invalid task ID"),
-                        }
-                    }
-                    "list" | "ls" => {
-                        let filter = parts.get(1).copied();
-                        app.list_tasks(filter);
-                    }
-                    "stats" | "statistics" => {
-                        let (total, completed, pending) = app.get_stats();
-                        println!("\n📊 Statistics:");
-                        println!("  Total tasks: {}", total);
-                        println!("  Completed: {} ✅", completed);
-                        println!("  Pending: {} ⏳", pending);
-                        if total > 0 {
-                            let completion_rate = (completed as f64 / total as f64) * 100.0;
-                            println!("  Completion rate: {:.1}%", completion_rate);
-                        }
-                        println!();
-                    }
-                    _ => {
-                        println!("❌ Unknown command. Type 'help' for available commands.");
-                    }
-                }
-            }
-            Err(error) => {
-                println!("Error reading input: {}", error);
-                break;
+    let cli = cli::Cli::parse();
+    let format = cli.output_format();
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            let renderer = Renderer::detect(Style::default(), Layout::default());
+            eprintln!("{}", renderer.prefixed("❌", &e));
+            std::process::exit(cli::EXIT_INVALID);
+        }
+    };
+    let file = cli.file.or_else(|| config.file.clone());
+    let file = file.unwrap_or_else(|| cli::DEFAULT_FILE.to_string());
+    let renderer = Renderer::detect(config.style, config.layout);
+    let mut app = match TodoApp::open(file, renderer) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("{}", renderer.prefixed("❌", &e));
+            std::process::exit(cli::EXIT_IO);
+        }
+    };
+    if let Some(workflow) = cli.workflow.or_else(|| config.workflow.clone()) {
+        app.workflow = workflow;
+    }
+    app.workflow.set_labels(&config.status_labels);
+    app.config = config;
+    
+    match cli.command {
+        Some(command) => {
+            if let Err(e) = cli::execute(&mut app, command, format) {
+                eprintln!("{}", app.renderer.prefixed("❌", &e.to_string()));
+                std::process::exit(e.exit_code());
             }
         }
+        None => repl::run(&mut app),
     }
 }
 
-// Note: This example uses a placeholder for chrono
-// In a real project, you would add chrono to Cargo.toml
-mod chrono {
-    pub struct Utc;
+// An empty app backed by a fresh file in the temp directory
+#[cfg(test)]
+fn temp_app(name: &str) -> TodoApp {
+    let file_name = format!("todo-test-{}-{}.txt", name, std::process::id());
+    let path = std::env::temp_dir().join(file_name);
+    let _ = std::fs::remove_file(&path);
+    TodoApp::new(path.to_string_lossy().into_owned()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
     
-    impl Utc {
-        pub fn now() -> DateTime {
-            DateTime
-        }
+    #[test]
+    fn test_toggle_records_completion() {
+        let mut task = Task::new(1, "Write report".to_string(), Priority::Medium);
+        assert!(task.completed_at.is_none());
+        
+        task.set_status(Status::Done);
+        assert!(task.is_done());
+        assert!(task.completed_at.is_some());
+        assert!(task.updated_at >= task.created_at);
+        
+        task.set_status(Status::Todo);
+        assert!(!task.is_done());
+        assert!(task.completed_at.is_none());
+    }
+    
+    #[test]
+    fn test_timestamps_round_trip() {
+        let mut task = Task::new(1, "Write report".to_string(), Priority::Medium);
+        task.set_status(Status::Done);
+        
+        let loaded = Task::from_file_format(&task.to_file_format()).unwrap();
+        assert_eq!(loaded.created_at.timestamp(), task.created_at.timestamp());
+        assert_eq!(loaded.updated_at.timestamp(), task.updated_at.timestamp());
+        assert_eq!(
+            loaded.completed_at.map(|t| t.timestamp()),
+            task.completed_at.map(|t| t.timestamp())
+        );
+    }
+    
+    #[test]
+    fn test_overdue_sorts_first() {
+        let now = Local::now().naive_local();
+        let high = Task::new(1, "High".to_string(), Priority::High);
+        let mut late = Task::new(2, "Late".to_string(), Priority::Low);
+        late.due = Some(Due::parse("yesterday", now.date()).unwrap());
+        
+        let mut tasks = vec![&high, &late];
+        sort_tasks(&mut tasks, now);
+        assert_eq!(tasks[0].id, 2);
+        
+        late.set_status(Status::Done);
+        let mut tasks = vec![&late, &high];
+        sort_tasks(&mut tasks, now);
+        assert_eq!(tasks[0].id, 1);
+    }
+    
+    #[test]
+    fn test_completing_recurring_task_schedules_next() {
+        let mut app = temp_app("recurring");
+        let today = Local::now().date_naive();
+        let due = Due { date: today, time: None };
+        let rule = Some(Recurrence::Daily);
+        let id = app.add_task("Standup".to_string(), Priority::Low, Some(due), rule);
+        
+        let next = app.toggle_task(id, false).unwrap().expect("next occurrence");
+        assert!(app.tasks[&id].is_done());
+        assert!(app.tasks[&id].recurrence.is_none());
+        assert_eq!(app.tasks[&next].recurrence, Some(Recurrence::Daily));
+        assert_eq!(app.tasks[&next].due.unwrap().date, today + chrono::Days::new(1));
+        
+        // The rule survives a reload
+        app.save_to_file().unwrap();
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks[&next].recurrence, Some(Recurrence::Daily));
+        let _ = fs::remove_file(app.file_path());
+        
+        // A rule with no date left refuses, leaving the task as it was
+        let last = Due { date: NaiveDate::MAX, time: None };
+        let rule = Some(Recurrence::Daily);
+        let id = app.add_task("Forever".to_string(), Priority::Low, Some(last), rule);
+        assert_eq!(app.toggle_task(id, false), Err(TaskError::NoNextOccurrence(id)));
+        assert!(!app.tasks[&id].is_done());
+        assert_eq!(app.tasks[&id].recurrence, Some(Recurrence::Daily));
     }
     
-    pub struct DateTime;
+    #[test]
+    fn test_tags_and_projects() {
+        let mut app = temp_app("tags");
+        let title = "Fix login #work #bug +website".to_string();
+        let a = app.add_task(title, Priority::High, None, None);
+        let b = app.add_task("Buy milk #home".to_string(), Priority::Low, None, None);
+        app.toggle_task(b, false).unwrap();
+        app.tag_task(b, &["work".to_string()], None);
+        
+        assert_eq!(app.tasks[&a].title, "Fix login");
+        let found = app.find_tasks(Some("+website")).unwrap();
+        assert_eq!(found.iter().map(|t| t.id).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(app.tag_counts()["work"], (1, 1));
+        
+        app.untag_task(a, &["bug".to_string()], true);
+        assert_eq!(app.tasks[&a].tags, vec!["work"]);
+        assert!(app.tasks[&a].project.is_none());
+        
+        app.save_to_file().unwrap();
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks[&b].tags, vec!["home", "work"]);
+        let _ = fs::remove_file(app.file_path());
+    }
     
-    impl DateTime {
-        pub fn format(&self, _fmt: &str) -> FormattedDateTime {
-            FormattedDateTime
+    #[test]
+    fn test_find_tasks_sort_and_limit() {
+        let mut app = temp_app("find");
+        app.add_task("One".to_string(), Priority::Low, None, None);
+        app.add_task("Two".to_string(), Priority::High, None, None);
+        app.add_task("Three".to_string(), Priority::Medium, None, None);
+        
+        let titles = |filter| -> Vec<String> {
+            app.find_tasks(Some(filter)).unwrap().iter().map(|t| t.title.clone()).collect()
+        };
+        assert_eq!(titles("all"), vec!["Two", "Three", "One"]);
+        assert_eq!(titles("sort:title limit:2"), vec!["One", "Three"]);
+        assert!(app.find_tasks(Some("priority>>high")).is_err());
+        let _ = fs::remove_file(app.file_path());
+    }
+    
+    #[test]
+    fn test_parent_needs_subtasks_done() {
+        let mut app = temp_app("subtasks");
+        let parent = app.add_task("Release".to_string(), Priority::High, None, None);
+        let child = app.add_task("Changelog".to_string(), Priority::Low, None, None);
+        app.set_parent(child, Some(parent)).unwrap();
+        
+        assert_eq!(
+            app.toggle_task(parent, false),
+            Err(TaskError::OpenSubtasks(parent, vec![child]))
+        );
+        app.toggle_task(child, false).unwrap();
+        app.toggle_task(parent, false).unwrap();
+        assert!(app.tasks[&parent].is_done());
+        
+        app.toggle_task(child, false).unwrap();
+        app.toggle_task(parent, false).unwrap();
+        app.toggle_task(parent, true).unwrap();
+        assert!(app.tasks[&parent].is_done());
+        let _ = fs::remove_file(app.file_path());
+    }
+    
+    #[test]
+    fn test_cycles_rejected() {
+        let mut app = temp_app("cycles");
+        let a = app.add_task("A".to_string(), Priority::Medium, None, None);
+        let b = app.add_task("B".to_string(), Priority::Medium, None, None);
+        let c = app.add_task("C".to_string(), Priority::Medium, None, None);
+        
+        app.set_parent(b, Some(a)).unwrap();
+        app.set_parent(c, Some(b)).unwrap();
+        assert!(matches!(app.set_parent(a, Some(c)), Err(TaskError::Cycle(_))));
+        assert!(matches!(app.set_parent(a, Some(a)), Err(TaskError::Cycle(_))));
+        
+        app.add_blocker(a, b).unwrap();
+        app.add_blocker(b, c).unwrap();
+        assert!(matches!(app.add_blocker(c, a), Err(TaskError::Cycle(_))));
+        assert!(matches!(app.add_blocker(c, c), Err(TaskError::Cycle(_))));
+        assert_eq!(app.open_blockers(&app.tasks[&a]), vec![b]);
+        
+        // Removing a task promotes its subtasks and clears links to it
+        app.remove_task(b);
+        assert_eq!(app.tasks[&c].parent, Some(a));
+        assert!(app.tasks[&a].blocked_by.is_empty());
+        
+        app.save_to_file().unwrap();
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks[&c].parent, Some(a));
+        let _ = fs::remove_file(app.file_path());
+    }
+    
+    #[test]
+    fn test_move_follows_workflow() {
+        let mut app = temp_app("move");
+        app.workflow = Workflow::parse("todo>in-progress; in-progress>done,waiting; done").unwrap();
+        let id = app.add_task("Write".to_string(), Priority::Medium, None, None);
+        
+        assert!(matches!(app.move_task(id, Status::Done, false), Err(TaskError::Transition(_))));
+        app.move_task(id, Status::InProgress, false).unwrap();
+        assert!(app.tasks[&id].completed_at.is_none());
+        app.move_task(id, Status::Done, false).unwrap();
+        assert!(app.tasks[&id].is_done() && app.tasks[&id].completed_at.is_some());
+        
+        // Toggling reopens whatever the workflow says
+        app.toggle_task(id, false).unwrap();
+        assert_eq!(app.tasks[&id].status, Status::Todo);
+        
+        // Files from before statuses store a done flag
+        let line = "id=7|title=Old|done=true|priority=Low|created=2026-01-01T00:00:00Z";
+        assert_eq!(Task::from_file_format(line).unwrap().status, Status::Done);
+        let line = line.replace("done=true", "status=waiting");
+        assert_eq!(Task::from_file_format(&line).unwrap().status, Status::Waiting);
+    }
+    
+    #[test]
+    fn test_one_timer_runs_at_a_time() {
+        let mut app = temp_app("timer");
+        let a = app.add_task("Draft".to_string(), Priority::Medium, None, None);
+        let b = app.add_task("Review".to_string(), Priority::Medium, None, None);
+        
+        assert_eq!(app.start_timer(a), Ok(None));
+        assert_eq!(app.start_timer(a), Err(TaskError::AlreadyTiming(a)));
+        assert_eq!(app.start_timer(b), Ok(Some(a)));
+        assert!(!app.tasks[&a].is_timing() && app.tasks[&b].is_timing());
+        
+        // Completing a task stops its timer
+        app.toggle_task(b, false).unwrap();
+        assert!(!app.tasks[&b].is_timing());
+        assert_eq!(app.stop_timer(), None);
+        assert_eq!(app.start_timer(b), Err(TaskError::Closed(b, Status::Done)));
+        
+        app.save_to_file().unwrap();
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks[&a].sessions, app.tasks[&a].sessions);
+        assert_eq!(reloaded.tasks[&b].sessions.len(), 1);
+        let _ = fs::remove_file(app.file_path());
+    }
+    
+    #[test]
+    fn test_undo_survives_restart() {
+        let mut app = temp_app("undo");
+        let history_path = app.history_path();
+        let run = |app: &mut TodoApp, command| {
+            cli::execute(app, command, output::Format::Ndjson).unwrap()
+        };
+        
+        run(&mut app, cli::Command::Add {
+            title: vec!["Ship".to_string(), "release".to_string()],
+            priority: Some(Priority::High),
+            due: None,
+            repeat: None,
+            parent: None,
+        });
+        run(&mut app, cli::Command::Remove { id: 1 });
+        assert!(app.tasks.is_empty());
+        
+        let mut app = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(app.undo().unwrap().label, "remove #1: Ship release");
+        assert_eq!(app.tasks[&1].title, "Ship release");
+        app.save_to_file().unwrap();
+        
+        let mut app = TodoApp::new(app.file_path().to_string()).unwrap();
+        let log = app.history.recent(10);
+        assert_eq!(log.len(), 2);
+        assert!(log[0].1 && !log[1].1);
+        app.redo().unwrap();
+        assert!(app.tasks.is_empty());
+        
+        let _ = fs::remove_file(app.file_path());
+        let _ = fs::remove_file(history_path);
+    }
+    
+    #[test]
+    fn test_trash_and_archive() {
+        let mut app = temp_app("shelves");
+        let path = app.file_path().to_string();
+        let a = app.add_task("Old chore".to_string(), Priority::Low, None, None);
+        let b = app.add_task("Draft".to_string(), Priority::Medium, None, None);
+        let c = app.add_task("Subtask".to_string(), Priority::Medium, None, None);
+        app.set_parent(c, Some(b)).unwrap();
+        let now = Utc::now();
+        let worked = tracking::Session { start: now - chrono::Duration::hours(1), end: Some(now) };
+        app.tasks.get_mut(&a).unwrap().sessions = vec![worked];
+        app.set_status(a, Status::Done, false).unwrap();
+        app.save_to_file().unwrap();
+        
+        // The removed task's id isn't given out again
+        app.remove_task(c);
+        app.save_to_file().unwrap();
+        let mut app = TodoApp::new(path.clone()).unwrap();
+        assert_eq!(app.trash.tasks[&c].title, "Subtask");
+        assert_eq!(app.next_id, c + 1);
+        
+        app.restore_task(c).unwrap();
+        app.save_to_file().unwrap();
+        assert_eq!(app.tasks[&c].parent, Some(b));
+        assert!(app.trash.tasks.is_empty());
+        
+        let cutoff = Utc::now() + chrono::Duration::days(1);
+        assert_eq!(app.archivable(cutoff), vec![a]);
+        app.archive_tasks(&[a]);
+        app.save_to_file().unwrap();
+        let mut app = TodoApp::new(path.clone()).unwrap();
+        assert!(!app.tasks.contains_key(&a) && app.trash.tasks.is_empty());
+        assert_eq!(app.find_archived(Some("chore")).unwrap().len(), 1);
+        assert_eq!(app.get_stats(false), (2, 0, 2));
+        assert_eq!(app.get_stats(true), (3, 1, 2));
+        
+        // Reports still count the archived task's time and completion
+        let today = Local::now().date_naive();
+        let report = tracking::report(&app.reported(), today - chrono::Days::new(1), now);
+        assert_eq!(report.total, 3600);
+        assert_eq!(reports::streak(&app.reported(), today).current, 1);
+        
+        app.remove_task(b);
+        app.save_to_file().unwrap();
+        app.purge(&[b]).unwrap();
+        let app = TodoApp::new(path.clone()).unwrap();
+        assert!(app.trash.tasks.is_empty());
+        assert_eq!(app.tasks[&c].parent, None);
+        
+        let shelves = [shelf::TRASH, shelf::ARCHIVE].map(|name| shelf::path(&path, name));
+        for file in shelves.into_iter().chain([path]) {
+            let _ = fs::remove_file(journal::path(&file));
+            let _ = fs::remove_file(file);
         }
     }
     
-    pub struct FormattedDateTime;
+    #[test]
+    fn test_journal_replay_and_compaction() {
+        let mut app = temp_app("journal");
+        let journal_path = journal::path(app.file_path());
+        let a = app.add_task("First".to_string(), Priority::Low, None, None);
+        app.save_to_file().unwrap();
+        assert!(!Path::new(&journal_path).exists());
+        
+        // Later saves only append to the journal
+        let b = app.add_task("Second".to_string(), Priority::Low, None, None);
+        app.remove_task(a);
+        app.save_to_file().unwrap();
+        assert!(!fs::read_to_string(app.file_path()).unwrap().contains("Second"));
+        
+        // A save cut short leaves a partial line, which is dropped on load
+        let mut file = fs::OpenOptions::new().append(true).open(&journal_path).unwrap();
+        std::io::Write::write_all(&mut file, b"- 2").unwrap();
+        let mut app = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(app.tasks.keys().collect::<Vec<_>>(), vec![&b]);
+        assert!(!Path::new(&journal_path).exists());
+        
+        for i in 0..journal::COMPACT_AFTER {
+            app.update_description(b, format!("edit {}", i));
+            app.save_to_file().unwrap();
+        }
+        assert!(journal::read(&journal_path).unwrap().entries.len() < journal::COMPACT_AFTER);
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks[&b].description, app.tasks[&b].description);
+        
+        let _ = fs::remove_file(&journal_path);
+        let _ = fs::remove_file(app.history_path());
+        let _ = fs::remove_file(app.file_path());
+    }
     
-    impl FormattedDateTime {
-        pub fn to_string(&self) -> String {
-            "2024-01-01 12:00:00".to_string()
+    #[test]
+    fn test_concurrent_saves_merge_or_conflict() {
+        let mut first = temp_app("concurrent");
+        let a = first.add_task("Shared".to_string(), Priority::Low, None, None);
+        first.save_to_file().unwrap();
+        let mut second = TodoApp::new(first.file_path().to_string()).unwrap();
+        
+        // Different tasks: both changes survive, and both adds keep a task
+        first.update_description(a, "from first".to_string());
+        let b = first.add_task("First's".to_string(), Priority::Low, None, None);
+        first.save_to_file().unwrap();
+        second.add_task("Second's".to_string(), Priority::Low, None, None);
+        second.save_to_file().unwrap();
+        
+        let merged = TodoApp::new(first.file_path().to_string()).unwrap();
+        assert_eq!(merged.tasks.len(), 3);
+        assert_eq!(merged.tasks[&a].description.as_deref(), Some("from first"));
+        assert_eq!(merged.tasks[&b].title, "First's");
+        assert_eq!(merged.tasks[&(b + 1)].title, "Second's");
+        
+        // The same task: the second save is refused and reloads the first's
+        first.update_description(a, "first again".to_string());
+        first.save_to_file().unwrap();
+        second.update_description(a, "second again".to_string());
+        let error = second.save_to_file().unwrap_err();
+        assert!(matches!(error, SaveError::Conflict(ref m) if m.contains("Task #1 was also")));
+        assert_eq!(second.tasks[&a].description.as_deref(), Some("first again"));
+        assert!(!second.dirty);
+        
+        let _ = fs::remove_file(journal::path(first.file_path()));
+        let _ = fs::remove_file(first.history_path());
+        let _ = fs::remove_file(format!("{}.lock", first.file_path()));
+        let _ = fs::remove_file(first.file_path());
+    }
+    
+    #[test]
+    fn test_migrate_to_sqlite() {
+        let mut app = temp_app("migrate");
+        let a = app.add_task("Pay | rent".to_string(), Priority::High, None, None);
+        let b = app.add_task("Call bank".to_string(), Priority::Low, None, None);
+        app.update_description(a, "line one\nline two".to_string());
+        app.tag_task(a, &["home".to_string()], Some("bills".to_string()));
+        app.add_blocker(a, b).unwrap();
+        app.toggle_task(b, false).unwrap();
+        app.save_to_file().unwrap();
+        
+        let db = store::Kind::Sqlite.rename(app.file_path());
+        let _ = fs::remove_file(&db);
+        let migrate = cli::Command::Migrate {
+            to: store::Kind::Sqlite,
+            output: None,
+        };
+        cli::execute(&mut app, migrate, output::Format::Ndjson).unwrap();
+        
+        let mut copied = TodoApp::new(db.clone()).unwrap();
+        assert_eq!(copied.records(), app.records());
+        copied.remove_task(b);
+        copied.save_to_file().unwrap();
+        assert_eq!(TodoApp::new(db.clone()).unwrap().tasks.len(), 1);
+        
+        for path in [db.clone(), format!("{}.lock", db), app.file_path().to_string()] {
+            let _ = fs::remove_file(path);
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_parse_legacy_timestamp() {
+        let dt = parse_timestamp("2024-01-01 12:00:00").unwrap();
+        assert_eq!(format_timestamp(&dt), "2024-01-01T12:00:00Z");
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+}
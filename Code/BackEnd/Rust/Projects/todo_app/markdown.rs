@@ -0,0 +1,408 @@
+// Markdown checklists
+//
+// Export writes every task as a `- [ ]` / `- [x]` checklist item under a
+// heading per priority ("## High priority") or per project ("## +bills",
+// "## No project"):
+//
+//   - [ ] Pay rent #home +bills 📅 2026-10-20 <!-- todo:3 -->
+//     - First line of the description
+//     - [ ] 🔴 A subtask <!-- todo:4 -->
+//
+// Descriptions are indented bullets, one per non-blank line, and subtasks are
+// nested under their parent. An item's priority is shown by its heading, or
+// by a leading 🚨, 🔴, 🟡, 🟢 or ⚪ when it differs, and its project likewise by its
+// heading or a +project word. The HTML comment carries the task id, which
+// Markdown renderers don't show.
+//
+// Import reads the checklist items of any Markdown document, ignoring other
+// text and code blocks. Items with a `todo:ID` comment update that task;
+// other items are added, and their new ids are written back into the file as
+// comments so the next import updates them too. Repeat rules, blockers and
+// creation times aren't part of the checklist, so they're kept as they were,
+// as is the priority of an item that doesn't give one. Closed tasks, done or
+// cancelled, are checked, and a task keeps its status unless its item was
+// checked or unchecked.
+
+use std::sync::LazyLock;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+use crate::due::Due;
+use crate::exchange::Imported;
+use crate::workflow::Status;
+use crate::{tags, Priority, Task};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Group {
+    /// One section per priority
+    #[default]
+    Priority,
+    /// One section per project
+    Project,
+}
+
+const NO_PROJECT: &str = "No project";
+const INDENT: &str = "  ";
+
+static ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[-*+]|\d+[.)])\s+\[([ xX])\](?:\s+(.*))?$").unwrap());
+static ID_COMMENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*<!--\s*todo:(\d+)\s*-->\s*$").unwrap());
+static DUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*📅\s*(\d{4}-\d{2}-\d{2}(?: \d{1,2}:\d{2})?)\s*$").unwrap());
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}#{1,6}(?:\s+(.*?))?(?:\s+#+)?\s*$").unwrap());
+static BULLET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:[-*+]|\d+[.)])\s*").unwrap());
+
+// What a heading says about the items under it
+#[derive(Default)]
+struct Section {
+    priority: Option<Priority>,
+    project: Option<String>,
+}
+
+impl Section {
+    fn from_heading(heading: &str) -> Section {
+        let lower = heading.to_lowercase();
+        let priority = lower.strip_suffix(" priority").and_then(|name| {
+            Priority::ALL
+                .iter()
+                .find(|p| p.to_string().to_lowercase() == name)
+        });
+        Section {
+            priority: priority.cloned(),
+            project: tags::parse_project(heading),
+        }
+    }
+}
+
+pub fn export(tasks: &[&Task], group: Group) -> String {
+    let mut out = String::from("# Tasks\n");
+    let is_top = |task: &&&Task| {
+        task.parent
+            .is_none_or(|parent| tasks.iter().all(|t| t.id != parent))
+    };
+    let top: Vec<&Task> = tasks.iter().filter(is_top).copied().collect();
+
+    let mut sections: Vec<(String, Section, Vec<&Task>)> = Vec::new();
+    match group {
+        Group::Priority => {
+            for priority in Priority::ALL {
+                let members = top.iter().filter(|t| t.priority == priority);
+                let members = members.copied().collect();
+                let heading = format!("{} priority", priority.to_string());
+                let section = Section {
+                    priority: Some(priority),
+                    project: None,
+                };
+                sections.push((heading, section, members));
+            }
+        }
+        Group::Project => {
+            let mut projects: Vec<Option<&String>> =
+                top.iter().map(|t| t.project.as_ref()).collect();
+            projects.sort_by_key(|project| (project.is_none(), *project));
+            projects.dedup();
+            for project in projects {
+                let members = top.iter().filter(|t| t.project.as_ref() == project);
+                let heading = project.map_or(NO_PROJECT.to_string(), |p| format!("+{}", p));
+                let section = Section {
+                    priority: None,
+                    project: project.cloned(),
+                };
+                sections.push((heading, section, members.copied().collect()));
+            }
+        }
+    }
+
+    for (heading, section, members) in sections {
+        if members.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {}\n\n", heading));
+        for task in members {
+            write_item(&mut out, task, tasks, &section, 0);
+        }
+    }
+    out
+}
+
+fn write_item(out: &mut String, task: &Task, tasks: &[&Task], section: &Section, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    let mut words = Vec::new();
+    if section.priority.as_ref() != Some(&task.priority) {
+        words.push(task.priority.emoji().to_string());
+    }
+    words.push(task.title.replace(['\n', '\r'], " "));
+    words.extend(task.tags.iter().map(|tag| format!("#{}", tag)));
+    if let Some(project) = task
+        .project
+        .as_ref()
+        .filter(|p| section.project.as_ref() != Some(*p))
+    {
+        words.push(format!("+{}", project));
+    }
+    if let Some(due) = task.due {
+        words.push(format!("📅 {}", due.to_file_format()));
+    }
+    words.push(format!("<!-- todo:{} -->", task.id));
+
+    let check = if task.status.is_closed() { 'x' } else { ' ' };
+    out.push_str(&format!("{}- [{}] {}\n", indent, check, words.join(" ")));
+    if let Some(description) = &task.description {
+        for line in description.lines().filter(|line| !line.trim().is_empty()) {
+            out.push_str(&format!("{}{}- {}\n", indent, INDENT, line.trim()));
+        }
+    }
+    for child in tasks.iter().filter(|t| t.parent == Some(task.id)) {
+        write_item(out, child, tasks, section, depth + 1);
+    }
+}
+
+pub fn parse(content: &str) -> Vec<Imported> {
+    scan(content).into_iter().map(|(_, item)| item).collect()
+}
+
+// Adds an id comment to each checklist item that lacks one, given the ids
+// the items were imported as. Returns the new content and how many were added.
+pub fn add_ids(content: &str, ids: &[u32]) -> (String, usize) {
+    let missing: Vec<(usize, u32)> = scan(content)
+        .iter()
+        .zip(ids)
+        .filter(|((_, item), _)| item.id.is_none())
+        .map(|((line, _), id)| (*line, *id))
+        .collect();
+
+    let mut out = String::with_capacity(content.len());
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        match missing.iter().find(|(number, _)| *number == index) {
+            Some((_, id)) => {
+                let text = line.trim_end_matches(['\n', '\r']);
+                out.push_str(text.trim_end());
+                out.push_str(&format!(" <!-- todo:{} -->", id));
+                out.push_str(&line[text.len()..]);
+            }
+            None => out.push_str(line),
+        }
+    }
+    (out, missing.len())
+}
+
+// Width of a line's leading whitespace, counting a tab as four spaces
+fn indent_of(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// The checklist items of a document with the index of the line each is on
+fn scan(content: &str) -> Vec<(usize, Imported)> {
+    let mut items: Vec<(usize, Imported)> = Vec::new();
+    let mut section = Section::default();
+    // (indent, position in items) of the items later lines can nest under
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut fenced = false;
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+            open.clear();
+            continue;
+        }
+        if fenced || trimmed.is_empty() {
+            continue;
+        }
+        if let Some(heading) = HEADING.captures(line) {
+            section = Section::from_heading(heading.get(1).map_or("", |m| m.as_str()));
+            open.clear();
+            continue;
+        }
+
+        let indent = indent_of(line);
+        while open
+            .last()
+            .is_some_and(|&(open_indent, _)| open_indent >= indent)
+        {
+            open.pop();
+        }
+        let parent = open.last().map(|&(_, position)| position);
+
+        if let Some(item) = ITEM.captures(trimmed) {
+            let text = item.get(2).map_or("", |m| m.as_str());
+            if let Some(mut imported) = from_item(text, &item[1] != " ", &section) {
+                imported.parent_index = parent;
+                open.push((indent, items.len()));
+                items.push((number, imported));
+            }
+        } else if let Some(position) = parent {
+            // Any other text nested under an item adds to its description
+            let text = BULLET.replace(trimmed, "");
+            let task = &mut items[position].1.task;
+            match &mut task.description {
+                Some(description) => {
+                    description.push('\n');
+                    description.push_str(&text);
+                }
+                None => task.description = Some(text.into_owned()),
+            }
+        }
+    }
+    items
+}
+
+fn from_item(text: &str, checked: bool, section: &Section) -> Option<Imported> {
+    let mut text = text.to_string();
+    let mut id = None;
+    if let Some(comment) = ID_COMMENT.captures(&text) {
+        id = comment[1].parse().ok();
+        text.truncate(comment.get(0).unwrap().start());
+    }
+    let mut due = None;
+    if let Some(date) = DUE.captures(&text) {
+        due = Due::from_file_format(&date[1]).ok();
+        if due.is_some() {
+            text.truncate(date.get(0).unwrap().start());
+        }
+    }
+    let mut priority = section.priority.clone();
+    for candidate in Priority::ALL {
+        if let Some(rest) = text.trim_start().strip_prefix(candidate.emoji()) {
+            text = rest.to_string();
+            priority = Some(candidate);
+            break;
+        }
+    }
+
+    let extracted = tags::extract(&text);
+    if extracted.title.is_empty() {
+        return None;
+    }
+    let mut task = Task::new(
+        0,
+        extracted.title,
+        priority.clone().unwrap_or(Priority::Medium),
+    );
+    task.tags = extracted.tags;
+    task.project = extracted.project.or_else(|| section.project.clone());
+    task.due = due;
+    if checked {
+        task.set_status(Status::Done);
+    }
+
+    let mut imported = Imported::new(id, task);
+    imported.keep.extend([
+        "created",
+        "completed",
+        "repeat",
+        "blocked_by",
+        "uid",
+        "status",
+    ]);
+    if priority.is_none() {
+        imported.keep.push("priority");
+    }
+    Some(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notes_checklist() {
+        let content = "# Trip notes\n\
+                       Some text.\n\
+                       \n\
+                       ## High priority\n\
+                       - [ ] Book flights #travel 📅 2026-11-02 <!-- todo:7 -->\n\
+                       \x20 - compare prices\n\
+                       \x20 * check bags\n\
+                       \x20 - [x] 🟢 Renew passport\n\
+                       - [ ]\n\
+                       \n\
+                       ```\n\
+                       - [ ] Not a task\n\
+                       ```\n\
+                       ## +home\n\
+                       1. [X] Water plants\n";
+        let items = parse(content);
+        assert_eq!(items.len(), 3);
+
+        let flights = &items[0];
+        assert_eq!(flights.id, Some(7));
+        assert_eq!(flights.task.title, "Book flights");
+        assert_eq!(flights.task.priority, Priority::High);
+        assert_eq!(flights.task.tags, vec!["travel"]);
+        assert_eq!(flights.task.due.unwrap().to_file_format(), "2026-11-02");
+        assert_eq!(
+            flights.task.description.as_deref(),
+            Some("compare prices\ncheck bags")
+        );
+        assert!(!flights.keep.contains(&"priority"));
+
+        let passport = &items[1];
+        assert_eq!(passport.parent_index, Some(0));
+        assert!(passport.task.is_done());
+        assert_eq!(passport.task.priority, Priority::Low);
+
+        let plants = &items[2];
+        assert_eq!(plants.task.project.as_deref(), Some("home"));
+        assert_eq!(plants.parent_index, None);
+        assert!(plants.keep.contains(&"priority"));
+
+        let (updated, added) = add_ids(content, &[7, 8, 9]);
+        assert_eq!(added, 2);
+        assert!(updated.contains("  - [x] 🟢 Renew passport <!-- todo:8 -->\n"));
+        assert!(updated.ends_with("1. [X] Water plants <!-- todo:9 -->\n"));
+        assert_eq!(parse(&updated)[2].id, Some(9));
+    }
+
+    #[test]
+    fn test_export_groups_and_round_trips() {
+        let mut parent = Task::new(1, "Pay rent".to_string(), Priority::High);
+        parent.add_tags(&["home".to_string()], Some("bills".to_string()));
+        parent.set_description("by transfer\n\nbefore the 5th".to_string());
+        let mut child = Task::new(2, "Find the IBAN".to_string(), Priority::Low);
+        child.parent = Some(1);
+        child.project = Some("bills".to_string());
+        child.set_status(Status::Done);
+        let other = Task::new(3, "Call mum".to_string(), Priority::Medium);
+        let tasks = [&parent, &child, &other];
+
+        let by_priority = export(&tasks, Group::Priority);
+        assert_eq!(
+            by_priority,
+            "# Tasks\n\n## High priority\n\n\
+             - [ ] Pay rent #home +bills <!-- todo:1 -->\n\
+             \x20 - by transfer\n\
+             \x20 - before the 5th\n\
+             \x20 - [x] 🟢 Find the IBAN +bills <!-- todo:2 -->\n\
+             \n## Medium priority\n\n\
+             - [ ] Call mum <!-- todo:3 -->\n"
+        );
+
+        let by_project = export(&tasks, Group::Project);
+        assert!(by_project.contains("## +bills\n\n- [ ] 🔴 Pay rent #home <!-- todo:1 -->"));
+        assert!(by_project.contains("## No project\n\n- [ ] 🟡 Call mum <!-- todo:3 -->"));
+
+        for content in [by_priority, by_project] {
+            let items = parse(&content);
+            let ids: Vec<Option<u32>> = items.iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+            for (item, task) in items.iter().zip(tasks) {
+                assert_eq!(item.task.title, task.title);
+                assert_eq!(item.task.priority, task.priority);
+                assert_eq!(item.task.project, task.project);
+                assert_eq!(item.task.status.is_closed(), task.status.is_closed());
+            }
+            assert_eq!(items[1].parent_index, Some(0));
+            assert_eq!(
+                items[0].task.description.as_deref(),
+                Some("by transfer\nbefore the 5th")
+            );
+        }
+    }
+}
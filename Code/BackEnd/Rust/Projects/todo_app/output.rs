@@ -0,0 +1,584 @@
+// Machine-readable output
+//
+// `--format json|ndjson|csv` (or `--json`) switches a command from the usual
+// text to one of these formats. Scripts may rely on everything below: fields
+// and columns are never renamed, removed or reordered, and new ones are only
+// added at the end.
+//
+// Tasks
+//
+//   Every task is written with these fields, in this order:
+//
+//     id            number
+//     title         string
+//     description   string or null
+//     completed     boolean, true for a task whose status is done
+//     priority      "urgent", "high", "medium", "low" or "someday"
+//     created_at    RFC 3339 UTC timestamp, e.g. "2026-10-18T09:30:00Z"
+//     updated_at    RFC 3339 UTC timestamp
+//     completed_at  RFC 3339 UTC timestamp, or null while pending
+//     due           "YYYY-MM-DD" or "YYYY-MM-DD HH:MM" in local time, or null
+//     overdue       boolean, true for a pending task past its due date
+//     repeat        repeat rule as accepted by `repeat`, e.g. "weekly:mon", or null
+//     tags          array of lowercase tag names without the '#'
+//     project       lowercase project name without the '+', or null
+//     parent        id of the parent task, or null
+//     blocked_by    array of ids this task waits on
+//     tracked       seconds of work recorded with `start` and `stop`, counting
+//                   a running session up to now
+//     status        "todo", "in-progress", "waiting", "done", "cancelled" or
+//                   the name of a status the workflow adds
+//
+//   json    a single array of task objects
+//   ndjson  one task object per line
+//   csv     a header row with the field names, then one row per task. Nulls
+//           are empty, booleans are true/false, and tags and blocked_by are
+//           joined with spaces. Fields are quoted per RFC 4180 when needed.
+//
+//   `list` writes the matching tasks, `today` the overdue tasks followed by
+//   those due today, and commands that change tasks write the tasks they
+//   touched (a completed recurring task is followed by its next occurrence).
+//   `trash` writes the tasks in the trash, and `archive` and `purge` the
+//   tasks they archived or deleted.
+//
+// Stats
+//
+//   `stats` writes one object (json and ndjson are the same). Archived tasks
+//   are only counted with --archived.
+//
+//     total            number of tasks
+//     completed        number of done tasks
+//     pending          number of open tasks, i.e. not done or cancelled
+//     overdue          number of pending tasks past their due date
+//     completion_rate  percentage of tasks completed, to one decimal place
+//     by_priority      {"high", "medium", "low"} each with total, completed
+//                      and pending, plus "urgent" and "someday" when the
+//                      config enables them or a task has them
+//     by_status        {status: number of tasks} for every status in the
+//                      workflow
+//     archived         number of tasks in the archive, counted or not
+//
+//   csv writes a header row and one row with the top-level numbers.
+//
+// Board
+//
+//   `board` writes {"columns": [{"status", "tasks"}]}, a column per status in
+//   workflow order with its tasks as above, or the tasks as csv with the
+//   status column telling them apart.
+//
+// Tags
+//
+//   `tags` writes {"tags": {name: {"open", "done"}}, "projects": {...}} as
+//   JSON, or rows of kind,name,open,done as csv with kind "tag" or "project".
+//   Cancelled tasks count as done here.
+//
+// Reports
+//
+//   Each `report` writes one object (json and ndjson are the same), with
+//   dates as "YYYY-MM-DD" local days and durations in seconds.
+//
+//   `report time`:
+//
+//     since    the first day counted
+//     total    seconds worked since then
+//     tasks    [{"id", "title", "seconds"}], most time first
+//     tags     [{"tag", "seconds"}], most time first; tag is null for the
+//              time spent on untagged tasks
+//     days     [{"date", "seconds"}] in date order, for days with time
+//
+//   csv writes rows of kind,name,seconds with kind "task" (name is the id),
+//   "tag" (name is empty for untagged tasks) or "day" (name is the date).
+//
+//   `report activity`:
+//
+//     by       "day" or "week"
+//     periods  [{"start", "created", "completed"}] in date order, start being
+//              the period's first day
+//
+//   csv writes the periods as rows of start,created,completed.
+//
+//   `report completion`:
+//
+//     since        the first day counted, or null for all time
+//     all          {"completed", "average_seconds"}: how many tasks were
+//                  completed and their average time from creation to
+//                  completion, null when there are none
+//     by_priority  {"high", "medium", "low"} each the same as all, plus
+//                  other levels as for stats
+//
+//   csv writes rows of priority,completed,average_seconds, with "all" first.
+//
+//   `report streak`:
+//
+//     current         days in a row with a completion, up to today or
+//                     yesterday
+//     longest         the longest such run
+//     last_completed  the latest day with a completion, or null
+//
+//   csv writes a header row and one row with these.
+//
+//   `report burndown`:
+//
+//     days     [{"date", "open"}] in date order, open being the number of
+//              tasks open at the end of that day
+//
+//   csv writes the days as rows of date,open.
+//
+// History
+//
+//   `log` writes its entries newest first with these fields:
+//
+//     time    RFC 3339 UTC timestamp of the change
+//     label   what the change did, e.g. "remove #12: Ship release"
+//     undone  boolean, true if the change has been undone
+//
+//   as a JSON array, one object per line for ndjson, or csv with a header row.
+//   `undo` and `redo` write the tasks they restored, like other changes.
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::history::Operation;
+use crate::reports::{Activity, Average, Completion, Period, Streak};
+use crate::tracking::{self, Report};
+use crate::workflow::Status;
+use crate::{format_timestamp, Priority, Task, TodoApp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+const TASK_COLUMNS: [&str; 17] = [
+    "id",
+    "title",
+    "description",
+    "completed",
+    "priority",
+    "created_at",
+    "updated_at",
+    "completed_at",
+    "due",
+    "overdue",
+    "repeat",
+    "tags",
+    "project",
+    "parent",
+    "blocked_by",
+    "tracked",
+    "status",
+];
+
+pub fn task_json(task: &Task, now: NaiveDateTime) -> Value {
+    json!({
+        "id": task.id,
+        "title": task.title,
+        "description": task.description,
+        "completed": task.is_done(),
+        "priority": task.priority.to_string().to_lowercase(),
+        "created_at": format_timestamp(&task.created_at),
+        "updated_at": format_timestamp(&task.updated_at),
+        "completed_at": task.completed_at.as_ref().map(format_timestamp),
+        "due": task.due.map(|due| due.to_file_format()),
+        "overdue": task.is_overdue(now),
+        "repeat": task.recurrence.as_ref().map(|rule| rule.to_file_format()),
+        "tags": task.tags,
+        "project": task.project,
+        "parent": task.parent,
+        "blocked_by": task.blocked_by,
+        "tracked": tracking::total_seconds(&task.sessions, Utc::now()),
+        "status": task.status.name(),
+    })
+}
+
+// Tasks in the given format. Text output is printed by the commands
+// themselves, so Text is treated like Json here.
+pub fn tasks(tasks: &[&Task], format: Format, now: NaiveDateTime) -> String {
+    let values = tasks.iter().map(|task| task_json(task, now));
+
+    match format {
+        Format::Text | Format::Json => format!("{}\n", Value::Array(values.collect())),
+        Format::Ndjson => values.map(|value| format!("{}\n", value)).collect(),
+        Format::Csv => {
+            let mut out = csv_row(TASK_COLUMNS.iter().map(|c| c.to_string()));
+            for value in values {
+                out.push_str(&csv_row(TASK_COLUMNS.iter().map(|c| csv_value(&value[c]))));
+            }
+            out
+        }
+    }
+}
+
+pub fn board(columns: &[(Status, Vec<&Task>)], format: Format, now: NaiveDateTime) -> String {
+    if format == Format::Csv {
+        let all: Vec<&Task> = columns
+            .iter()
+            .flat_map(|(_, tasks)| tasks.clone())
+            .collect();
+        return tasks(&all, format, now);
+    }
+    let columns: Vec<Value> = columns
+        .iter()
+        .map(|(status, tasks)| {
+            let tasks: Vec<Value> = tasks.iter().map(|task| task_json(task, now)).collect();
+            json!({ "status": status.name(), "tasks": tasks })
+        })
+        .collect();
+    format!("{}\n", json!({ "columns": columns }))
+}
+
+pub fn stats(app: &TodoApp, archived: bool, format: Format, now: NaiveDateTime) -> String {
+    let count = |priority: Option<&Priority>| {
+        let tasks = app
+            .counted(archived)
+            .filter(|t| priority.is_none_or(|p| t.priority == *p));
+        let (mut total, mut completed, mut pending) = (0, 0, 0);
+        for task in tasks {
+            total += 1;
+            if task.is_done() {
+                completed += 1;
+            } else if !task.status.is_closed() {
+                pending += 1;
+            }
+        }
+        (total, completed, pending)
+    };
+
+    let (total, completed, pending) = count(None);
+    let overdue = app.tasks.values().filter(|t| t.is_overdue(now)).count();
+    let completion_rate = if total > 0 {
+        (completed as f64 / total as f64 * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+
+    if format == Format::Csv {
+        let header = [
+            "total",
+            "completed",
+            "pending",
+            "overdue",
+            "completion_rate",
+            "archived",
+        ];
+        let row = [total, completed, pending, overdue].map(|n: usize| n.to_string());
+        let rate = format!("{:.1}", completion_rate);
+        let row = row
+            .into_iter()
+            .chain([rate, app.archive.tasks.len().to_string()]);
+        return csv_row(header.iter().map(|c| c.to_string())) + &csv_row(row);
+    }
+
+    let mut by_priority = serde_json::Map::new();
+    for priority in app.priority_levels() {
+        let (total, completed, pending) = count(Some(&priority));
+        by_priority.insert(
+            priority.to_string().to_lowercase(),
+            json!({ "total": total, "completed": completed, "pending": pending }),
+        );
+    }
+
+    let mut by_status = serde_json::Map::new();
+    for status in app.workflow.statuses() {
+        let count = app
+            .counted(archived)
+            .filter(|t| t.status == *status)
+            .count();
+        by_status.insert(status.name().to_string(), json!(count));
+    }
+
+    let value = json!({
+        "total": total,
+        "completed": completed,
+        "pending": pending,
+        "overdue": overdue,
+        "completion_rate": completion_rate,
+        "by_priority": by_priority,
+        "by_status": by_status,
+        "archived": app.archive.tasks.len(),
+    });
+    format!("{}\n", value)
+}
+
+pub fn tags(
+    tag_counts: &BTreeMap<&str, (usize, usize)>,
+    project_counts: &BTreeMap<&str, (usize, usize)>,
+    format: Format,
+) -> String {
+    if format == Format::Csv {
+        let mut out = csv_row(["kind", "name", "open", "done"].map(String::from));
+        for (kind, counts) in [("tag", tag_counts), ("project", project_counts)] {
+            for (name, (open, done)) in counts {
+                let row = [
+                    kind.to_string(),
+                    name.to_string(),
+                    open.to_string(),
+                    done.to_string(),
+                ];
+                out.push_str(&csv_row(row));
+            }
+        }
+        return out;
+    }
+
+    let counts = |counts: &BTreeMap<&str, (usize, usize)>| -> Value {
+        counts
+            .iter()
+            .map(|(name, (open, done))| (name.to_string(), json!({ "open": open, "done": done })))
+            .collect::<serde_json::Map<String, Value>>()
+            .into()
+    };
+    let value = json!({
+        "tags": counts(tag_counts),
+        "projects": counts(project_counts),
+    });
+    format!("{}\n", value)
+}
+
+pub fn time_report(report: &Report, format: Format) -> String {
+    if format == Format::Csv {
+        let mut out = csv_row(["kind", "name", "seconds"].map(String::from));
+        for (task, seconds) in &report.tasks {
+            out.push_str(&csv_row([
+                "task".to_string(),
+                task.id.to_string(),
+                seconds.to_string(),
+            ]));
+        }
+        for (tag, seconds) in &report.tags {
+            let name = tag.unwrap_or("").to_string();
+            out.push_str(&csv_row(["tag".to_string(), name, seconds.to_string()]));
+        }
+        for (date, seconds) in &report.days {
+            out.push_str(&csv_row([
+                "day".to_string(),
+                date.to_string(),
+                seconds.to_string(),
+            ]));
+        }
+        return out;
+    }
+
+    let tasks: Vec<Value> = report
+        .tasks
+        .iter()
+        .map(|(task, seconds)| json!({ "id": task.id, "title": task.title, "seconds": seconds }))
+        .collect();
+    let tags: Vec<Value> = report
+        .tags
+        .iter()
+        .map(|(tag, seconds)| json!({ "tag": tag, "seconds": seconds }))
+        .collect();
+    let days: Vec<Value> = report
+        .days
+        .iter()
+        .map(|(date, seconds)| json!({ "date": date.to_string(), "seconds": seconds }))
+        .collect();
+    let value = json!({
+        "since": report.since.to_string(),
+        "total": report.total,
+        "tasks": tasks,
+        "tags": tags,
+        "days": days,
+    });
+    format!("{}\n", value)
+}
+
+pub fn activity(periods: &[Activity], by: Period, format: Format) -> String {
+    if format == Format::Csv {
+        let mut out = csv_row(["start", "created", "completed"].map(String::from));
+        for period in periods {
+            let row = [
+                period.start.to_string(),
+                period.created.to_string(),
+                period.completed.to_string(),
+            ];
+            out.push_str(&csv_row(row));
+        }
+        return out;
+    }
+
+    let periods: Vec<Value> = periods
+        .iter()
+        .map(|period| {
+            json!({
+                "start": period.start.to_string(),
+                "created": period.created,
+                "completed": period.completed,
+            })
+        })
+        .collect();
+    format!("{}\n", json!({ "by": by.name(), "periods": periods }))
+}
+
+pub fn completion(completion: &Completion, since: Option<NaiveDate>, format: Format) -> String {
+    let named = [("all", &completion.all)].into_iter().chain(
+        completion
+            .by_priority
+            .iter()
+            .map(|(priority, average)| (priority.to_string(), average)),
+    );
+    let average = |average: &Average| -> Value {
+        json!({ "completed": average.count, "average_seconds": average.seconds() })
+    };
+
+    if format == Format::Csv {
+        let mut out = csv_row(["priority", "completed", "average_seconds"].map(String::from));
+        for (name, average) in named {
+            let seconds = average.seconds().map(|s| s.to_string()).unwrap_or_default();
+            out.push_str(&csv_row([
+                name.to_lowercase(),
+                average.count.to_string(),
+                seconds,
+            ]));
+        }
+        return out;
+    }
+
+    let by_priority: serde_json::Map<String, Value> = completion
+        .by_priority
+        .iter()
+        .map(|(priority, a)| (priority.to_string().to_lowercase(), average(a)))
+        .collect();
+    let value = json!({
+        "since": since.map(|date| date.to_string()),
+        "all": average(&completion.all),
+        "by_priority": by_priority,
+    });
+    format!("{}\n", value)
+}
+
+pub fn streak(streak: &Streak, format: Format) -> String {
+    let last = streak.last.map(|date| date.to_string());
+    if format == Format::Csv {
+        return csv_row(["current", "longest", "last_completed"].map(String::from))
+            + &csv_row([
+                streak.current.to_string(),
+                streak.longest.to_string(),
+                last.unwrap_or_default(),
+            ]);
+    }
+
+    let value = json!({
+        "current": streak.current,
+        "longest": streak.longest,
+        "last_completed": last,
+    });
+    format!("{}\n", value)
+}
+
+pub fn burndown(days: &[(NaiveDate, usize)], format: Format) -> String {
+    if format == Format::Csv {
+        let mut out = csv_row(["date", "open"].map(String::from));
+        for (date, open) in days {
+            out.push_str(&csv_row([date.to_string(), open.to_string()]));
+        }
+        return out;
+    }
+
+    let days: Vec<Value> = days
+        .iter()
+        .map(|(date, open)| json!({ "date": date.to_string(), "open": open }))
+        .collect();
+    format!("{}\n", json!({ "days": days }))
+}
+
+pub fn log(entries: &[(&Operation, bool)], format: Format) -> String {
+    let values = entries.iter().map(|(operation, undone)| {
+        json!({
+            "time": format_timestamp(&operation.time),
+            "label": operation.label,
+            "undone": undone,
+        })
+    });
+
+    match format {
+        Format::Text | Format::Json => format!("{}\n", Value::Array(values.collect())),
+        Format::Ndjson => values.map(|value| format!("{}\n", value)).collect(),
+        Format::Csv => {
+            let columns = ["time", "label", "undone"];
+            let mut out = csv_row(columns.map(String::from));
+            for value in values {
+                out.push_str(&csv_row(columns.iter().map(|c| csv_value(&value[c]))));
+            }
+            out
+        }
+    }
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(csv_value)
+            .collect::<Vec<String>>()
+            .join(" "),
+        other => other.to_string(),
+    }
+}
+
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields.into_iter().map(|f| csv_field(&f)).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn sample() -> Task {
+        let mut task = Task::new(3, "Ship, \"v2\"".to_string(), Priority::High);
+        task.set_description("line one\nline two".to_string());
+        task.tags = vec!["release".to_string(), "work".to_string()];
+        task.blocked_by = vec![1, 2];
+        task
+    }
+
+    #[test]
+    fn test_task_fields_in_order() {
+        let value = task_json(&sample(), Local::now().naive_local());
+        let keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        assert_eq!(keys, TASK_COLUMNS.iter().collect::<Vec<_>>());
+        assert_eq!(value["priority"], "high");
+        assert_eq!(value["due"], Value::Null);
+        assert_eq!(value["tags"], json!(["release", "work"]));
+    }
+
+    #[test]
+    fn test_ndjson_is_one_task_per_line() {
+        let task = sample();
+        let out = tasks(&[&task, &task], Format::Ndjson, Local::now().naive_local());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(parsed["id"], 3);
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let task = sample();
+        let out = tasks(&[&task], Format::Csv, Local::now().naive_local());
+        let (header, row) = out.split_once("\r\n").unwrap();
+
+        assert_eq!(header, TASK_COLUMNS.join(","));
+        assert!(row.starts_with("3,\"Ship, \"\"v2\"\"\",\"line one\nline two\",false,high,"));
+        assert!(row.ends_with(",release work,,,1 2,0,todo\r\n"));
+    }
+}
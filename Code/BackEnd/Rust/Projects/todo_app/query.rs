@@ -0,0 +1,969 @@
+// Query language for `list`
+//
+//   list pending #work and (priority>=medium or due:overdue)
+//   list not done title~/^fix/i sort:due,-priority limit:5
+//
+// Terms next to each other are ANDed; OR joins alternatives and binds more
+// loosely than AND. NOT (or a leading `-`) negates the following term and
+// parentheses group. A term is one of:
+//
+//   keywords      all, done, completed, pending, open, urgent, high, medium,
+//                 low, someday, overdue, today, week, blocked, ready (open
+//                 and unblocked)
+//   markers       #tag, +project
+//   comparisons   <field><op><value> with op one of : = != < <= > >= ~
+//                   status:done|pending|overdue|blocked|ready, or any
+//                     status such as status:in-progress (workflow.rs)
+//                   priority>=medium
+//                   created, updated, completed, due against a date such as
+//                     2026-01-01, today or -7d
+//                   due:overdue|today|week|none|any
+//                   tag:work, project:website, project:none, id>10,
+//                   parent:3, parent:none
+//                   title~text, desc~text, text~text
+//   text          bare words, "quoted phrases" and /regex/ search the title
+//                 and description; /regex/i ignores case
+//
+// `sort:key[,key...]` (prefix a key with - to reverse it) and `limit:N`
+// may appear anywhere outside parentheses. Sort keys are id, priority
+// (highest first), due, created, updated, completed and title.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use regex::{Regex, RegexBuilder};
+
+use crate::due::Due;
+use crate::workflow::Status;
+use crate::{tags, Priority, Task};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    // Character offset and length of the offending token
+    pub start: usize,
+    pub len: usize,
+}
+
+impl QueryError {
+    fn at(span: Span, message: impl Into<String>) -> Self {
+        QueryError {
+            message: message.into(),
+            start: span.start,
+            len: span.len.max(1),
+        }
+    }
+
+    // The message followed by the query with the offending token underlined
+    pub fn render(&self, query: &str) -> String {
+        format!(
+            "{}\n    {}\n    {}{}",
+            self.message,
+            query,
+            " ".repeat(self.start),
+            "^".repeat(self.len)
+        )
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.start + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    start: usize,
+    len: usize,
+}
+
+impl Span {
+    fn sub(self, offset: usize, len: usize) -> Span {
+        Span {
+            start: self.start + offset,
+            len,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Word(String),
+}
+
+const OPERATOR_CHARS: [char; 6] = [':', '=', '!', '<', '>', '~'];
+
+fn tokenize(input: &str) -> Result<Vec<(Token, Span)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            let token = if c == '(' {
+                Token::LParen
+            } else {
+                Token::RParen
+            };
+            tokens.push((token, Span { start: i, len: 1 }));
+            i += 1;
+            continue;
+        }
+
+        // A word runs to the next space or parenthesis, except inside
+        // "quotes" or a /regex/, which may contain either
+        let start = i;
+        let mut word = String::new();
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+            let c = chars[i];
+            let opens_regex =
+                c == '/' && (word.is_empty() || word == "-" || word.ends_with(OPERATOR_CHARS));
+            if c != '"' && !opens_regex {
+                word.push(c);
+                i += 1;
+                continue;
+            }
+
+            let open = i;
+            word.push(c);
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        let what = if c == '"' { "quote" } else { "regex" };
+                        let span = Span {
+                            start: open,
+                            len: 1,
+                        };
+                        return Err(QueryError::at(span, format!("unterminated {}", what)));
+                    }
+                    Some('\\') if i + 1 < chars.len() => {
+                        word.push('\\');
+                        word.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&next) => {
+                        word.push(next);
+                        i += 1;
+                        if next == c {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        tokens.push((
+            Token::Word(word),
+            Span {
+                start,
+                len: i - start,
+            },
+        ));
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering == Ordering::Equal,
+            CmpOp::Ne => ordering != Ordering::Equal,
+            CmpOp::Lt => ordering == Ordering::Less,
+            CmpOp::Le => ordering != Ordering::Greater,
+            CmpOp::Gt => ordering == Ordering::Greater,
+            CmpOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateField {
+    Created,
+    Updated,
+    Completed,
+    Due,
+}
+
+impl DateField {
+    fn value(self, task: &Task) -> Option<NaiveDate> {
+        match self {
+            DateField::Created => Some(task.created_at.with_timezone(&Local).date_naive()),
+            DateField::Updated => Some(task.updated_at.with_timezone(&Local).date_naive()),
+            DateField::Completed => task
+                .completed_at
+                .map(|t| t.with_timezone(&Local).date_naive()),
+            DateField::Due => task.due.map(|d| d.date),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextField {
+    Title,
+    Description,
+    Any,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Substring(needle) => text.to_lowercase().contains(needle),
+            Matcher::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    All,
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    // Neither done nor cancelled
+    Open,
+    Status(Status),
+    Overdue,
+    DueToday,
+    DueThisWeek,
+    HasDue(bool),
+    Blocked,
+    Parent(Option<u32>),
+    Priority(CmpOp, Priority),
+    Date(DateField, CmpOp, NaiveDate),
+    Tag(String),
+    Project(Option<String>),
+    Id(CmpOp, u32),
+    Text(TextField, Matcher),
+}
+
+// Higher is more important, for priority comparisons
+fn priority_level(priority: &Priority) -> u8 {
+    4 - priority.rank()
+}
+
+impl Expr {
+    // `tasks` is the whole list, needed to tell whether blockers are done
+    fn matches(&self, task: &Task, tasks: &HashMap<u32, Task>, now: NaiveDateTime) -> bool {
+        let today = now.date();
+        match self {
+            Expr::All => true,
+            Expr::Not(expr) => !expr.matches(task, tasks, now),
+            Expr::And(a, b) => a.matches(task, tasks, now) && b.matches(task, tasks, now),
+            Expr::Or(a, b) => a.matches(task, tasks, now) || b.matches(task, tasks, now),
+            Expr::Open => !task.status.is_closed(),
+            Expr::Status(status) => task.status == *status,
+            Expr::Overdue => task.is_overdue(now),
+            Expr::DueToday => task.due.is_some_and(|d| d.is_today(today)),
+            Expr::DueThisWeek => task.due.is_some_and(|d| d.is_this_week(today)),
+            Expr::HasDue(has) => task.due.is_some() == *has,
+            Expr::Blocked => {
+                !task.status.is_closed()
+                    && task
+                        .blocked_by
+                        .iter()
+                        .any(|id| tasks.get(id).is_some_and(|t| !t.status.is_closed()))
+            }
+            Expr::Parent(parent) => task.parent == *parent,
+            Expr::Priority(op, priority) => {
+                op.test(priority_level(&task.priority).cmp(&priority_level(priority)))
+            }
+            Expr::Date(field, op, date) => field.value(task).is_some_and(|d| op.test(d.cmp(date))),
+            Expr::Tag(tag) => task.tags.contains(tag),
+            Expr::Project(project) => task.project == *project,
+            Expr::Id(op, id) => op.test(task.id.cmp(id)),
+            Expr::Text(field, matcher) => {
+                let in_title = || matcher.is_match(&task.title);
+                let in_desc = || {
+                    task.description
+                        .as_deref()
+                        .is_some_and(|d| matcher.is_match(d))
+                };
+                match field {
+                    TextField::Title => in_title(),
+                    TextField::Description => in_desc(),
+                    TextField::Any => in_title() || in_desc(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Id,
+    Priority,
+    Due,
+    Created,
+    Updated,
+    Completed,
+    Title,
+}
+
+impl SortKey {
+    fn parse(name: &str) -> Option<SortKey> {
+        match name {
+            "id" => Some(SortKey::Id),
+            "priority" | "pri" => Some(SortKey::Priority),
+            "due" => Some(SortKey::Due),
+            "created" => Some(SortKey::Created),
+            "updated" => Some(SortKey::Updated),
+            "completed" => Some(SortKey::Completed),
+            "title" => Some(SortKey::Title),
+            _ => None,
+        }
+    }
+
+    // Tasks without a value sort last in either direction
+    fn compare(self, a: &Task, b: &Task, descending: bool) -> Ordering {
+        fn optional<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) if descending => b.cmp(&a),
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        match self {
+            SortKey::Id => optional(Some(a.id), Some(b.id), descending),
+            SortKey::Priority => {
+                optional(Some(a.priority.rank()), Some(b.priority.rank()), descending)
+            }
+            SortKey::Due => optional(
+                a.due.map(|d| (d.date, d.time)),
+                b.due.map(|d| (d.date, d.time)),
+                descending,
+            ),
+            SortKey::Created => optional(Some(a.created_at), Some(b.created_at), descending),
+            SortKey::Updated => optional(Some(a.updated_at), Some(b.updated_at), descending),
+            SortKey::Completed => optional(a.completed_at, b.completed_at, descending),
+            SortKey::Title => optional(
+                Some(a.title.to_lowercase()),
+                Some(b.title.to_lowercase()),
+                descending,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    expr: Option<Expr>,
+    sort: Vec<(SortKey, bool)>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn parse(input: &str, today: NaiveDate) -> Result<Query, QueryError> {
+        let mut query = Query::default();
+        let mut sort_given = false;
+        let mut terms = Vec::new();
+        let mut depth = 0;
+
+        // sort: and limit: are clauses rather than filter terms
+        for (token, span) in tokenize(input)? {
+            match &token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                Token::Word(word) => {
+                    let lower = word.to_lowercase();
+                    let clause = lower
+                        .strip_prefix("sort:")
+                        .map(|v| (true, v))
+                        .or_else(|| lower.strip_prefix("limit:").map(|v| (false, v)));
+                    if let Some((is_sort, value)) = clause {
+                        if depth > 0 {
+                            return Err(QueryError::at(
+                                span,
+                                "sort: and limit: can't be used inside parentheses",
+                            ));
+                        }
+                        let name_len = if is_sort { 5 } else { 6 };
+                        let value_span = span.sub(name_len, span.len - name_len);
+                        if is_sort {
+                            if sort_given {
+                                return Err(QueryError::at(span, "sort: given more than once"));
+                            }
+                            sort_given = true;
+                            query.sort = parse_sort(value, value_span)?;
+                        } else {
+                            if query.limit.is_some() {
+                                return Err(QueryError::at(span, "limit: given more than once"));
+                            }
+                            let limit = value.parse().map_err(|_| {
+                                QueryError::at(value_span, format!("invalid limit '{}'", value))
+                            })?;
+                            query.limit = Some(limit);
+                        }
+                        continue;
+                    }
+                }
+            }
+            terms.push((token, span));
+        }
+
+        if !terms.is_empty() {
+            let mut parser = Parser {
+                tokens: &terms,
+                pos: 0,
+                end: input.chars().count(),
+                today,
+            };
+            let expr = parser.parse_or()?;
+            if let Some((_, span)) = terms.get(parser.pos) {
+                return Err(QueryError::at(*span, "unexpected ')'"));
+            }
+            query.expr = Some(expr);
+        }
+
+        Ok(query)
+    }
+
+    pub fn matches(&self, task: &Task, tasks: &HashMap<u32, Task>, now: NaiveDateTime) -> bool {
+        self.expr
+            .as_ref()
+            .is_none_or(|expr| expr.matches(task, tasks, now))
+    }
+
+    pub fn has_sort(&self) -> bool {
+        !self.sort.is_empty()
+    }
+
+    // Ordering from the sort: clause, Equal when it doesn't decide
+    pub fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        self.sort
+            .iter()
+            .map(|(key, descending)| key.compare(a, b, *descending))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+fn parse_sort(value: &str, span: Span) -> Result<Vec<(SortKey, bool)>, QueryError> {
+    let mut keys = Vec::new();
+    let mut offset = 0;
+
+    for part in value.split(',') {
+        let len = part.chars().count();
+        let (name, descending) = match part.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (part, false),
+        };
+        let key = SortKey::parse(name).ok_or_else(|| {
+            QueryError::at(
+                span.sub(offset, len),
+                format!("unknown sort key '{}'", name),
+            )
+        })?;
+        keys.push((key, descending));
+        offset += len + 1;
+    }
+
+    Ok(keys)
+}
+
+struct Parser<'a> {
+    tokens: &'a [(Token, Span)],
+    pos: usize,
+    // Length of the input, for errors at the end of the query
+    end: usize,
+    today: NaiveDate,
+}
+
+impl Parser<'_> {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some((Token::Word(word), _)) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_not()?;
+        loop {
+            match self.tokens.get(self.pos) {
+                None | Some((Token::RParen, _)) => break,
+                _ if self.peek_keyword("or") => break,
+                _ if self.peek_keyword("and") => self.pos += 1,
+                _ => {}
+            }
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, QueryError> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        let Some((token, span)) = self.tokens.get(self.pos).cloned() else {
+            let span = Span {
+                start: self.end,
+                len: 1,
+            };
+            return Err(QueryError::at(
+                span,
+                "expected a filter term at the end of the query",
+            ));
+        };
+        self.pos += 1;
+
+        match token {
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                match self.tokens.get(self.pos) {
+                    Some((Token::RParen, _)) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(QueryError::at(span, "this '(' is never closed")),
+                }
+            }
+            Token::RParen => Err(QueryError::at(span, "expected a filter term before ')'")),
+            Token::Word(word) => {
+                let lower = word.to_lowercase();
+                if matches!(lower.as_str(), "and" | "or") {
+                    return Err(QueryError::at(
+                        span,
+                        format!("expected a filter term before '{}'", word),
+                    ));
+                }
+                match word.strip_prefix('-') {
+                    Some(rest) if !rest.is_empty() => {
+                        let term = self.parse_term(rest, span.sub(1, span.len - 1))?;
+                        Ok(Expr::Not(Box::new(term)))
+                    }
+                    _ => self.parse_term(&word, span),
+                }
+            }
+        }
+    }
+
+    fn parse_term(&self, word: &str, span: Span) -> Result<Expr, QueryError> {
+        let keyword = match word.to_lowercase().as_str() {
+            "all" => Some(Expr::All),
+            "done" | "completed" => Some(Expr::Status(Status::Done)),
+            "pending" | "open" => Some(Expr::Open),
+            "urgent" => Some(Expr::Priority(CmpOp::Eq, Priority::Urgent)),
+            "high" => Some(Expr::Priority(CmpOp::Eq, Priority::High)),
+            "medium" => Some(Expr::Priority(CmpOp::Eq, Priority::Medium)),
+            "low" => Some(Expr::Priority(CmpOp::Eq, Priority::Low)),
+            "someday" => Some(Expr::Priority(CmpOp::Eq, Priority::Someday)),
+            "overdue" => Some(Expr::Overdue),
+            "today" => Some(Expr::DueToday),
+            "week" => Some(Expr::DueThisWeek),
+            "blocked" => Some(Expr::Blocked),
+            "ready" => Some(ready()),
+            _ => None,
+        };
+        if let Some(expr) = keyword {
+            return Ok(expr);
+        }
+
+        if let Some(tag) = tags::parse_tag(word) {
+            return Ok(Expr::Tag(tag));
+        }
+        if let Some(project) = tags::parse_project(word) {
+            return Ok(Expr::Project(Some(project)));
+        }
+        if word.starts_with('"') || word.starts_with('/') {
+            return Ok(Expr::Text(TextField::Any, parse_matcher(word, span)?));
+        }
+
+        match split_comparison(word) {
+            Some((field, op, value)) => {
+                let field_len = field.chars().count();
+                let op_span = span.sub(field_len, op.len());
+                let value_offset = field_len + op.len();
+                let value_span = span.sub(value_offset, span.len - value_offset);
+                if value.is_empty() {
+                    return Err(QueryError::at(
+                        op_span,
+                        format!("missing value after '{}'", op),
+                    ));
+                }
+                self.parse_comparison(
+                    field,
+                    span.sub(0, field_len),
+                    op,
+                    op_span,
+                    value,
+                    value_span,
+                )
+            }
+            None => Ok(Expr::Text(
+                TextField::Any,
+                Matcher::Substring(word.to_lowercase()),
+            )),
+        }
+    }
+
+    fn parse_comparison(
+        &self,
+        field: &str,
+        field_span: Span,
+        op: &str,
+        op_span: Span,
+        value: &str,
+        value_span: Span,
+    ) -> Result<Expr, QueryError> {
+        let lower = value.to_lowercase();
+        let field_name = field.to_lowercase();
+        let bad_op = || {
+            QueryError::at(
+                op_span,
+                format!("operator '{}' can't be used with {}", op, field_name),
+            )
+        };
+        let bad_value =
+            |what: &str| QueryError::at(value_span, format!("invalid {} '{}'", what, value));
+
+        let cmp = match op {
+            ":" | "=" => Some(CmpOp::Eq),
+            "!=" => Some(CmpOp::Ne),
+            "<" => Some(CmpOp::Lt),
+            "<=" => Some(CmpOp::Le),
+            ">" => Some(CmpOp::Gt),
+            ">=" => Some(CmpOp::Ge),
+            _ => None,
+        };
+        // Fields that only support equality get != by negation
+        let equality = |expr: Expr| match op {
+            ":" | "=" => Ok(expr),
+            "!=" => Ok(Expr::Not(Box::new(expr))),
+            _ => Err(bad_op()),
+        };
+
+        match field_name.as_str() {
+            "status" | "is" => equality(match lower.as_str() {
+                "pending" | "open" => Expr::Open,
+                "overdue" => Expr::Overdue,
+                "blocked" => Expr::Blocked,
+                "ready" => ready(),
+                _ => Expr::Status(Status::parse(value).map_err(|_| bad_value("status"))?),
+            }),
+            "priority" | "pri" => {
+                let priority = Priority::from_str(value).ok_or_else(|| bad_value("priority"))?;
+                Ok(Expr::Priority(cmp.ok_or_else(bad_op)?, priority))
+            }
+            "created" | "updated" | "completed" | "due" => {
+                let date_field = match field_name.as_str() {
+                    "created" => DateField::Created,
+                    "updated" => DateField::Updated,
+                    "completed" => DateField::Completed,
+                    _ => DateField::Due,
+                };
+                if date_field == DateField::Due {
+                    let special = match lower.as_str() {
+                        "overdue" => Some(Expr::Overdue),
+                        "today" => Some(Expr::DueToday),
+                        "week" => Some(Expr::DueThisWeek),
+                        "none" => Some(Expr::HasDue(false)),
+                        "any" => Some(Expr::HasDue(true)),
+                        _ => None,
+                    };
+                    if let Some(expr) = special {
+                        return equality(expr);
+                    }
+                }
+                let date = Due::parse(value, self.today)
+                    .ok()
+                    .filter(|due| due.time.is_none())
+                    .ok_or_else(|| bad_value("date"))?;
+                Ok(Expr::Date(date_field, cmp.ok_or_else(bad_op)?, date.date))
+            }
+            "tag" => {
+                let tag =
+                    tags::parse_tag(&format!("#{}", value)).ok_or_else(|| bad_value("tag"))?;
+                equality(Expr::Tag(tag))
+            }
+            "project" | "proj" => {
+                if lower == "none" {
+                    return equality(Expr::Project(None));
+                }
+                let project = tags::parse_project(&format!("+{}", value))
+                    .ok_or_else(|| bad_value("project"))?;
+                equality(Expr::Project(Some(project)))
+            }
+            "parent" => {
+                if lower == "none" {
+                    return equality(Expr::Parent(None));
+                }
+                let id = value.parse().map_err(|_| bad_value("id"))?;
+                equality(Expr::Parent(Some(id)))
+            }
+            "id" => {
+                let id = value.parse().map_err(|_| bad_value("id"))?;
+                Ok(Expr::Id(cmp.ok_or_else(bad_op)?, id))
+            }
+            "title" | "desc" | "description" | "text" => {
+                let text_field = match field_name.as_str() {
+                    "title" => TextField::Title,
+                    "text" => TextField::Any,
+                    _ => TextField::Description,
+                };
+                let expr = Expr::Text(text_field, parse_matcher(value, value_span)?);
+                match op {
+                    "~" | ":" => Ok(expr),
+                    "!=" => Ok(Expr::Not(Box::new(expr))),
+                    _ => Err(bad_op()),
+                }
+            }
+            _ => Err(QueryError::at(
+                field_span,
+                format!("unknown field '{}'", field),
+            )),
+        }
+    }
+}
+
+// Open tasks that aren't waiting on anything
+fn ready() -> Expr {
+    Expr::And(
+        Box::new(Expr::Open),
+        Box::new(Expr::Not(Box::new(Expr::Blocked))),
+    )
+}
+
+// Splits `field<op>value`, or None when the word isn't a comparison
+fn split_comparison(word: &str) -> Option<(&str, &str, &str)> {
+    let op_start = word.find(OPERATOR_CHARS)?;
+    let field = &word[..op_start];
+    if field.is_empty() || !field.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let rest = &word[op_start..];
+    let op = ["!=", "<=", ">=", ":", "=", "<", ">", "~"]
+        .into_iter()
+        .find(|op| rest.starts_with(op))?;
+    Some((field, op, &rest[op.len()..]))
+}
+
+fn parse_matcher(value: &str, span: Span) -> Result<Matcher, QueryError> {
+    if let Some(quoted) = value.strip_prefix('"') {
+        let inner = quoted.strip_suffix('"').unwrap_or(quoted);
+        let text = inner.replace("\\\"", "\"").replace("\\\\", "\\");
+        return Ok(Matcher::Substring(text.to_lowercase()));
+    }
+
+    if let Some(regex) = value.strip_prefix('/') {
+        let close = regex
+            .rfind('/')
+            .ok_or_else(|| QueryError::at(span, "unterminated regex"))?;
+        let (pattern, flags) = (&regex[..close], &regex[close + 1..]);
+        if let Some(flag) = flags.chars().find(|&c| c != 'i') {
+            return Err(QueryError::at(
+                span,
+                format!("unknown regex flag '{}'", flag),
+            ));
+        }
+        let regex = RegexBuilder::new(&pattern.replace("\\/", "/"))
+            .case_insensitive(flags.contains('i'))
+            .build()
+            .map_err(|e| {
+                let reason = e.to_string();
+                let reason = reason
+                    .lines()
+                    .last()
+                    .unwrap_or("")
+                    .trim_start_matches("error: ");
+                QueryError::at(span, format!("invalid regex: {}", reason))
+            })?;
+        return Ok(Matcher::Regex(regex));
+    }
+
+    Ok(Matcher::Substring(value.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Days, Utc};
+
+    fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    fn task(id: u32, title: &str, priority: Priority) -> Task {
+        let mut task = Task::new(id, title.to_string(), priority);
+        task.tags = vec!["work".to_string()];
+        task
+    }
+
+    fn matching(query: &str, tasks: &[Task]) -> Vec<u32> {
+        let query = Query::parse(query, today()).unwrap();
+        let now = Local::now().naive_local();
+        let all: HashMap<u32, Task> = tasks.iter().map(|t| (t.id, t.clone())).collect();
+        tasks
+            .iter()
+            .filter(|t| query.matches(t, &all, now))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    fn sample() -> Vec<Task> {
+        let mut a = task(1, "Fix login bug", Priority::High);
+        a.due = Some(Due::parse("yesterday", today()).unwrap());
+        let mut b = task(2, "Write docs", Priority::Medium);
+        b.description = Some("API reference".to_string());
+        b.tags.clear();
+        b.status = Status::InProgress;
+        let mut c = task(3, "Fix typo", Priority::Low);
+        c.status = Status::Done;
+        c.completed_at = Some(Utc::now());
+        vec![a, b, c]
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        let tasks = sample();
+        assert_eq!(matching("fix", &tasks), vec![1, 3]);
+        assert_eq!(matching("fix and not done", &tasks), vec![1]);
+        assert_eq!(matching("fix -done", &tasks), vec![1]);
+        assert_eq!(matching("low or high", &tasks), vec![1, 3]);
+        assert_eq!(matching("#work (low or medium)", &tasks), vec![3]);
+        assert_eq!(matching("not (#work or done)", &tasks), vec![2]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let tasks = sample();
+        assert_eq!(matching("priority>=medium", &tasks), vec![1, 2]);
+        assert_eq!(matching("pri<high", &tasks), vec![2, 3]);
+        assert_eq!(matching("due:overdue", &tasks), vec![1]);
+        assert_eq!(matching("due:none", &tasks), vec![2, 3]);
+        assert_eq!(matching("created<2000-01-01", &tasks), Vec::<u32>::new());
+        assert_eq!(matching("created>=today", &tasks), vec![1, 2, 3]);
+        assert_eq!(matching("completed:today", &tasks), vec![3]);
+        assert_eq!(matching("id!=2 status:pending", &tasks), vec![1]);
+        assert_eq!(matching("status:doing or status:done", &tasks), vec![2, 3]);
+        assert_eq!(matching("status:review", &tasks), Vec::<u32>::new());
+        assert!(Query::parse("status:later!", today()).is_err());
+        assert_eq!(matching("tag:work project:none", &tasks), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_blocked_and_parent() {
+        let mut tasks = sample();
+        tasks[1].blocked_by = vec![1];
+        tasks[0].blocked_by = vec![3];
+        tasks[2].parent = Some(2);
+        assert_eq!(matching("blocked", &tasks), vec![2]);
+        assert_eq!(matching("ready", &tasks), vec![1]);
+        assert_eq!(matching("parent:2", &tasks), vec![3]);
+        assert_eq!(matching("parent:none", &tasks), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_text_and_regex() {
+        let tasks = sample();
+        assert_eq!(matching("desc~api", &tasks), vec![2]);
+        assert_eq!(matching("\"login bug\"", &tasks), vec![1]);
+        assert!(matching("title~/^fix (login|typo)$/", &tasks).is_empty());
+        assert_eq!(matching("title~/^fix/i", &tasks), vec![1, 3]);
+        assert_eq!(matching("/(docs|typo)$/", &tasks), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_sort_and_limit() {
+        let tasks = sample();
+        let query = Query::parse("sort:-priority limit:2", today()).unwrap();
+        let mut sorted: Vec<&Task> = tasks.iter().collect();
+        sorted.sort_by(|a, b| query.compare(a, b));
+        assert_eq!(
+            sorted.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(query.limit, Some(2));
+
+        let query = Query::parse("sort:due", today()).unwrap();
+        sorted.sort_by(|a, b| query.compare(a, b).then(a.id.cmp(&b.id)));
+        assert_eq!(sorted[0].id, 1);
+    }
+
+    #[test]
+    fn test_errors_point_at_token() {
+        let err = Query::parse("pending prio>=high", today()).unwrap_err();
+        assert_eq!((err.start, err.len), (8, 4));
+        assert!(err.message.contains("unknown field"));
+
+        let err = Query::parse("priority>=severe", today()).unwrap_err();
+        assert_eq!((err.start, err.len), (10, 6));
+
+        let err = Query::parse("title<abc", today()).unwrap_err();
+        assert_eq!((err.start, err.len), (5, 1));
+
+        let err = Query::parse("(high or low", today()).unwrap_err();
+        assert_eq!(err.start, 0);
+
+        let err = Query::parse("high or", today()).unwrap_err();
+        assert_eq!(err.start, 7);
+
+        let err = Query::parse("high)", today()).unwrap_err();
+        assert_eq!(err.start, 4);
+
+        let err = Query::parse("title~/fix(/", today()).unwrap_err();
+        assert!(err.message.starts_with("invalid regex"));
+
+        let err = Query::parse("sort:size", today()).unwrap_err();
+        assert_eq!((err.start, err.len), (5, 4));
+
+        let err = Query::parse("\"open quote", today()).unwrap_err();
+        assert_eq!(err.start, 0);
+        assert!(err.render("\"open quote").ends_with("\n    ^"));
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        let tasks = sample();
+        let week_ago = today() - Days::new(7);
+        let query = format!("created>{}", week_ago.format("%Y-%m-%d"));
+        assert_eq!(matching(&query, &tasks), matching("created>-7d", &tasks));
+    }
+}
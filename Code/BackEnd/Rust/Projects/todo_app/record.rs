@@ -0,0 +1,259 @@
+// On-disk record format for the todo file
+//
+// Version 2 and later files start with a header line and store one task per line as
+// `key=value` fields separated by `|`. Backslashes, pipes, newlines and
+// carriage returns inside values are escaped, so a title or description can
+// hold any text. Files without a header are version 1: six unescaped fields
+// in a fixed order, which we can still read and upgrade.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::Task;
+
+pub const FORMAT_VERSION: u32 = 10;
+const HEADER_PREFIX: &str = "# todo-app format v";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub struct ParsedFile {
+    pub version: u32,
+    pub tasks: Vec<Task>,
+    pub errors: Vec<ParseError>,
+}
+
+pub fn header() -> String {
+    format!("{}{}", HEADER_PREFIX, FORMAT_VERSION)
+}
+
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Splits a line on unescaped `|` and unescapes every field
+pub fn split_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\\') => current.push('\\'),
+                Some('|') => current.push('|'),
+                Some('n') => current.push('\n'),
+                Some('r') => current.push('\r'),
+                Some(other) => return Err(format!("invalid escape sequence '\\{}'", other)),
+                None => return Err("line ends with a lone '\\'".to_string()),
+            },
+            '|' => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    fields.push(current);
+    Ok(fields)
+}
+
+#[derive(Debug, Default)]
+pub struct Record {
+    fields: Vec<(String, String)>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.fields.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn encode(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, escape(value)))
+            .collect::<Vec<String>>()
+            .join("|")
+    }
+
+    pub fn decode(line: &str, known_keys: &[&str]) -> Result<Record, String> {
+        let mut record = Record::new();
+
+        for field in split_fields(line)? {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("field '{}' is not in key=value form", field))?;
+
+            if !known_keys.contains(&key) {
+                return Err(format!("unknown field '{}'", key));
+            }
+            if record.get(key).is_some() {
+                return Err(format!("field '{}' appears more than once", key));
+            }
+            record.push(key, value);
+        }
+
+        Ok(record)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn require(&self, key: &str) -> Result<&str, String> {
+        self.get(key)
+            .ok_or_else(|| format!("missing field '{}'", key))
+    }
+}
+
+// Reads the version from a header line, or None if the line is not a header
+fn parse_header(line: &str) -> Option<Result<u32, String>> {
+    let version = line.strip_prefix(HEADER_PREFIX)?;
+    Some(
+        version
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid format version '{}'", version.trim())),
+    )
+}
+
+pub fn parse_file(content: &str) -> Result<ParsedFile, String> {
+    let mut lines = content.lines().enumerate().peekable();
+
+    let version = match lines.peek().and_then(|(_, line)| parse_header(line)) {
+        Some(version) => {
+            lines.next();
+            version?
+        }
+        None => 1,
+    };
+
+    if version > FORMAT_VERSION {
+        return Err(format!(
+            "file uses format v{}, but this version of todo only understands up to v{}",
+            version, FORMAT_VERSION
+        ));
+    }
+
+    let mut parsed = ParsedFile {
+        version,
+        tasks: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let mut seen_ids = HashSet::new();
+
+    for (index, line) in lines {
+        if line.trim().is_empty() || (version > 1 && line.starts_with('#')) {
+            continue;
+        }
+
+        let result = if version == 1 {
+            Task::from_legacy_format(line)
+        } else {
+            Task::from_file_format(line)
+        };
+
+        match result {
+            Ok(task) if !seen_ids.insert(task.id) => parsed.errors.push(ParseError {
+                line: index + 1,
+                message: format!("duplicate task id {}", task.id),
+            }),
+            Ok(task) => parsed.tasks.push(task),
+            Err(message) => parsed.errors.push(ParseError {
+                line: index + 1,
+                message,
+            }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    #[test]
+    fn test_escape_round_trip() {
+        let value = "a|b\\c\nd\re";
+        let line = format!("{}|{}", escape(value), escape("plain"));
+        assert_eq!(split_fields(&line).unwrap(), vec![value, "plain"]);
+    }
+
+    #[test]
+    fn test_invalid_escape() {
+        assert!(split_fields("abc\\x").is_err());
+        assert!(split_fields("abc\\").is_err());
+    }
+
+    #[test]
+    fn test_task_with_separators_survives() {
+        let mut task = Task::new(7, "Fix a|b parser".to_string(), Priority::High);
+        task.set_description("first line\nsecond | line".to_string());
+
+        let content = format!("{}\n{}\n", header(), task.to_file_format());
+        let parsed = parse_file(&content).unwrap();
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].title, task.title);
+        assert_eq!(parsed.tasks[0].description, task.description);
+    }
+
+    #[test]
+    fn test_legacy_file() {
+        let content = "1|Buy milk||false|High|2024-01-01 12:00:00\n2|Broken|line\n";
+        let parsed = parse_file(content).unwrap();
+
+        assert_eq!(parsed.version, 1);
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].title, "Buy milk");
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 2);
+    }
+
+    #[test]
+    fn test_reports_bad_lines() {
+        let content = format!(
+            "{}\nid=1|title=ok|done=maybe|priority=Low|created=2026-01-01T00:00:00Z\n",
+            header()
+        );
+        let parsed = parse_file(&content).unwrap();
+
+        assert!(parsed.tasks.is_empty());
+        assert_eq!(parsed.errors[0].line, 2);
+        assert!(parsed.errors[0].message.contains("done"));
+    }
+
+    #[test]
+    fn test_newer_version_rejected() {
+        let content = format!("{}{}\n", HEADER_PREFIX, FORMAT_VERSION + 1);
+        assert!(parse_file(&content).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+// Recurrence rules for repeating tasks
+//
+// A rule only decides which date comes next. Completing a recurring task is
+// handled by TodoApp::toggle_task, which closes the current occurrence and
+// creates the next one. Dates run out some 260,000 years from now, so a rule
+// can have no next date; toggle_task then refuses to complete the task.
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+use crate::due::parse_weekday;
+
+// "<n>d" repeats at most every ten years
+const MAX_DAYS: u32 = 3650;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+impl Recurrence {
+    // Accepts "daily", "weekdays", "weekly", "weekly:mon,fri", "monthly",
+    // "monthly:15" and "<n>d". Bare "weekly" and "monthly" repeat on the
+    // anchor date's weekday or day of month.
+    pub fn parse(input: &str, anchor: NaiveDate) -> Result<Recurrence, String> {
+        let input = input.trim().to_lowercase();
+        let invalid = || format!("can't understand repeat rule '{}'", input);
+
+        let (kind, arg) = match input.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (input.as_str(), None),
+        };
+
+        let rule = match (kind, arg) {
+            ("daily" | "day", None) => Recurrence::Daily,
+            ("weekdays", None) => Recurrence::Weekly(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ]),
+            ("weekly" | "week", None) => Recurrence::Weekly(vec![anchor.weekday()]),
+            ("weekly" | "week", Some(days)) => {
+                let mut weekdays = Vec::new();
+                for day in days.split(',') {
+                    let weekday = parse_weekday(day.trim()).ok_or_else(invalid)?;
+                    if !weekdays.contains(&weekday) {
+                        weekdays.push(weekday);
+                    }
+                }
+                weekdays.sort_by_key(|d| d.num_days_from_monday());
+                Recurrence::Weekly(weekdays)
+            }
+            ("monthly" | "month", None) => Recurrence::Monthly(anchor.day()),
+            ("monthly" | "month", Some(day)) => match day.parse() {
+                Ok(day @ 1..=31) => Recurrence::Monthly(day),
+                _ => return Err(invalid()),
+            },
+            (days, None) => match days.strip_suffix('d').map(|n| n.parse()) {
+                Some(Ok(n @ 1..=MAX_DAYS)) => Recurrence::EveryNDays(n),
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+
+        Ok(rule)
+    }
+
+    // The first occurrence strictly after `date`; None past the last date
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::EveryNDays(n) => date.checked_add_days(Days::new((*n).into())),
+            Recurrence::Weekly(days) => (1..=7)
+                .map_while(|offset| date.checked_add_days(Days::new(offset)))
+                .find(|d| days.contains(&d.weekday())),
+            Recurrence::Monthly(day) => {
+                let this_month = day_in_month(date, *day);
+                if this_month > date {
+                    Some(this_month)
+                } else {
+                    Some(day_in_month(date.checked_add_months(Months::new(1))?, *day))
+                }
+            }
+        }
+    }
+
+    // The first occurrence on or after `date`, used to schedule a new
+    // recurring task that has no due date yet
+    pub fn first_on_or_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::EveryNDays(_) => Some(date),
+            _ => self.next_after(date.pred_opt()?),
+        }
+    }
+
+    // The next occurrence after `date` that isn't today or earlier, so a task
+    // completed late doesn't come back already overdue
+    pub fn next_from(&self, date: NaiveDate, today: NaiveDate) -> Option<NaiveDate> {
+        let mut next = self.next_after(date)?;
+        while next <= today {
+            next = self.next_after(next)?;
+        }
+        Some(next)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly(days) => {
+                let names: Vec<String> = days.iter().map(|d| d.to_string()).collect();
+                format!("weekly on {}", names.join(", "))
+            }
+            Recurrence::Monthly(day) => format!("monthly on day {}", day),
+            Recurrence::EveryNDays(n) => format!("every {} days", n),
+        }
+    }
+
+    pub fn to_file_format(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly(days) => {
+                let names: Vec<String> =
+                    days.iter().map(|d| d.to_string().to_lowercase()).collect();
+                format!("weekly:{}", names.join(","))
+            }
+            Recurrence::Monthly(day) => format!("monthly:{}", day),
+            Recurrence::EveryNDays(n) => format!("{}d", n),
+        }
+    }
+
+    pub fn from_file_format(s: &str) -> Result<Recurrence, String> {
+        // Stored rules always carry their weekday or day, so the anchor is unused
+        Recurrence::parse(s, NaiveDate::MIN)
+    }
+}
+
+// The given day of `date`'s month, clamped to the month's last day
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| date.with_day(d))
+        .unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_daily_and_every_n_days() {
+        assert_eq!(
+            Recurrence::Daily.next_after(date(2026, 12, 31)),
+            Some(date(2027, 1, 1))
+        );
+        assert_eq!(
+            Recurrence::EveryNDays(10).next_after(date(2026, 1, 25)),
+            Some(date(2026, 2, 4))
+        );
+    }
+
+    #[test]
+    fn test_weekly() {
+        // 2026-10-14 is a Wednesday
+        let rule = Recurrence::parse("weekly:fri,mon", date(2026, 10, 14)).unwrap();
+        assert_eq!(rule, Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]));
+        assert_eq!(
+            rule.next_after(date(2026, 10, 14)),
+            Some(date(2026, 10, 16))
+        );
+        assert_eq!(
+            rule.next_after(date(2026, 10, 16)),
+            Some(date(2026, 10, 19))
+        );
+
+        let rule = Recurrence::parse("weekly", date(2026, 10, 14)).unwrap();
+        assert_eq!(
+            rule.next_after(date(2026, 10, 14)),
+            Some(date(2026, 10, 21))
+        );
+    }
+
+    #[test]
+    fn test_monthly_clamps_short_months() {
+        let rule = Recurrence::parse("monthly:31", date(2026, 1, 1)).unwrap();
+        assert_eq!(rule.next_after(date(2026, 1, 31)), Some(date(2026, 2, 28)));
+        assert_eq!(rule.next_after(date(2026, 2, 28)), Some(date(2026, 3, 31)));
+        assert_eq!(rule.next_after(date(2026, 3, 5)), Some(date(2026, 3, 31)));
+    }
+
+    #[test]
+    fn test_next_from_skips_past_dates() {
+        let rule = Recurrence::Daily;
+        let today = date(2026, 10, 14);
+        assert_eq!(
+            rule.next_from(date(2026, 10, 1), today),
+            Some(date(2026, 10, 15))
+        );
+        assert_eq!(
+            rule.next_from(date(2026, 10, 20), today),
+            Some(date(2026, 10, 21))
+        );
+    }
+
+    #[test]
+    fn test_first_on_or_after() {
+        let today = date(2026, 10, 14);
+        assert_eq!(Recurrence::Daily.first_on_or_after(today), Some(today));
+        assert_eq!(
+            Recurrence::EveryNDays(3).first_on_or_after(today),
+            Some(today)
+        );
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Wed]).first_on_or_after(today),
+            Some(today)
+        );
+        assert_eq!(
+            Recurrence::Monthly(1).first_on_or_after(today),
+            Some(date(2026, 11, 1))
+        );
+    }
+
+    #[test]
+    fn test_no_date_past_the_last() {
+        let last = NaiveDate::MAX;
+        assert_eq!(Recurrence::Daily.next_after(last), None);
+        assert_eq!(Recurrence::EveryNDays(MAX_DAYS).next_after(last), None);
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Mon]).next_after(last),
+            None
+        );
+        assert_eq!(Recurrence::Monthly(31).next_after(last), None);
+        assert_eq!(Recurrence::Daily.next_from(date(2026, 1, 1), last), None);
+    }
+
+    #[test]
+    fn test_file_format_round_trip() {
+        for input in ["daily", "weekly:mon,wed", "monthly:15", "3d"] {
+            let rule = Recurrence::from_file_format(input).unwrap();
+            assert_eq!(rule.to_file_format(), input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let anchor = date(2026, 10, 14);
+        assert!(Recurrence::parse("monthly:0", anchor).is_err());
+        assert!(Recurrence::parse("weekly:funday", anchor).is_err());
+        assert!(Recurrence::parse("0d", anchor).is_err());
+        assert!(Recurrence::parse("99999999d", anchor).is_err());
+        assert!(Recurrence::parse("sometimes", anchor).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+// Text output for the terminal
+//
+// Task listings in text (`list`, `list --archived` and `today`) go through a
+// Renderer, which fits them to wherever they're printed:
+//
+//   markers  emoji such as ✅ ⏳ 🔴 where the terminal can show them, or
+//            ASCII such as [x] [ ] [!] with the priority written out
+//   color    ANSI colors for priorities and overdue dates; closed tasks dim
+//   width    titles, descriptions and other details wrap to the terminal's
+//            width, continuing under the text they started
+//   layout   full, with a task's details on lines of their own, or compact,
+//            one line per task with the title shortened to fit
+//
+// The config's style (config.rs) decides the markers and color. auto, the
+// default, uses emoji when standard output is a terminal with a UTF-8 locale
+// other than the Linux console or a VT-style serial terminal, and color when
+// it's a terminal other than TERM=dumb; emoji, plain and color choose for
+// themselves. NO_COLOR turns color off whatever the style. The width is
+// COLUMNS, or else the terminal's, or 80. The layout is the config's, and
+// `list --compact` picks the compact one. The other output formats
+// (output.rs) don't come through here.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use chrono::{NaiveDateTime, Utc};
+use clap::ValueEnum;
+use ratatui::crossterm::terminal;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::workflow::Status;
+use crate::{format_ids, format_local, tracking, Task, TodoApp};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Style {
+    #[default]
+    Auto,
+    Emoji,
+    Plain,
+    Color,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Layout {
+    #[default]
+    Full,
+    Compact,
+}
+
+// Text gets at least this many columns, however deep it's indented
+const MIN_TEXT_WIDTH: usize = 20;
+const RULE_WIDTH: usize = 80;
+const INDENT: &str = "    ";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Renderer {
+    pub emoji: bool,
+    pub color: bool,
+    pub width: usize,
+    pub layout: Layout,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            emoji: true,
+            color: false,
+            width: RULE_WIDTH,
+            layout: Layout::Full,
+        }
+    }
+}
+
+// Whether the locale, from the first of these that's set, is UTF-8
+fn utf8_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()));
+    locale.is_some_and(|locale| {
+        let locale = locale.to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+// The Linux console and serial terminals have no emoji
+fn limited(term: &str) -> bool {
+    term == "dumb" || term == "linux" || term.starts_with("vt")
+}
+
+// Greedy word wrap to `width` columns; a word longer than that is split
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let gap = usize::from(!line.is_empty());
+        if line.width() + gap + word.width() <= width {
+            if gap == 1 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            if !line.is_empty() && line.width() + c.width().unwrap_or(0) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+impl Renderer {
+    // For standard output, with the config's style and layout
+    pub fn detect(style: Style, layout: Layout) -> Renderer {
+        let tty = io::stdout().is_terminal();
+        let term = env::var("TERM").unwrap_or_default();
+        let (emoji, color) = match style {
+            Style::Auto => (
+                tty && utf8_locale() && !limited(&term),
+                tty && term != "dumb",
+            ),
+            Style::Emoji => (true, false),
+            Style::Plain => (false, false),
+            Style::Color => (false, true),
+        };
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let columns = env::var("COLUMNS").ok().and_then(|c| c.trim().parse().ok());
+        let size = || tty.then(terminal::size).and_then(Result::ok);
+        let width = columns
+            .or_else(|| size().map(|(columns, _)| usize::from(columns)))
+            .filter(|&width| width > 0)
+            .unwrap_or(RULE_WIDTH);
+        Renderer {
+            emoji,
+            color: color && !no_color,
+            width,
+            layout,
+        }
+    }
+
+    // The emoji, or the ASCII marker without emoji
+    pub fn marker<'a>(&self, emoji: &'a str, ascii: &'a str) -> &'a str {
+        if self.emoji {
+            emoji
+        } else {
+            ascii
+        }
+    }
+
+    // The text in an SGR color such as "31" (red), when colored
+    pub fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    // The text after an emoji, as in "📋 Todo List:" or "✅ Added task #1",
+    // or just the text without emoji
+    pub fn prefixed(&self, emoji: &str, text: &str) -> String {
+        if self.emoji {
+            format!("{} {}", emoji, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    // The line under a heading
+    pub fn rule(&self) -> String {
+        "-".repeat(self.width.min(RULE_WIDTH))
+    }
+
+    // At most `width` columns of the text, ending in an ellipsis when cut
+    pub fn truncate(&self, text: &str, width: usize) -> String {
+        if text.width() <= width {
+            return text.to_string();
+        }
+        let ellipsis = self.marker("…", "...");
+        let mut cut = String::new();
+        let mut used = ellipsis.width();
+        for c in text.chars() {
+            used += c.width().unwrap_or(0);
+            if used > width {
+                break;
+            }
+            cut.push(c);
+        }
+        cut + ellipsis
+    }
+
+    // The text cut or padded to exactly `width` columns
+    pub fn fit(&self, text: &str, width: usize) -> String {
+        let text = self.truncate(text, width);
+        let pad = width.saturating_sub(text.width());
+        text + &" ".repeat(pad)
+    }
+
+    // `lead` followed by the text wrapped to the width, later lines indented
+    // to where the text starts. Each of the text's own lines wraps separately.
+    fn hang(&self, lead: &str, text: &str) -> Vec<String> {
+        let pad = " ".repeat(lead.width());
+        let room = self.width.saturating_sub(lead.width()).max(MIN_TEXT_WIDTH);
+        let pieces = text.lines().flat_map(|line| wrap(line, room));
+        let mut lines: Vec<String> = pieces
+            .enumerate()
+            .map(|(i, piece)| format!("{}{}", if i == 0 { lead } else { &pad }, piece))
+            .collect();
+        if lines.is_empty() {
+            lines.push(lead.trim_end().to_string());
+        }
+        lines
+    }
+
+    // Colors the word the last line ends with, such as a priority
+    fn paint_end(&self, lines: &mut [String], word: &str, code: &str) {
+        if let Some(last) = lines.last_mut() {
+            if let Some(rest) = last.strip_suffix(word) {
+                *last = format!("{}{}", rest, self.paint(code, word));
+            }
+        }
+    }
+
+    // Status marker, priority emoji and id, as a task's line starts
+    fn lead(&self, app: &TodoApp, task: &Task, indent: &str) -> String {
+        let status = if !task.status.is_closed() && !app.open_blockers(task).is_empty() {
+            self.marker("⛔", "[!]")
+        } else {
+            self.marker(task.status.emoji(), task.status.marker())
+        };
+        match self.emoji {
+            true => format!(
+                "{}{} {} [{}] ",
+                indent,
+                status,
+                task.priority.emoji(),
+                task.id
+            ),
+            false => format!("{}{} [{}] ", indent, status, task.id),
+        }
+    }
+
+    // The lines showing a task that's `depth` subtasks down
+    pub fn task(
+        &self,
+        app: &TodoApp,
+        task: &Task,
+        depth: usize,
+        now: NaiveDateTime,
+        layout: Layout,
+    ) -> Vec<String> {
+        match layout {
+            Layout::Full => self.full(app, task, depth, now),
+            Layout::Compact => vec![self.compact(app, task, depth, now)],
+        }
+    }
+
+    fn full(&self, app: &TodoApp, task: &Task, depth: usize, now: NaiveDateTime) -> Vec<String> {
+        let indent = INDENT.repeat(depth);
+        let priority = task.priority.to_string();
+        let title = format!("{} - {}", task.title, priority);
+        let mut lines = self.hang(&self.lead(app, task, &indent), &title);
+        if task.status.is_closed() {
+            lines = lines.iter().map(|line| self.paint("2", line)).collect();
+        } else {
+            self.paint_end(&mut lines, priority, task.priority.color());
+        }
+
+        // Each detail goes on lines of its own, behind an emoji when there are any
+        let field = |emoji: &str, text: &str| {
+            let lead = match self.emoji {
+                true => format!("{}{}{} ", indent, INDENT, emoji),
+                false => format!("{}{}", indent, INDENT),
+            };
+            self.hang(&lead, text)
+        };
+
+        if let Some(desc) = &task.description {
+            lines.extend(field("📝", desc));
+        }
+
+        if !matches!(task.status, Status::Todo | Status::Done) {
+            lines.extend(field(
+                "🚦",
+                &format!("Status: {}", app.workflow.label(&task.status)),
+            ));
+        }
+
+        let blockers = app.open_blockers(task);
+        if !task.status.is_closed() && !blockers.is_empty() {
+            lines.extend(field(
+                "⛔",
+                &format!("Blocked by: {}", format_ids(&blockers)),
+            ));
+        }
+
+        let children = app.children(task.id);
+        if !children.is_empty() {
+            let done = children
+                .iter()
+                .filter(|id| app.tasks[id].status.is_closed())
+                .count();
+            let text = format!("Subtasks: {}/{} done", done, children.len());
+            lines.extend(field("📂", &text));
+        }
+
+        if let Some(due) = &task.due {
+            let overdue = task.is_overdue(now);
+            let marker = if overdue {
+                self.marker(" ⚠️ OVERDUE", " OVERDUE")
+            } else {
+                ""
+            };
+            let due = due.describe(now.date(), &app.config.date_format);
+            let mut due = field("📅", &format!("Due: {}{}", due, marker));
+            if overdue {
+                self.paint_end(&mut due, "OVERDUE", "31");
+            }
+            lines.extend(due);
+        }
+
+        if let Some(rule) = &task.recurrence {
+            lines.extend(field("🔁", &format!("Repeats: {}", rule.describe())));
+        }
+
+        if !task.tags.is_empty() {
+            let tags: Vec<String> = task.tags.iter().map(|t| format!("#{}", t)).collect();
+            lines.extend(field("🏷️", &format!("Tags: {}", tags.join(" "))));
+        }
+
+        if let Some(project) = &task.project {
+            lines.extend(field("📁", &format!("Project: +{}", project)));
+        }
+
+        if !task.sessions.is_empty() {
+            let tracked = tracking::total_seconds(&task.sessions, Utc::now());
+            let marker = if task.is_timing() { " (running)" } else { "" };
+            let text = format!("Time: {}{}", tracking::format_duration(tracked), marker);
+            lines.extend(field("⏱️", &text));
+        }
+
+        let time = |dt| format_local(dt, &app.config.time_format());
+        lines.extend(field("🕒", &format!("Created: {}", time(&task.created_at))));
+        if task.updated_at != task.created_at {
+            lines.extend(field("✏️", &format!("Updated: {}", time(&task.updated_at))));
+        }
+        if let Some(completed_at) = &task.completed_at {
+            lines.extend(field("🏁", &format!("Completed: {}", time(completed_at))));
+        }
+        lines.push(String::new());
+        lines
+    }
+
+    fn compact(&self, app: &TodoApp, task: &Task, depth: usize, now: NaiveDateTime) -> String {
+        let closed = task.status.is_closed();
+        let lead = self.lead(app, task, &"  ".repeat(depth));
+
+        // What follows the title, each with the color it's shown in
+        let mut details: Vec<(String, Option<&str>)> = Vec::new();
+        if !self.emoji {
+            let priority = task.priority.to_string().to_lowercase();
+            details.push((format!("({})", priority), Some(task.priority.color())));
+        }
+        if let Some(due) = &task.due {
+            let overdue = task.is_overdue(now);
+            let marker = if overdue {
+                self.marker(" ⚠️", " OVERDUE")
+            } else {
+                ""
+            };
+            let due = due.describe(now.date(), &app.config.date_format);
+            let text = format!("{}{}{}", self.marker("📅 ", "due "), due, marker);
+            details.push((text, overdue.then_some("31")));
+        }
+        let children = app.children(task.id);
+        if !children.is_empty() {
+            let done = children
+                .iter()
+                .filter(|id| app.tasks[id].status.is_closed())
+                .count();
+            let label = self.marker("📂 ", "subtasks ");
+            details.push((format!("{}{}/{}", label, done, children.len()), None));
+        }
+        if task.is_timing() {
+            details.push((self.marker("⏱️", "timing").to_string(), None));
+        }
+        let mut markers: Vec<String> = task.tags.iter().map(|t| format!("#{}", t)).collect();
+        markers.extend(task.project.iter().map(|p| format!("+{}", p)));
+        if !markers.is_empty() {
+            details.push((markers.join(" "), None));
+        }
+
+        let rest: usize = details.iter().map(|(text, _)| 2 + text.width()).sum();
+        let room = self.width.saturating_sub(lead.width() + rest);
+        let mut line = lead + &self.truncate(&task.title, room.max(MIN_TEXT_WIDTH));
+        for (text, color) in details {
+            line.push_str("  ");
+            line.push_str(&match color.filter(|_| !closed) {
+                Some(code) => self.paint(code, &text),
+                None => text,
+            });
+        }
+        if closed {
+            line = self.paint("2", &line);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{temp_app, Priority};
+    use chrono::Local;
+
+    #[test]
+    fn test_wrap_and_truncate() {
+        assert_eq!(
+            wrap("pay the rent on time", 8),
+            ["pay the", "rent on", "time"]
+        );
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 10), [""]);
+
+        let ascii = Renderer {
+            emoji: false,
+            ..Renderer::default()
+        };
+        assert_eq!(ascii.truncate("Pay the rent", 8), "Pay t...");
+        assert_eq!(Renderer::default().truncate("Pay the rent", 8), "Pay the…");
+        assert_eq!(ascii.truncate("Pay", 8), "Pay");
+        assert_eq!(ascii.prefixed("📋", "Todo List:"), "Todo List:");
+        assert_eq!(ascii.paint("31", "red"), "red");
+        let color = Renderer {
+            color: true,
+            ..ascii
+        };
+        assert_eq!(color.paint("31", "red"), "\x1b[31mred\x1b[0m");
+    }
+
+    #[test]
+    fn test_render_layouts() {
+        let mut app = temp_app("render-layouts");
+        let title = "Renew the passport before the summer holidays start #admin";
+        let id = app.add_task(title.to_string(), Priority::High, None, None);
+        app.update_description(id, "Photos from the shop on the corner".to_string());
+        let now = Local::now().naive_local();
+        let renderer = Renderer {
+            emoji: false,
+            color: false,
+            width: 40,
+            layout: Layout::Full,
+        };
+
+        let lines = renderer.task(&app, &app.tasks[&id], 0, now, Layout::Full);
+        assert_eq!(lines[0], "[ ] [1] Renew the passport before the");
+        assert_eq!(lines[1], "        summer holidays start - High");
+        assert_eq!(lines[2], "    Photos from the shop on the corner");
+        assert!(lines
+            .iter()
+            .all(|line| line.width() <= 40 && line.is_ascii()));
+
+        let lines = renderer.task(&app, &app.tasks[&id], 1, now, Layout::Compact);
+        assert_eq!(lines, ["  [ ] [1] Renew the passpor...  (high)  #admin"]);
+        let emoji = Renderer {
+            emoji: true,
+            ..renderer
+        };
+        let lines = emoji.task(&app, &app.tasks[&id], 0, now, Layout::Compact);
+        assert_eq!(lines, ["⏳ 🔴 [1] Renew the passport be…  #admin"]);
+    }
+}
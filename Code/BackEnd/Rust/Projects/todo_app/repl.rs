@@ -0,0 +1,525 @@
+// Interactive `todo>` prompt
+//
+// Each line is parsed into a cli::Command and run through cli::execute, so the
+// prompt and the one-shot subcommands behave the same. The prompt keeps its
+// own shorthand: the priority can be the last word of an `add`, options are
+// written as `due:`, `repeat:` and `parent:`, and `list` takes the rest of the
+// line as its query.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+use crate::cli::{self, Command};
+use crate::edit::Fields;
+use crate::output::Format;
+use crate::reports;
+use crate::workflow::Status;
+use crate::{tags, Priority, TodoApp};
+
+#[derive(Debug)]
+enum Line {
+    Quit,
+    Help,
+    Run(Box<Command>),
+}
+
+pub fn run(app: &mut TodoApp) {
+    println!(
+        "{}",
+        app.renderer.prefixed("📝", "Welcome to Rust Todo App!")
+    );
+    println!("Type 'help' for commands or 'quit' to exit.");
+
+    loop {
+        print!("todo> ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) => break,
+            Ok(_) => {
+                let input = input.trim();
+                if input.is_empty() {
+                    continue;
+                }
+
+                match parse_line(input, &app.config.priorities) {
+                    Ok(Line::Quit) => {
+                        println!("Goodbye!{}", app.renderer.marker(" 👋", ""));
+                        break;
+                    }
+                    Ok(Line::Help) => print_help(app),
+                    Ok(Line::Run(command)) => {
+                        if let Err(e) = cli::execute(app, *command, Format::Text) {
+                            println!("{}", app.renderer.prefixed("❌", &e.to_string()));
+                        }
+                    }
+                    Err(message) => println!("{}", app.renderer.prefixed("❌", &message)),
+                }
+            }
+            Err(error) => {
+                println!("Error reading input: {}", error);
+                break;
+            }
+        }
+    }
+}
+
+fn task_id(s: &str) -> Result<u32, String> {
+    s.parse().map_err(|_| "Invalid task ID".to_string())
+}
+
+fn usage(text: &str) -> Result<Line, String> {
+    Err(format!("Usage: {}", text))
+}
+
+fn words(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|s| s.to_string()).collect()
+}
+
+// The words without a trailing option, and whether it was there
+fn flag<'a>(parts: &[&'a str], names: &[&str]) -> (Vec<&'a str>, bool) {
+    match parts.split_last() {
+        Some((last, rest)) if parts.len() > 1 && names.contains(last) => (rest.to_vec(), true),
+        _ => (parts.to_vec(), false),
+    }
+}
+
+fn parse_line(input: &str, priorities: &[Priority]) -> Result<Line, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let name = parts[0].to_lowercase();
+
+    let command = match name.as_str() {
+        "quit" | "exit" | "q" => return Ok(Line::Quit),
+        "help" | "h" => return Ok(Line::Help),
+        "add" => parse_add(parts, priorities)?,
+        "remove" | "rm" => {
+            if parts.len() != 2 {
+                return usage("remove <id>");
+            }
+            Command::Remove {
+                id: task_id(parts[1])?,
+            }
+        }
+        "toggle" | "t" => {
+            let (parts, force) = flag(&parts, &["--force", "-f"]);
+            let (parts, dry_run) = flag(&parts, &["--dry-run", "-n"]);
+            if parts.len() < 2 {
+                return usage("toggle <id|query> [--force] [--dry-run]");
+            }
+            Command::Toggle {
+                target: words(&parts[1..]),
+                force,
+                dry_run,
+            }
+        }
+        "move" | "mv" => {
+            let (parts, force) = flag(&parts, &["--force", "-f"]);
+            let (parts, dry_run) = flag(&parts, &["--dry-run", "-n"]);
+            if parts.len() < 3 {
+                return usage("move <id|query> <status> [--force] [--dry-run]");
+            }
+            let last = parts.len() - 1;
+            Command::Move {
+                target: words(&parts[1..last]),
+                status: Status::parse(parts[last])?,
+                force,
+                dry_run,
+            }
+        }
+        "edit" => {
+            if parts.len() != 2 {
+                return usage("edit <id>");
+            }
+            Command::Edit {
+                target: vec![task_id(parts[1])?.to_string()],
+                fields: Fields::default(),
+                editor: true,
+                force: false,
+                dry_run: false,
+            }
+        }
+        "board" => Command::Board {
+            query: words(&parts[1..]),
+        },
+        "start" => {
+            if parts.len() != 2 {
+                return usage("start <id>");
+            }
+            Command::Start {
+                id: task_id(parts[1])?,
+            }
+        }
+        "stop" => Command::Stop,
+        "report" => parse_report(&parts[1..])?,
+        "desc" | "description" => {
+            if parts.len() < 3 {
+                return usage("desc <id> <description>");
+            }
+            Command::Desc {
+                id: task_id(parts[1])?,
+                text: words(&parts[2..]),
+            }
+        }
+        "due" => {
+            if parts.len() < 3 {
+                return usage("due <id> <when|none>");
+            }
+            Command::Due {
+                id: task_id(parts[1])?,
+                when: words(&parts[2..]),
+            }
+        }
+        "repeat" => {
+            if parts.len() != 3 {
+                return usage("repeat <id> <rule|none>");
+            }
+            Command::Repeat {
+                id: task_id(parts[1])?,
+                rule: parts[2].to_string(),
+            }
+        }
+        "parent" => {
+            if parts.len() != 3 {
+                return usage("parent <id> <parent_id|none>");
+            }
+            Command::Parent {
+                id: task_id(parts[1])?,
+                parent: parts[2].to_string(),
+            }
+        }
+        "block" | "unblock" => {
+            if parts.len() != 3 {
+                return usage(&format!("{} <id> <blocker_id>", parts[0]));
+            }
+            let id = task_id(parts[1])?;
+            let blocker = task_id(parts[2])?;
+            if name == "block" {
+                Command::Block { id, blocker }
+            } else {
+                Command::Unblock { id, blocker }
+            }
+        }
+        "tag" | "untag" => {
+            if parts.len() < 3 {
+                return usage(&format!("{} <id> <#tag|+project>...", parts[0]));
+            }
+            let id = task_id(parts[1])?;
+            let markers = words(&parts[2..]);
+            if name == "tag" {
+                Command::Tag { id, markers }
+            } else {
+                Command::Untag { id, markers }
+            }
+        }
+        "tags" => Command::Tags,
+        "today" | "agenda" => Command::Today,
+        "list" | "ls" | "archived" => {
+            let (_, compact) = flag(&parts, &["--compact", "-c"]);
+            let mut filter = input[parts[0].len()..].trim();
+            if compact {
+                let option = parts[parts.len() - 1];
+                filter = filter.strip_suffix(option).unwrap_or(filter).trim_end();
+            }
+            Command::List {
+                filter: Some(filter.to_string()).filter(|f| !f.is_empty()),
+                query: Vec::new(),
+                archived: name == "archived",
+                compact,
+            }
+        }
+        "stats" | "statistics" => Command::Stats {
+            archived: matches!(parts.get(1), Some(&"--archived" | &"-a" | &"archived")),
+        },
+        "trash" => Command::Trash,
+        "restore" => {
+            if parts.len() != 2 {
+                return usage("restore <id>");
+            }
+            Command::Restore {
+                id: task_id(parts[1])?,
+            }
+        }
+        "purge" => match parts.get(1) {
+            None => Command::Purge { id: None },
+            Some(id) => Command::Purge {
+                id: Some(task_id(id)?),
+            },
+        },
+        "archive" => match parts.get(1).map(|n| n.parse()) {
+            None => Command::Archive {
+                older_than: 30,
+                dry_run: false,
+            },
+            Some(Ok(older_than)) => Command::Archive {
+                older_than,
+                dry_run: false,
+            },
+            Some(Err(_)) => return usage("archive [days]"),
+        },
+        "tui" => Command::Tui,
+        "undo" => Command::Undo,
+        "redo" => Command::Redo,
+        "log" => match parts.get(1).map(|n| n.parse()) {
+            None => Command::Log { limit: 10 },
+            Some(Ok(limit)) => Command::Log { limit },
+            Some(Err(_)) => return usage("log [count]"),
+        },
+        _ => return Err("Unknown command. Type 'help' for available commands.".to_string()),
+    };
+
+    Ok(Line::Run(Box::new(command)))
+}
+
+// report [kind] [by:day|week] [since]
+fn parse_report(words: &[&str]) -> Result<Command, String> {
+    let mut words = words.to_vec();
+    let kind = match words.first().map(|w| reports::Kind::from_str(w, true)) {
+        Some(Ok(kind)) => {
+            words.remove(0);
+            kind
+        }
+        _ => reports::Kind::default(),
+    };
+    let by = words
+        .iter()
+        .position(|w| w.to_lowercase().starts_with("by:"))
+        .map(|pos| reports::Period::from_str(&words.remove(pos)[3..], true))
+        .transpose()
+        .map_err(|_| "by: takes day or week".to_string())?;
+    let since = words.join(" ");
+
+    Ok(Command::Report {
+        kind,
+        since: Some(since).filter(|s| !s.is_empty()),
+        by,
+    })
+}
+
+fn parse_add(mut parts: Vec<&str>, priorities: &[Priority]) -> Result<Command, String> {
+    let repeat = parts
+        .iter()
+        .position(|w| w.to_lowercase().starts_with("repeat:"))
+        .map(|pos| parts.remove(pos)[7..].to_string());
+    let parent = parts
+        .iter()
+        .position(|w| w.to_lowercase().starts_with("parent:"))
+        .map(|pos| parts.remove(pos)[7..].parse::<u32>())
+        .transpose()
+        .map_err(|_| "Invalid parent task ID".to_string())?;
+
+    // Everything from a `due:` word onwards is the due date
+    let due_at = parts
+        .iter()
+        .position(|w| w.to_lowercase().starts_with("due:"));
+    let (parts, due) = match due_at {
+        Some(pos) => {
+            let when = format!("{} {}", &parts[pos][4..], parts[pos + 1..].join(" "));
+            (&parts[..pos], Some(when.trim().to_string()))
+        }
+        None => (&parts[..], None),
+    };
+
+    if parts.len() < 2 {
+        return Err("Usage: add <title> [priority] [repeat:<rule>] [due:<when>]".to_string());
+    }
+
+    // The priority is the last word that isn't a #tag or +project, as long as
+    // some other title word comes before it and it names an enabled level
+    let mut title = words(&parts[1..]);
+    let plain: Vec<usize> = (0..title.len())
+        .filter(|&i| {
+            tags::parse_tag(&title[i]).is_none() && tags::parse_project(&title[i]).is_none()
+        })
+        .collect();
+    let mut priority = None;
+    if let [_, .., last] = plain[..] {
+        priority = Priority::from_str(&title[last]).filter(|p| priorities.contains(p));
+        if priority.is_some() {
+            title.remove(last);
+        }
+    }
+
+    Ok(Command::Add {
+        title,
+        priority,
+        due,
+        repeat,
+        parent,
+    })
+}
+
+fn print_help(app: &TodoApp) {
+    let config = &app.config;
+    println!("\n{}", app.renderer.prefixed("📚", "Todo App Commands:"));
+    println!("{}", app.renderer.rule());
+    println!("  add <title> [priority] [options] - Add a new task");
+    println!("    Options: parent:<id>, repeat:<rule>, due:<when> (due: must come last)");
+    println!("    Words like #tag and +project in the title become tags and the project");
+    println!("  remove <id>               - Move a task to the trash");
+    println!("  trash                     - List the tasks in the trash");
+    println!("  restore <id>              - Bring a task back from the trash or archive");
+    println!("  purge [id]                - Delete a task in the trash, or all, for good");
+    println!("  archive [days]            - Archive tasks closed over 30 (or days) days ago");
+    println!("  toggle <id|query> [--force] - Toggle task completion");
+    println!("    A task with open subtasks can only be completed with --force");
+    println!("    A query changes every task it matches; --dry-run shows what would change");
+    println!("  move <id|query> <status>  - Move tasks along the workflow:");
+    println!("    todo, in-progress, waiting, done, cancelled or one the workflow adds");
+    println!("  board [query]             - Show tasks in a column per status");
+    println!("  start <id>                - Start timing work on a task");
+    println!("  stop                      - Stop timing the current task");
+    println!("  report [kind] [by:week] [since] - Show a report:");
+    println!("    time (default)  time worked per task, tag and day");
+    println!("    activity        tasks created and completed per day, or week with by:week");
+    println!("    completion      average time from creation to completion per priority");
+    println!("    streak          days in a row with a completion");
+    println!("    burndown        a chart of open tasks at the end of each day");
+    println!("    since is a date such as 2026-10-01 or -30d");
+    println!("  edit <id>                 - Edit every field of a task in $EDITOR");
+    println!("  desc <id> <description>   - Add description to task");
+    println!("  due <id> <when|none>      - Set or clear a task's due date");
+    println!("  repeat <id> <rule|none>   - Make a task recurring, or stop it repeating");
+    println!("  parent <id> <parent|none> - Make a task a subtask of another, or top-level");
+    println!("  block <id> <blocker_id>   - Mark a task as waiting on another");
+    println!("  unblock <id> <blocker_id> - Remove that dependency");
+    println!("  tag <id> <#tag|+project>...   - Add tags or set the project");
+    println!("  untag <id> <#tag|+project>... - Remove tags or clear the project");
+    println!("  list [query] [-c]         - List tasks matching a query, -c one per line");
+    println!("    Terms: all, done, pending, high, medium, low, overdue, today, week,");
+    println!("           blocked, ready, #tag, +project, words, \"phrases\", /regex/");
+    println!("    Fields: status:done, priority>=medium, created<2026-01-01, due:overdue,");
+    println!("            due<=+3d, tag:x, project:x, parent:3, id>10, title~text,");
+    println!("            desc~/regex/i");
+    println!("    Combine with and, or, not (or -term) and parentheses;");
+    println!("    finish with sort:due,-priority and limit:N");
+    println!("    e.g. list #work (high or due:overdue) -done sort:due limit:5");
+    println!("  tags                      - Show tags and projects with task counts");
+    println!("  today                     - Show overdue tasks and tasks due today");
+    println!("  archived [query] [-c]     - Search the archive");
+    println!("  stats [--archived]        - Show statistics, counting archived tasks too");
+    println!("  undo / redo               - Revert or reapply the last change");
+    println!("  tui                       - Browse and edit tasks full-screen");
+    println!("  log [count]               - List recent changes");
+    println!("  help                      - Show this help");
+    println!("  quit                      - Exit application");
+    println!(
+        "\nPriorities: {} (default: {})",
+        config.priority_names(),
+        config.default_priority.to_string().to_lowercase()
+    );
+    println!("Due dates: 2026-05-01, today, tomorrow, +3d, +2w, +1m, fri, next fri,");
+    println!("           optionally followed by a time such as 17:00 or 5pm");
+    println!("Repeat rules: daily, weekdays, weekly, weekly:mon,fri, monthly, monthly:15, 3d");
+    println!("\nEvery command can also be run directly, e.g. `todo add \"Ship it\" -p high`;");
+    println!("see `todo --help`.");
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn parse(input: &str) -> Result<Line, String> {
+        parse_line(input, &Config::default().priorities)
+    }
+
+    fn command(input: &str) -> Command {
+        match parse(input) {
+            Ok(Line::Run(command)) => *command,
+            _ => panic!("'{}' didn't parse to a command", input),
+        }
+    }
+
+    #[test]
+    fn test_add_shorthand() {
+        assert_eq!(
+            command("add Ship release #work high repeat:weekly due:fri 5pm"),
+            Command::Add {
+                title: vec!["Ship".into(), "release".into(), "#work".into()],
+                priority: Some(Priority::High),
+                due: Some("fri 5pm".to_string()),
+                repeat: Some("weekly".to_string()),
+                parent: None,
+            }
+        );
+        // A lone word is the title even if it names a priority
+        assert!(matches!(
+            command("add high"),
+            Command::Add { priority: None, .. }
+        ));
+        // and so is a level the config doesn't enable
+        assert!(matches!(
+            command("add Visit Japan someday"),
+            Command::Add { priority: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_errors_and_usage() {
+        assert!(matches!(parse("quit"), Ok(Line::Quit)));
+        assert_eq!(parse("rm x").err().unwrap(), "Invalid task ID");
+        assert!(parse("toggle").unwrap_err().starts_with("Usage:"));
+        assert!(parse("frobnicate").is_err());
+        assert!(parse("start").unwrap_err().starts_with("Usage:"));
+        assert_eq!(
+            command("move 3 doing -f"),
+            Command::Move {
+                target: vec!["3".to_string()],
+                status: Status::InProgress,
+                force: true,
+                dry_run: false,
+            }
+        );
+        assert_eq!(
+            command("toggle status:pending #sprint12 --dry-run"),
+            Command::Toggle {
+                target: vec!["status:pending".into(), "#sprint12".into()],
+                force: false,
+                dry_run: true,
+            }
+        );
+        assert!(parse("edit x").is_err());
+        assert!(parse("move 3 later!")
+            .unwrap_err()
+            .contains("invalid status"));
+        assert_eq!(
+            command("report -30d"),
+            Command::Report {
+                kind: reports::Kind::Time,
+                since: Some("-30d".to_string()),
+                by: None,
+            }
+        );
+        assert_eq!(
+            command("report activity by:week 2026-09-01"),
+            Command::Report {
+                kind: reports::Kind::Activity,
+                since: Some("2026-09-01".to_string()),
+                by: Some(reports::Period::Week),
+            }
+        );
+        assert_eq!(
+            command("list #work -done"),
+            Command::List {
+                filter: Some("#work -done".to_string()),
+                query: Vec::new(),
+                archived: false,
+                compact: false,
+            }
+        );
+        assert_eq!(
+            command("archived \"big  move\" -c"),
+            Command::List {
+                filter: Some("\"big  move\"".to_string()),
+                query: Vec::new(),
+                archived: true,
+                compact: true,
+            }
+        );
+        assert_eq!(command("purge 4"), Command::Purge { id: Some(4) });
+        assert!(parse("archive soon").unwrap_err().starts_with("Usage:"));
+    }
+}
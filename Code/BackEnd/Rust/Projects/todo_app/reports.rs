@@ -0,0 +1,383 @@
+// Reports
+//
+// `todo report [KIND] [--since DATE]` shows one of these, worked out from the
+// tasks' creation and completion times in local days:
+//
+//   time        time worked per task, tag and day (tracking.rs); the last
+//               7 days unless --since says otherwise
+//   activity    tasks created and completed in each day, or each Monday-to-
+//               Sunday week with --by week; the last 14 days or 8 weeks
+//   completion  average time from creation to completion per priority, for
+//               tasks completed since DATE, or for all of them
+//   streak      how many days in a row, up to today, have had a completion,
+//               and the longest such run. A streak still counts when nothing
+//               has been completed yet today.
+//   burndown    an ASCII chart of how many tasks were open at the end of
+//               each day; the last 14 days
+//
+// Only done tasks count as completed; cancelled ones just stop being open in
+// the burndown. A closed task with no completion time, from a file written
+// before tasks had one, isn't counted in any of them. Archived tasks count
+// like the rest, so archiving doesn't change what a report says.
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Days, NaiveDate};
+use clap::ValueEnum;
+
+use crate::exchange::{local_date, start_of};
+use crate::{tracking, Priority, Task};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    /// Time worked per task, tag and day
+    #[default]
+    Time,
+    /// Tasks created and completed per day or week
+    Activity,
+    /// Average time from creation to completion per priority
+    Completion,
+    /// Days in a row with a completion
+    Streak,
+    /// Open tasks at the end of each day, as a chart
+    Burndown,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Period {
+    #[default]
+    Day,
+    /// Monday to Sunday
+    Week,
+}
+
+impl Period {
+    pub fn name(&self) -> &str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+        }
+    }
+
+    // The first day of the period containing `date`
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+        }
+    }
+
+    fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Days::new(1),
+            Period::Week => start + Days::new(7),
+        }
+    }
+}
+
+impl Kind {
+    // The first day a report covers when --since isn't given
+    pub fn default_since(&self, by: Period, today: NaiveDate) -> Option<NaiveDate> {
+        match (self, by) {
+            (Kind::Time, _) => Some(today - Days::new(6)),
+            (Kind::Activity, Period::Day) | (Kind::Burndown, _) => Some(today - Days::new(13)),
+            (Kind::Activity, Period::Week) => Some(by.start(today) - Days::new(7 * 7)),
+            (Kind::Completion, _) | (Kind::Streak, _) => None,
+        }
+    }
+}
+
+// Tasks whose closing, if they're closed, has a real time
+fn dated<'a>(tasks: &'a [&'a Task]) -> impl Iterator<Item = &'a Task> {
+    tasks
+        .iter()
+        .copied()
+        .filter(|task| !task.status.is_closed() || task.completed_at.is_some())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activity {
+    // The first day of the period
+    pub start: NaiveDate,
+    pub created: usize,
+    pub completed: usize,
+}
+
+// Every period from the one containing `since` to the one containing `today`
+pub fn activity(tasks: &[&Task], since: NaiveDate, today: NaiveDate, by: Period) -> Vec<Activity> {
+    let mut periods = Vec::new();
+    let mut start = by.start(since);
+    while start <= today {
+        periods.push(Activity {
+            start,
+            created: 0,
+            completed: 0,
+        });
+        start = by.next(start);
+    }
+    let index = |day: NaiveDate| {
+        periods
+            .binary_search_by_key(&by.start(day), |period| period.start)
+            .ok()
+    };
+
+    let mut counts = vec![(0, 0); periods.len()];
+    for task in dated(tasks) {
+        if let Some(i) = index(local_date(&task.created_at)) {
+            counts[i].0 += 1;
+        }
+        let completed_at = task.completed_at.filter(|_| task.is_done());
+        if let Some(i) = completed_at.and_then(|time| index(local_date(&time))) {
+            counts[i].1 += 1;
+        }
+    }
+    for (period, (created, completed)) in periods.iter_mut().zip(counts) {
+        period.created = created;
+        period.completed = completed;
+    }
+    periods
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Average {
+    pub count: usize,
+    total: i64,
+}
+
+impl Average {
+    fn add(&mut self, seconds: i64) {
+        self.count += 1;
+        self.total += seconds;
+    }
+
+    pub fn seconds(&self) -> Option<i64> {
+        (self.count > 0).then(|| self.total / self.count as i64)
+    }
+}
+
+pub struct Completion {
+    pub by_priority: Vec<(Priority, Average)>,
+    pub all: Average,
+}
+
+// Creation-to-completion times of tasks completed since a day, if given, for
+// each of the priority levels
+pub fn completion(tasks: &[&Task], since: Option<NaiveDate>, levels: &[Priority]) -> Completion {
+    let from = since.map(start_of);
+    let mut by_priority: Vec<(Priority, Average)> = levels
+        .iter()
+        .map(|priority| (priority.clone(), Average::default()))
+        .collect();
+    let mut all = Average::default();
+
+    for task in tasks.iter().filter(|task| task.is_done()) {
+        let Some(completed_at) = task.completed_at else {
+            continue;
+        };
+        if from.is_some_and(|from| completed_at < from) {
+            continue;
+        }
+        let seconds = (completed_at - task.created_at).num_seconds().max(0);
+        all.add(seconds);
+        if let Some((_, average)) = by_priority.iter_mut().find(|(p, _)| *p == task.priority) {
+            average.add(seconds);
+        }
+    }
+
+    Completion { by_priority, all }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Streak {
+    pub current: usize,
+    pub longest: usize,
+    pub last: Option<NaiveDate>,
+}
+
+pub fn streak(tasks: &[&Task], today: NaiveDate) -> Streak {
+    let days: BTreeSet<NaiveDate> = tasks
+        .iter()
+        .filter_map(|task| task.completed_at.as_ref().filter(|_| task.is_done()))
+        .map(local_date)
+        .filter(|day| *day <= today)
+        .collect();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in &days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let last = days.last().copied();
+    let yesterday = today.pred_opt();
+    let current = if last == Some(today) || last == yesterday {
+        run
+    } else {
+        0
+    };
+    Streak {
+        current,
+        longest,
+        last,
+    }
+}
+
+// How many tasks were open at the end of each day from `since` to `today`
+pub fn burndown(tasks: &[&Task], since: NaiveDate, today: NaiveDate) -> Vec<(NaiveDate, usize)> {
+    since
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| {
+            let end = start_of(day + Days::new(1));
+            let open = dated(tasks)
+                .filter(|task| task.created_at < end)
+                .filter(|task| task.completed_at.is_none_or(|time| time >= end))
+                .count();
+            (day, open)
+        })
+        .collect()
+}
+
+const CHART_HEIGHT: usize = 10;
+
+// A bar per day, scaled to at most CHART_HEIGHT rows, with the first and last
+// dates under the axis:
+//
+//   12 |####
+//      |########
+//      |############  ##
+//    0 +------------------
+//       10-05        10-13
+pub fn chart(points: &[(NaiveDate, usize)]) -> String {
+    let max = points.iter().map(|(_, open)| *open).max().unwrap_or(0);
+    let height = max.clamp(1, CHART_HEIGHT);
+    let margin = max.to_string().len();
+    // Two characters a day unless that would get too wide
+    let bar = if points.len() <= 35 { "##" } else { "#" };
+    let width = points.len() * bar.len();
+
+    let mut out = String::new();
+    for row in (1..=height).rev() {
+        let label = if row == height {
+            max.to_string()
+        } else {
+            String::new()
+        };
+        let mut line = format!("{:>margin$} |", label);
+        for (_, open) in points {
+            let rows = (open * height).div_ceil(max.max(1));
+            line.push_str(if rows >= row { bar } else { &"  "[..bar.len()] });
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!("{:>margin$} +{}\n", 0, "-".repeat(width)));
+
+    if let (Some((first, _)), Some((last, _))) = (points.first(), points.last()) {
+        let (first, last) = (first.format("%m-%d"), last.format("%m-%d"));
+        let gap = " ".repeat(width.saturating_sub(10));
+        let axis = if width >= 12 {
+            format!("{}{}{}", first, gap, last)
+        } else {
+            first.to_string()
+        };
+        out.push_str(&format!("{:>margin$}  {}\n", "", axis));
+    }
+    out
+}
+
+// "3d 4h" for a day or more, otherwise as tracked time is shown
+pub fn format_span(seconds: i64) -> String {
+    let hours = seconds / 3600;
+    if hours >= 24 {
+        format!("{}d {}h", hours / 24, hours % 24)
+    } else {
+        tracking::format_duration(seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::Status;
+    use chrono::{Duration, Local};
+
+    // A task created `created` days before today, at noon, and completed
+    // `done` days before today if given
+    fn task(id: u32, priority: Priority, created: u64, done: Option<u64>) -> Task {
+        let today = Local::now().date_naive();
+        let noon = |days_ago| start_of(today - Days::new(days_ago)) + Duration::hours(12);
+        let mut task = Task::new(id, format!("Task {}", id), priority);
+        task.created_at = noon(created);
+        if let Some(days_ago) = done {
+            task.status = Status::Done;
+            task.completed_at = Some(noon(days_ago));
+        }
+        task
+    }
+
+    fn sample() -> Vec<Task> {
+        vec![
+            task(1, Priority::High, 5, Some(4)),
+            task(2, Priority::High, 5, Some(2)),
+            task(3, Priority::Low, 3, Some(1)),
+            task(4, Priority::Medium, 2, Some(1)),
+            task(5, Priority::Medium, 1, None),
+            task(6, Priority::Low, 0, Some(0)),
+        ]
+    }
+
+    #[test]
+    fn test_activity_and_burndown() {
+        let sample = sample();
+        let tasks: Vec<&Task> = sample.iter().collect();
+        let today = Local::now().date_naive();
+        let since = today - Days::new(5);
+
+        let days = activity(&tasks, since, today, Period::Day);
+        let counts: Vec<(usize, usize)> = days.iter().map(|d| (d.created, d.completed)).collect();
+        assert_eq!(counts, vec![(2, 0), (0, 1), (1, 0), (1, 1), (1, 2), (1, 1)]);
+        let weeks = activity(&tasks, since, today, Period::Week);
+        assert_eq!(weeks[0].start.weekday(), chrono::Weekday::Mon);
+        assert_eq!(weeks.iter().map(|w| w.created).sum::<usize>(), 6);
+
+        let open: Vec<usize> = burndown(&tasks, since, today)
+            .into_iter()
+            .map(|(_, open)| open)
+            .collect();
+        assert_eq!(open, vec![2, 1, 2, 2, 1, 1]);
+
+        let chart = chart(&burndown(&tasks, since, today));
+        assert!(chart.starts_with("2 |##  ####\n  |############\n"));
+        assert!(chart.contains("0 +------------\n"));
+    }
+
+    #[test]
+    fn test_completion_and_streak() {
+        let sample = sample();
+        let tasks: Vec<&Task> = sample.iter().collect();
+        let today = Local::now().date_naive();
+
+        let levels = [Priority::High, Priority::Medium, Priority::Low];
+        let times = completion(&tasks, None, &levels);
+        assert_eq!(times.all.count, 5);
+        let high = &times.by_priority[0].1;
+        assert_eq!(high.seconds(), Some(2 * 86400));
+        assert_eq!(times.by_priority[1].1.seconds(), Some(86400));
+        assert_eq!(format_span(2 * 86400 + 3600), "2d 1h");
+        let recent = completion(&tasks, Some(today - Days::new(1)), &levels);
+        assert_eq!(recent.all.count, 3);
+
+        let streak = streak(&tasks, today);
+        assert_eq!((streak.current, streak.longest), (3, 3));
+        assert_eq!(streak.last, Some(today));
+        let later = super::streak(&tasks, today + Days::new(2));
+        assert_eq!(later.current, 0);
+    }
+}
@@ -0,0 +1,752 @@
+// Local HTTP API
+//
+// `todo serve [--port 8080]` lets other programs on this machine, such as a
+// web dashboard, work with the todo file at http://127.0.0.1:PORT. It only
+// listens on the loopback interface. Bodies are JSON, and tasks have the
+// fields listed in output.rs.
+//
+//   GET    /tasks              tasks matching a `list` query (see query.rs):
+//                              ?q=QUERY, plus any other KEY=VALUE parameter
+//                              as the term KEY:VALUE, e.g.
+//                              /tasks?tag=work&status=pending&sort=due
+//   POST   /tasks              add a task; 201 with its Location
+//   GET    /tasks/{id}         one task
+//   PATCH  /tasks/{id}         change the fields given
+//   DELETE /tasks/{id}         move a task to the trash; 204
+//   POST   /tasks/{id}/toggle  toggle done/pending, with ?force=true to
+//                              complete a task with open subtasks. Returns
+//                              {"task", "next"}, next being the new occurrence
+//                              of a completed recurring task or null.
+//   GET    /stats              the `stats` object; ?archived=true counts archived
+//                              tasks too
+//
+// POST and PATCH take an object with any of title (required to add),
+// description, priority, due, repeat, parent, tags, project, blocked_by and
+// status, written as the commands accept them ("fri 5pm", "weekly:mon"); null
+// clears a field. #tag and +project words in a title work as they do for
+// `add`, a priority has to be one the config enables, and a status is only
+// reached along the workflow, as with `move`.
+//
+// Responses about a task carry its ETag. GET answers 304 Not Modified when
+// If-None-Match has the current ETag, and PATCH, DELETE and toggle refuse
+// with 412 Precondition Failed when If-Match doesn't, so a client can't
+// overwrite a change it hasn't seen. Like any todo command, each request
+// works on the tasks as currently saved and saves its change along with the
+// undo history, so the CLI and the prompt can be used alongside the server.
+//
+// Errors are {"error": message}, with status 400 for invalid input, 404 for
+// an unknown task or path, 405 for an unsupported method, 409 for a change
+// the tasks don't allow (open subtasks, cycles, a move the workflow doesn't
+// have) or that clashes with another
+// todo process, 412 as above, 413 for a body over 1 MiB, 415 for a body that
+// isn't application/json, and 500 when the todo file can't be used.
+
+use std::io::Read;
+
+use chrono::{Local, Utc};
+use serde_json::{json, Value};
+use tiny_http::{Header, Server};
+
+use crate::cli::CliError;
+use crate::due::Due;
+use crate::output::{self, Format};
+use crate::recurrence::Recurrence;
+use crate::workflow::Status;
+use crate::{parse_priority, tags, Priority, SaveError, Task, TaskError, TodoApp};
+
+const HOST: &str = "127.0.0.1";
+const MAX_BODY: usize = 1 << 20;
+
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Response {
+        Response {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: format!("{}\n", value),
+        }
+    }
+
+    fn empty(status: u16) -> Response {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, &json!({ "error": message }))
+    }
+
+    fn header(mut self, name: &'static str, value: String) -> Response {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn invalid(message: impl Into<String>) -> ApiError {
+        ApiError::new(400, message)
+    }
+}
+
+impl From<TaskError> for ApiError {
+    fn from(error: TaskError) -> Self {
+        let status = match error {
+            TaskError::NotFound(_) => 404,
+            TaskError::OpenSubtasks(..)
+            | TaskError::Cycle(_)
+            | TaskError::Closed(..)
+            | TaskError::AlreadyTiming(_)
+            | TaskError::Transition(_)
+            | TaskError::NoNextOccurrence(_) => 409,
+        };
+        ApiError::new(status, error.to_string())
+    }
+}
+
+impl From<SaveError> for ApiError {
+    fn from(error: SaveError) -> Self {
+        let status = match error {
+            SaveError::Io(_) => 500,
+            SaveError::Conflict(_) => 409,
+        };
+        ApiError::new(status, error.to_string())
+    }
+}
+
+pub fn serve(app: &mut TodoApp, port: u16) -> Result<(), CliError> {
+    let server = Server::http((HOST, port))
+        .map_err(|e| CliError::Io(format!("Can't listen on {}:{}: {}", HOST, port, e)))?;
+    let banner = format!(
+        "Serving {} at http://{}:{} (Ctrl+C to stop)",
+        app.file_path(),
+        HOST,
+        port
+    );
+    println!("{}", app.renderer.prefixed("🌐", &banner));
+    for request in server.incoming_requests() {
+        respond(app, request);
+    }
+    Ok(())
+}
+
+// Answers one request from the server and logs it to stderr
+pub fn respond(app: &mut TodoApp, mut request: tiny_http::Request) {
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str().to_string())
+    };
+    let (content_type, if_match) = (header("Content-Type"), header("If-Match"));
+    let if_none_match = header("If-None-Match");
+    let (method, url) = (request.method().to_string(), request.url().to_string());
+
+    let mut body = String::new();
+    let read = request
+        .as_reader()
+        .take(MAX_BODY as u64 + 1)
+        .read_to_string(&mut body);
+    let response = match read {
+        Err(_) => Response::error(400, "The request body isn't UTF-8 text"),
+        Ok(length) if length > MAX_BODY => Response::error(413, "The request body is over 1 MiB"),
+        Ok(_) => handle(
+            app,
+            &Request {
+                method: method.clone(),
+                url: url.clone(),
+                content_type,
+                if_match,
+                if_none_match,
+                body,
+            },
+        ),
+    };
+    eprintln!("{} {} {}", method, url, response.status);
+
+    let mut reply = tiny_http::Response::from_data(response.body).with_status_code(response.status);
+    for (name, value) in response.headers {
+        if let Ok(header) = Header::from_bytes(name, value) {
+            reply.add_header(header);
+        }
+    }
+    if let Err(e) = request.respond(reply) {
+        let warning = format!("Couldn't answer {} {}: {}", method, url, e);
+        eprintln!("{}", app.renderer.prefixed("⚠️", &warning));
+    }
+}
+
+pub fn handle(app: &mut TodoApp, request: &Request) -> Response {
+    route(app, request).unwrap_or_else(|e| Response::error(e.status, &e.message))
+}
+
+fn route(app: &mut TodoApp, request: &Request) -> Result<Response, ApiError> {
+    // Another process may have saved since the last request
+    app.load_from_file().map_err(|e| ApiError::new(500, e))?;
+
+    let (path, query) = request.url.split_once('?').unwrap_or((&request.url, ""));
+    let params = parse_params(query)?;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let id = |segment: &str| {
+        segment
+            .parse::<u32>()
+            .map_err(|_| ApiError::new(404, format!("No such path: {}", path)))
+    };
+
+    let method = request.method.as_str();
+    match (segments.as_slice(), method) {
+        (["tasks"], "GET") => list_tasks(app, request, &params),
+        (["tasks"], "POST") => create_task(app, request),
+        (["tasks"], _) => Ok(not_allowed("GET, POST")),
+        (["tasks", segment], _) => {
+            let id = id(segment)?;
+            match method {
+                "GET" => get_task(app, request, id),
+                "PATCH" => update_task(app, request, id),
+                "DELETE" => delete_task(app, request, id),
+                _ => Ok(not_allowed("GET, PATCH, DELETE")),
+            }
+        }
+        (["tasks", segment, "toggle"], "POST") => {
+            let force = params.iter().any(|(k, v)| k == "force" && v == "true");
+            toggle_task(app, request, id(segment)?, force)
+        }
+        (["tasks", _, "toggle"], _) => Ok(not_allowed("POST")),
+        (["stats"], "GET") => {
+            let now = Local::now().naive_local();
+            let archived = params.iter().any(|(k, v)| k == "archived" && v == "true");
+            let body = output::stats(app, archived, Format::Json, now);
+            Ok(cached(request, Response::json(200, &parse_json(&body)?)))
+        }
+        (["stats"], _) => Ok(not_allowed("GET")),
+        _ => Err(ApiError::new(404, format!("No such path: {}", path))),
+    }
+}
+
+fn not_allowed(allow: &str) -> Response {
+    Response::error(405, "Method not allowed").header("Allow", allow.to_string())
+}
+
+fn list_tasks(
+    app: &TodoApp,
+    request: &Request,
+    params: &[(String, String)],
+) -> Result<Response, ApiError> {
+    let terms: Vec<String> = params
+        .iter()
+        .map(|(key, value)| match key.as_str() {
+            "q" => value.clone(),
+            _ => format!("{}:{}", key, value),
+        })
+        .collect();
+    let filter = terms.join(" ");
+    let filter = Some(filter.as_str()).filter(|f| !f.trim().is_empty());
+
+    let tasks = app
+        .find_tasks(filter)
+        .map_err(|e| ApiError::invalid(format!("Invalid query: {}", e)))?;
+    let now = Local::now().naive_local();
+    let values = tasks.iter().map(|task| output::task_json(task, now));
+    Ok(cached(
+        request,
+        Response::json(200, &Value::Array(values.collect())),
+    ))
+}
+
+fn get_task(app: &TodoApp, request: &Request, id: u32) -> Result<Response, ApiError> {
+    let task = app.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+    let response = task_response(200, task);
+    if lists_tag(request.if_none_match.as_deref(), &etag(task)) {
+        return Ok(Response::empty(304).header("ETag", etag(task)));
+    }
+    Ok(response)
+}
+
+fn create_task(app: &mut TodoApp, request: &Request) -> Result<Response, ApiError> {
+    let mut fields = parse_fields(request)?;
+    let title = fields
+        .title
+        .take()
+        .filter(|title| !tags::extract(title).title.trim().is_empty())
+        .ok_or_else(|| ApiError::invalid("A new task needs a title"))?;
+    let priority = fields.priority.take();
+    let priority = priority.unwrap_or_else(|| app.config.default_priority.clone());
+    app.config
+        .check_priority(&priority)
+        .map_err(ApiError::invalid)?;
+
+    let id = app.change::<_, ApiError>(|app| {
+        let id = app.add_task(title, priority, None, None);
+        apply_fields(app, id, fields)?;
+        Ok((id, format!("add #{}: {}", id, app.tasks[&id].title)))
+    })?;
+    Ok(task_response(201, &app.tasks[&id]).header("Location", format!("/tasks/{}", id)))
+}
+
+fn update_task(app: &mut TodoApp, request: &Request, id: u32) -> Result<Response, ApiError> {
+    check_if_match(app, request, id)?;
+    let fields = parse_fields(request)?;
+    app.change::<_, ApiError>(|app| {
+        apply_fields(app, id, fields)?;
+        Ok(((), format!("edit #{}: {}", id, app.tasks[&id].title)))
+    })?;
+    Ok(task_response(200, &app.tasks[&id]))
+}
+
+fn delete_task(app: &mut TodoApp, request: &Request, id: u32) -> Result<Response, ApiError> {
+    check_if_match(app, request, id)?;
+    app.change::<_, ApiError>(|app| {
+        let label = format!("remove #{}: {}", id, app.tasks[&id].title);
+        app.remove_task(id);
+        Ok(((), label))
+    })?;
+    Ok(Response::empty(204))
+}
+
+fn toggle_task(
+    app: &mut TodoApp,
+    request: &Request,
+    id: u32,
+    force: bool,
+) -> Result<Response, ApiError> {
+    check_if_match(app, request, id)?;
+    let next = app.change::<_, ApiError>(|app| {
+        let next = app.toggle_task(id, force)?;
+        let task = &app.tasks[&id];
+        let verb = if task.is_done() { "complete" } else { "reopen" };
+        Ok((next, format!("{} #{}: {}", verb, id, task.title)))
+    })?;
+
+    let now = Local::now().naive_local();
+    let task = &app.tasks[&id];
+    let value = json!({
+        "task": output::task_json(task, now),
+        "next": next.map(|next| output::task_json(&app.tasks[&next], now)),
+    });
+    Ok(Response::json(200, &value).header("ETag", etag(task)))
+}
+
+// An ETag that changes whenever anything about the task does
+fn etag(task: &Task) -> String {
+    format!("\"{:016x}\"", fnv1a(task.to_file_format().as_bytes()))
+}
+
+// FNV-1a, which unlike std's hasher gives the same ETags from one run to the
+// next
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Whether an If-Match or If-None-Match header lists `tag` (or is "*")
+fn lists_tag(header: Option<&str>, tag: &str) -> bool {
+    header.is_some_and(|header| {
+        header
+            .split(',')
+            .any(|candidate| candidate.trim() == "*" || candidate.trim() == tag)
+    })
+}
+
+fn check_if_match(app: &TodoApp, request: &Request, id: u32) -> Result<(), ApiError> {
+    let task = app.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+    if request.if_match.is_some() && !lists_tag(request.if_match.as_deref(), &etag(task)) {
+        return Err(ApiError::new(
+            412,
+            format!(
+                "Task #{} has changed since it was read; fetch it again and retry",
+                id
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn task_response(status: u16, task: &Task) -> Response {
+    let value = output::task_json(task, Local::now().naive_local());
+    Response::json(status, &value).header("ETag", etag(task))
+}
+
+// Adds an ETag made from the body, answering 304 if the client has it already
+fn cached(request: &Request, response: Response) -> Response {
+    let tag = format!("\"{:016x}\"", fnv1a(response.body.as_bytes()));
+    if lists_tag(request.if_none_match.as_deref(), &tag) {
+        return Response::empty(304).header("ETag", tag);
+    }
+    response.header("ETag", tag)
+}
+
+fn parse_params(query: &str) -> Result<Vec<(String, String)>, ApiError> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+// Decodes %XX escapes and '+' as a space, as in form-encoded query strings
+fn percent_decode(text: &str) -> Result<String, ApiError> {
+    let invalid = || ApiError::invalid(format!("Invalid URL encoding in '{}'", text));
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' => {
+                let hex = tail.get(..2).ok_or_else(invalid)?;
+                let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                rest = &tail[2..];
+            }
+            b'+' => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+fn parse_json(text: &str) -> Result<Value, ApiError> {
+    serde_json::from_str(text).map_err(|e| ApiError::invalid(format!("Invalid JSON: {}", e)))
+}
+
+// Fields of a POST or PATCH body. The outer Option is whether the field was
+// given, the inner one whether it was null.
+#[derive(Default)]
+struct Fields {
+    title: Option<String>,
+    description: Option<Option<String>>,
+    priority: Option<Priority>,
+    due: Option<Option<Due>>,
+    repeat: Option<Option<String>>,
+    parent: Option<Option<u32>>,
+    tags: Option<Vec<String>>,
+    project: Option<Option<String>>,
+    blocked_by: Option<Vec<u32>>,
+    status: Option<Status>,
+}
+
+fn parse_fields(request: &Request) -> Result<Fields, ApiError> {
+    let json_body = request
+        .content_type
+        .as_deref()
+        .is_some_and(|t| t.split(';').next().unwrap_or("").trim() == "application/json");
+    if !json_body {
+        return Err(ApiError::new(415, "Send the body as application/json"));
+    }
+    let Value::Object(object) = parse_json(&request.body)? else {
+        return Err(ApiError::invalid("The body must be a JSON object"));
+    };
+
+    let today = Local::now().date_naive();
+    let mut fields = Fields::default();
+    for (key, value) in &object {
+        let text = || string(key, value);
+        match key.as_str() {
+            "title" => fields.title = Some(text()?.ok_or_else(|| not_null(key))?),
+            "description" => fields.description = Some(text()?.filter(|t| !t.is_empty())),
+            "priority" => {
+                let priority = text()?.ok_or_else(|| not_null(key))?;
+                fields.priority = Some(parse_priority(&priority).map_err(ApiError::invalid)?);
+            }
+            "due" => {
+                let due = text()?.map(|when| Due::parse(&when, today));
+                fields.due = Some(due.transpose().map_err(ApiError::invalid)?);
+            }
+            "repeat" => fields.repeat = Some(text()?),
+            "parent" => fields.parent = Some(id_or_null(key, value)?),
+            "tags" => {
+                let mut tags = Vec::new();
+                for name in list(key, value)? {
+                    let name = string(key, name)?.unwrap_or_default();
+                    let tag = tags::parse_tag(&format!("#{}", name.trim_start_matches('#')))
+                        .ok_or_else(|| ApiError::invalid(format!("'{}' is not a tag", name)))?;
+                    tags::add_tag(&mut tags, tag);
+                }
+                fields.tags = Some(tags);
+            }
+            "project" => {
+                let project = text()?.map(|name| {
+                    tags::parse_project(&format!("+{}", name.trim_start_matches('+')))
+                        .ok_or_else(|| ApiError::invalid(format!("'{}' is not a project", name)))
+                });
+                fields.project = Some(project.transpose()?);
+            }
+            "blocked_by" => {
+                let ids = list(key, value)?.iter().map(|id| id_or_null(key, id));
+                let ids: Option<Vec<u32>> = ids.collect::<Result<_, _>>()?;
+                fields.blocked_by = Some(ids.ok_or_else(|| not_null(key))?);
+            }
+            "status" => {
+                let status = text()?.ok_or_else(|| not_null(key))?;
+                fields.status = Some(Status::parse(&status).map_err(ApiError::invalid)?);
+            }
+            _ => {
+                return Err(ApiError::invalid(format!(
+                    "Unknown or read-only field '{}'",
+                    key
+                )))
+            }
+        }
+    }
+    Ok(fields)
+}
+
+fn not_null(key: &str) -> ApiError {
+    ApiError::invalid(format!("'{}' can't be null", key))
+}
+
+fn string(key: &str, value: &Value) -> Result<Option<String>, ApiError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(text) => Ok(Some(text.clone())),
+        _ => Err(ApiError::invalid(format!("'{}' must be a string", key))),
+    }
+}
+
+fn id_or_null(key: &str, value: &Value) -> Result<Option<u32>, ApiError> {
+    match value {
+        Value::Null => Ok(None),
+        _ => match value.as_u64().and_then(|id| u32::try_from(id).ok()) {
+            Some(id) => Ok(Some(id)),
+            None => Err(ApiError::invalid(format!("'{}' must be a task id", key))),
+        },
+    }
+}
+
+fn list<'a>(key: &str, value: &'a Value) -> Result<&'a Vec<Value>, ApiError> {
+    value
+        .as_array()
+        .ok_or_else(|| ApiError::invalid(format!("'{}' must be an array", key)))
+}
+
+fn apply_fields(app: &mut TodoApp, id: u32, fields: Fields) -> Result<(), ApiError> {
+    let today = Local::now().date_naive();
+    let edit = |app: &mut TodoApp, f: &dyn Fn(&mut Task)| {
+        let task = app.tasks.get_mut(&id).unwrap();
+        f(task);
+        task.updated_at = Utc::now();
+        app.dirty = true;
+    };
+
+    if let Some(title) = fields.title {
+        if tags::extract(&title).title.trim().is_empty() {
+            return Err(ApiError::invalid("The title can't be empty"));
+        }
+        app.rename_task(id, &title);
+    }
+    if let Some(description) = fields.description {
+        edit(app, &|task| task.description = description.clone());
+    }
+    if let Some(priority) = fields.priority {
+        app.config
+            .check_priority(&priority)
+            .map_err(ApiError::invalid)?;
+        app.set_priority(id, priority);
+    }
+    if let Some(due) = fields.due {
+        app.set_due(id, due);
+    }
+    if let Some(rule) = fields.repeat {
+        let anchor = app.tasks[&id].due.map_or(today, |due| due.date);
+        let recurrence = rule
+            .map(|rule| Recurrence::parse(&rule, anchor))
+            .transpose()
+            .map_err(ApiError::invalid)?;
+        app.set_recurrence(id, recurrence);
+    }
+    if let Some(parent) = fields.parent {
+        app.set_parent(id, parent)?;
+    }
+    if let Some(tags) = fields.tags {
+        edit(app, &|task| task.tags = tags.clone());
+    }
+    if let Some(project) = fields.project {
+        edit(app, &|task| task.project = project.clone());
+    }
+    if let Some(blocked_by) = fields.blocked_by {
+        for blocker in app.tasks[&id].blocked_by.clone() {
+            if !blocked_by.contains(&blocker) {
+                app.remove_blocker(id, blocker)?;
+            }
+        }
+        for blocker in blocked_by {
+            app.add_blocker(id, blocker)?;
+        }
+    }
+    if let Some(status) = fields.status {
+        app.move_task(id, status, false)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_app;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    fn request(method: &str, url: &str, body: Option<Value>) -> Request {
+        Request {
+            method: method.to_string(),
+            url: url.to_string(),
+            content_type: body.as_ref().map(|_| "application/json".to_string()),
+            if_match: None,
+            if_none_match: None,
+            body: body.map_or(String::new(), |body| body.to_string()),
+        }
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        let mut headers = response.headers.iter();
+        headers.find(|(n, _)| *n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_tasks_crud_with_etags() {
+        let mut app = temp_app("server-crud");
+        let new_task = json!({ "title": "Write docs #work", "priority": "high", "due": "+2d" });
+        let created = handle(&mut app, &request("POST", "/tasks", Some(new_task)));
+        assert_eq!(created.status, 201);
+        assert_eq!(header(&created, "Location"), Some("/tasks/1"));
+        assert_eq!(body(&created)["tags"], json!(["work"]));
+        let tag = header(&created, "ETag").unwrap().to_string();
+
+        let mut get = request("GET", "/tasks/1", None);
+        get.if_none_match = Some(tag.clone());
+        assert_eq!(handle(&mut app, &get).status, 304);
+
+        let mut stale = request("PATCH", "/tasks/1", Some(json!({ "due": null })));
+        stale.if_match = Some("\"0\"".to_string());
+        assert_eq!(handle(&mut app, &stale).status, 412);
+        stale.if_match = Some(tag.clone());
+        let patched = handle(&mut app, &stale);
+        assert_eq!(patched.status, 200);
+        assert_eq!(body(&patched)["due"], Value::Null);
+        assert_ne!(header(&patched, "ETag"), Some(tag.as_str()));
+
+        let listed = handle(&mut app, &request("GET", "/tasks?tag=work&q=pending", None));
+        assert_eq!(body(&listed).as_array().unwrap().len(), 1);
+        let bad_query = handle(
+            &mut app,
+            &request("GET", "/tasks?q=priority%3E%3Dhuge", None),
+        );
+        assert_eq!(bad_query.status, 400);
+
+        let toggled = handle(&mut app, &request("POST", "/tasks/1/toggle", None));
+        assert_eq!(body(&toggled)["task"]["completed"], true);
+        assert_eq!(app.history.recent(1)[0].0.label, "complete #1: Write docs");
+
+        assert_eq!(
+            handle(&mut app, &request("DELETE", "/tasks/1", None)).status,
+            204
+        );
+        assert_eq!(
+            handle(&mut app, &request("GET", "/tasks/1", None)).status,
+            404
+        );
+        assert_eq!(
+            handle(&mut app, &request("GET", "/nowhere", None)).status,
+            404
+        );
+    }
+
+    #[test]
+    fn test_invalid_requests_change_nothing() {
+        let mut app = temp_app("server-invalid");
+        let add = |title: &str| request("POST", "/tasks", Some(json!({ "title": title })));
+        handle(&mut app, &add("A"));
+        handle(&mut app, &add("B"));
+
+        // The cycle is only found after the title has changed
+        let body = json!({ "title": "A renamed", "blocked_by": [2] });
+        handle(
+            &mut app,
+            &request("PATCH", "/tasks/2", Some(json!({ "blocked_by": [1] }))),
+        );
+        let cycle = handle(&mut app, &request("PATCH", "/tasks/1", Some(body)));
+        assert_eq!(cycle.status, 409);
+        assert_eq!(app.tasks[&1].title, "A");
+
+        let status = |name: &str| request("PATCH", "/tasks/1", Some(json!({ "status": name })));
+        assert_eq!(handle(&mut app, &status("cancelled")).status, 200);
+        assert_eq!(handle(&mut app, &status("waiting")).status, 409);
+        assert_eq!(app.tasks[&1].status, Status::Cancelled);
+
+        let mut plain = add("C");
+        plain.content_type = Some("text/plain".to_string());
+        assert_eq!(handle(&mut app, &plain).status, 415);
+        let unknown = request("PATCH", "/tasks/1", Some(json!({ "id": 5 })));
+        assert_eq!(handle(&mut app, &unknown).status, 400);
+        let put = handle(&mut app, &request("PUT", "/tasks/1", None));
+        assert_eq!(
+            (put.status, header(&put, "Allow")),
+            (405, Some("GET, PATCH, DELETE"))
+        );
+        assert_eq!(app.tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_serves_over_loopback() {
+        let mut app = temp_app("server-loopback");
+        let server = Server::http((HOST, 0)).unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            let request = "GET /stats HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        respond(&mut app, server.recv().unwrap());
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("ETag: \""));
+        assert!(response.contains("\"total\":0"));
+    }
+}
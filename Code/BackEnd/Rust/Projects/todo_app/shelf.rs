@@ -0,0 +1,107 @@
+// The trash and the archive
+//
+// Removing a task moves it to the trash rather than dropping it, and `todo
+// archive` moves closed tasks that were finished a while ago out of the todo
+// list into the archive. `todo restore` brings a task back from either, and
+// `todo purge` deletes tasks in the trash for good.
+//
+// Each is a store of its own next to the todo file and of the same kind:
+// todos.txt keeps them in todos.trash.txt and todos.archive.txt, todos.db in
+// todos.trash.db and todos.archive.db. Nothing but purge writes to them
+// directly. Whenever TodoApp saves, a task deleted from the todo file goes to
+// the trash, or to the archive when it was archived, and a task that's back
+// in the todo file, restored or brought back by undo, leaves both. So undo
+// and the server and terminal UI all work with the trash as they are.
+//
+// A task in the trash keeps its last update time as the time it was removed.
+// Ids of tasks in either are never given to new tasks, so a task always comes
+// back under its own id.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::journal::Entry;
+use crate::store::{self, Loaded, Notice, TaskStore};
+use crate::Task;
+
+pub const TRASH: &str = "trash";
+pub const ARCHIVE: &str = "archive";
+
+pub struct Shelf {
+    store: Box<dyn TaskStore>,
+    pub tasks: HashMap<u32, Task>,
+}
+
+// The store for a shelf of the todo file at `path`, e.g. todos.txt ->
+// todos.trash.txt
+pub fn path(path: &str, name: &str) -> String {
+    let file = Path::new(path);
+    let stem = file
+        .file_stem()
+        .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+    let name = match file.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, name, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, name),
+    };
+    file.with_file_name(name).to_string_lossy().into_owned()
+}
+
+impl Shelf {
+    pub fn new(file: &str, name: &str) -> Self {
+        Shelf {
+            store: store::open(&path(file, name)),
+            tasks: HashMap::new(),
+        }
+    }
+
+    // Reads the tasks as currently saved, returning what the store had to
+    // fix. Called with the todo file's lock held, which covers its shelves too.
+    pub fn reload(&mut self) -> Result<Vec<Notice>, String> {
+        let loaded = match self.store.exists() {
+            true => self.store.read()?,
+            false => Loaded::new(HashMap::new()),
+        };
+        self.tasks = loaded.tasks;
+        Ok(loaded.notices)
+    }
+
+    // Saves the changes on top of what was last read. Called with the lock held.
+    pub fn write(&mut self, changes: Vec<Entry>) -> Result<(), String> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        for change in &changes {
+            match change {
+                Entry::Put(task) => self.tasks.insert(task.id, (**task).clone()),
+                Entry::Delete(id) => self.tasks.remove(id),
+            };
+        }
+        self.store.write(&changes, &self.tasks)
+    }
+
+    // The tasks sorted by id
+    pub fn sorted(&self) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self.tasks.values().collect();
+        tasks.sort_by_key(|t| t.id);
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shelf_paths() {
+        assert_eq!(path("todos.txt", TRASH), "todos.trash.txt");
+        assert_eq!(
+            path("/home/me/todos.db", ARCHIVE),
+            "/home/me/todos.archive.db"
+        );
+        assert_eq!(path("todos", TRASH), "todos.trash");
+        assert_eq!(
+            store::Kind::of(&path("work.sqlite", TRASH)),
+            store::Kind::Sqlite
+        );
+    }
+}
@@ -0,0 +1,298 @@
+// SQLite storage
+//
+// Tasks can be kept in an embedded SQLite database instead of the flat todo
+// file: one row per task in the `tasks` table, with each field in its own
+// column. Text columns use the same encodings as the todo file (timestamps
+// in RFC 3339, due dates as "YYYY-MM-DD[ HH:MM]", comma-separated tags and
+// blocked_by ids, work sessions as in tracking.rs), so the two can be
+// converted without losing anything.
+//
+// The schema is created and upgraded by MIGRATIONS, run in order inside a
+// transaction. SQLite's user_version records how many have been applied, and
+// a database from a newer todo is refused rather than guessed at.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::types::FromSql;
+use rusqlite::{params, Connection, Row};
+
+use crate::due::Due;
+use crate::journal::Entry;
+use crate::recurrence::Recurrence;
+use crate::store::{Loaded, TaskStore};
+use crate::tracking;
+use crate::workflow::Status;
+use crate::{format_timestamp, parse_id, parse_priority, parse_tags, parse_timestamp, Task};
+
+const MIGRATIONS: [&str; 4] = [
+    "
+    CREATE TABLE tasks (
+        id           INTEGER PRIMARY KEY,
+        title        TEXT NOT NULL,
+        description  TEXT,
+        completed    INTEGER NOT NULL,
+        priority     TEXT NOT NULL,
+        created_at   TEXT NOT NULL,
+        updated_at   TEXT NOT NULL,
+        completed_at TEXT,
+        due          TEXT,
+        repeat       TEXT,
+        tags         TEXT NOT NULL,
+        project      TEXT,
+        parent       INTEGER,
+        blocked_by   TEXT NOT NULL
+    );
+    CREATE INDEX tasks_completed ON tasks (completed);
+    CREATE INDEX tasks_priority ON tasks (priority);
+    CREATE INDEX tasks_due ON tasks (due);
+    ",
+    "ALTER TABLE tasks ADD COLUMN uid TEXT;",
+    "ALTER TABLE tasks ADD COLUMN time TEXT NOT NULL DEFAULT '';",
+    "
+    ALTER TABLE tasks ADD COLUMN status TEXT NOT NULL DEFAULT 'todo';
+    UPDATE tasks SET status = 'done' WHERE completed;
+    DROP INDEX tasks_completed;
+    ALTER TABLE tasks DROP COLUMN completed;
+    CREATE INDEX tasks_status ON tasks (status);
+    ",
+];
+
+pub struct SqliteStore {
+    path: String,
+    connection: Option<Connection>,
+}
+
+impl SqliteStore {
+    pub fn new(path: String) -> Self {
+        SqliteStore {
+            path,
+            connection: None,
+        }
+    }
+
+    // Opens (or creates) the database and brings its schema up to date
+    fn connect(&mut self) -> Result<&mut Connection, String> {
+        if self.connection.is_none() {
+            let mut connection = Connection::open(&self.path)
+                .map_err(|e| format!("Error opening {}: {}", self.path, e))?;
+            migrate(&mut connection)
+                .map_err(|e| format!("Error upgrading {}: {}", self.path, e))?;
+            self.connection = Some(connection);
+        }
+        Ok(self.connection.as_mut().unwrap())
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), String> {
+    let version: usize = connection
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "database uses schema v{}, but this todo understands up to v{}",
+            version,
+            MIGRATIONS.len()
+        ));
+    }
+
+    let transaction = connection.transaction().map_err(|e| e.to_string())?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        transaction
+            .execute_batch(migration)
+            .and_then(|_| transaction.pragma_update(None, "user_version", index + 1))
+            .map_err(|e| e.to_string())?;
+    }
+    transaction.commit().map_err(|e| e.to_string())
+}
+
+fn insert(connection: &Connection, task: &Task) -> rusqlite::Result<usize> {
+    let blocked_by: Vec<String> = task.blocked_by.iter().map(|id| id.to_string()).collect();
+    connection.execute(
+        "INSERT OR REPLACE INTO tasks (id, title, description, status, priority, created_at,
+             updated_at, completed_at, due, repeat, tags, project, parent, blocked_by, uid,
+             time)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            task.id,
+            task.title,
+            task.description,
+            task.status.name(),
+            task.priority.to_string(),
+            format_timestamp(&task.created_at),
+            format_timestamp(&task.updated_at),
+            task.completed_at.as_ref().map(format_timestamp),
+            task.due.map(|due| due.to_file_format()),
+            task.recurrence.as_ref().map(|rule| rule.to_file_format()),
+            task.tags.join(","),
+            task.project,
+            task.parent,
+            blocked_by.join(","),
+            task.uid,
+            tracking::encode(&task.sessions),
+        ],
+    )
+}
+
+fn column<T: FromSql>(row: &Row, index: usize) -> Result<T, String> {
+    row.get(index).map_err(|e| e.to_string())
+}
+
+// Columns in the order selected by read
+fn task_from_row(row: &Row) -> Result<Task, String> {
+    let text = |index| column::<Option<String>>(row, index);
+
+    Ok(Task {
+        id: column(row, 0)?,
+        title: column(row, 1)?,
+        description: text(2)?,
+        status: Status::parse(&column::<String>(row, 3)?)?,
+        priority: parse_priority(&column::<String>(row, 4)?)?,
+        created_at: parse_timestamp(&column::<String>(row, 5)?)?,
+        updated_at: parse_timestamp(&column::<String>(row, 6)?)?,
+        completed_at: text(7)?.as_deref().map(parse_timestamp).transpose()?,
+        due: text(8)?.as_deref().map(Due::from_file_format).transpose()?,
+        recurrence: text(9)?
+            .as_deref()
+            .map(Recurrence::from_file_format)
+            .transpose()?,
+        tags: match column::<String>(row, 10)?.as_str() {
+            "" => Vec::new(),
+            tags => parse_tags(tags)?,
+        },
+        project: text(11)?,
+        parent: column(row, 12)?,
+        blocked_by: match column::<String>(row, 13)?.as_str() {
+            "" => Vec::new(),
+            ids => ids.split(',').map(parse_id).collect::<Result<_, _>>()?,
+        },
+        uid: text(14)?,
+        sessions: match column::<String>(row, 15)?.as_str() {
+            "" => Vec::new(),
+            sessions => tracking::decode(sessions)?,
+        },
+    })
+}
+
+impl TaskStore for SqliteStore {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn exists(&self) -> bool {
+        Path::new(&self.path).exists()
+    }
+
+    fn read(&mut self) -> Result<Loaded, String> {
+        if !self.exists() {
+            return Ok(Loaded::new(HashMap::new()));
+        }
+        let path = self.path.clone();
+        let loading = |e: String| format!("Error loading {}: {}", path, e);
+        let connection = self.connect()?;
+        let mut statement = connection
+            .prepare(
+                "SELECT id, title, description, status, priority, created_at, updated_at,
+                     completed_at, due, repeat, tags, project, parent, blocked_by, uid, time
+                 FROM tasks",
+            )
+            .map_err(|e| loading(e.to_string()))?;
+        let mut rows = statement.query([]).map_err(|e| loading(e.to_string()))?;
+
+        let mut tasks = HashMap::new();
+        while let Some(row) = rows.next().map_err(|e| loading(e.to_string()))? {
+            let task = task_from_row(row).map_err(loading)?;
+            tasks.insert(task.id, task);
+        }
+        Ok(Loaded::new(tasks))
+    }
+
+    fn write(&mut self, changes: &[Entry], _tasks: &HashMap<u32, Task>) -> Result<(), String> {
+        let path = self.path.clone();
+        let error = |e: rusqlite::Error| format!("Error saving to {}: {}", path, e);
+        let connection = self.connect()?;
+
+        let transaction = connection.transaction().map_err(error)?;
+        for change in changes {
+            match change {
+                Entry::Put(task) => insert(&transaction, task),
+                Entry::Delete(id) => transaction.execute("DELETE FROM tasks WHERE id = ?1", [id]),
+            }
+            .map_err(error)?;
+        }
+        transaction.commit().map_err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use chrono::Local;
+
+    #[test]
+    fn test_tasks_round_trip() {
+        let file_name = format!("todo-sqlite-{}.db", std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let path = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+
+        let today = Local::now().date_naive();
+        let mut task = Task::new(7, "Pay | rent".to_string(), Priority::High);
+        task.set_description("line one\nline two".to_string());
+        task.set_recurrence(Some(Recurrence::parse("monthly", today).unwrap()), today);
+        task.add_tags(&["home".to_string()], Some("bills".to_string()));
+        task.blocked_by = vec![2, 3];
+        task.uid = Some("abc-123@example.com".to_string());
+        task.sessions =
+            tracking::decode("2026-10-18T09:00:00Z/2026-10-18T10:00:00Z,2026-10-18T11:00:00Z/")
+                .unwrap();
+        task.status = Status::Waiting;
+        let plain = Task::new(8, "Plain".to_string(), Priority::Low);
+
+        let mut store = SqliteStore::new(path.clone());
+        let changes = [
+            Entry::Put(Box::new(task.clone())),
+            Entry::Put(Box::new(plain)),
+        ];
+        store.write(&changes, &HashMap::new()).unwrap();
+        store.write(&[Entry::Delete(8)], &HashMap::new()).unwrap();
+
+        let tasks = SqliteStore::new(path.clone()).read().unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[&7].to_file_format(), task.to_file_format());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_migration_turns_done_flag_into_status() {
+        let file_name = format!("todo-sqlite-v3-{}.db", std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let path = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+
+        // A database as the previous schema left it
+        let connection = Connection::open(&path).unwrap();
+        for migration in &MIGRATIONS[..3] {
+            connection.execute_batch(migration).unwrap();
+        }
+        connection.pragma_update(None, "user_version", 3).unwrap();
+        for (id, completed) in [(1, true), (2, false)] {
+            connection
+                .execute(
+                    "INSERT INTO tasks (id, title, completed, priority, created_at, updated_at,
+                         tags, blocked_by)
+                     VALUES (?1, 'Task', ?2, 'Low', '2026-01-01T00:00:00Z',
+                         '2026-01-01T00:00:00Z', '', '')",
+                    params![id, completed],
+                )
+                .unwrap();
+        }
+        drop(connection);
+
+        let tasks = SqliteStore::new(path.clone()).read().unwrap().tasks;
+        assert_eq!(tasks[&1].status, Status::Done);
+        assert_eq!(tasks[&2].status, Status::Todo);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+// Where tasks are kept
+//
+// TodoApp reads and writes tasks through a TaskStore, chosen by the todo
+// file's name:
+//
+//   FileStore    the flat todo file (record.rs) with its journal (journal.rs)
+//   SqliteStore  an embedded SQLite database (sqlite.rs), for names ending in
+//                .db, .sqlite or .sqlite3
+//
+// Whatever the store, TodoApp holds `<file>.lock` around every read and write
+// (sync.rs), keeps the undo history in `<file>.history`, and keeps removed and
+// archived tasks in stores of their own (shelf.rs). `todo migrate --to
+// sqlite` copies the tasks from one store to the other.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::journal::{self, Entry};
+use crate::record::{self, ParsedFile, FORMAT_VERSION};
+use crate::sqlite::SqliteStore;
+use crate::{records, sync, Task};
+
+pub trait TaskStore {
+    // The todo file or database
+    fn path(&self) -> &str;
+
+    // Whether anything has been saved yet
+    fn exists(&self) -> bool;
+
+    // Every saved task, by id. Called with the lock held.
+    fn read(&mut self) -> Result<Loaded, String>;
+
+    // Saves `changes`, which bring what was last read or written up to
+    // `tasks`. Called with the lock held.
+    fn write(&mut self, changes: &[Entry], tasks: &HashMap<u32, Task>) -> Result<(), String>;
+}
+
+// What a store read, and anything it had to fix on the way for TodoApp to
+// report. Stores don't print.
+pub struct Loaded {
+    pub tasks: HashMap<u32, Task>,
+    pub notices: Vec<Notice>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+    // Something skipped or dropped, and where the original was kept
+    Warning(String),
+    // The file rewritten in the current format
+    Upgraded(String),
+}
+
+impl Loaded {
+    pub fn new(tasks: HashMap<u32, Task>) -> Loaded {
+        Loaded {
+            tasks,
+            notices: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    /// The flat todo file
+    File,
+    /// An SQLite database
+    Sqlite,
+}
+
+const SQLITE_EXTENSIONS: [&str; 3] = ["db", "sqlite", "sqlite3"];
+
+impl Kind {
+    pub fn of(path: &str) -> Kind {
+        let extension = Path::new(path).extension().and_then(|e| e.to_str());
+        match extension {
+            Some(e) if SQLITE_EXTENSIONS.contains(&e.to_lowercase().as_str()) => Kind::Sqlite,
+            _ => Kind::File,
+        }
+    }
+
+    // `path` renamed for this kind of store, e.g. todos.txt -> todos.db
+    pub fn rename(&self, path: &str) -> String {
+        let extension = match self {
+            Kind::File => "txt",
+            Kind::Sqlite => "db",
+        };
+        Path::new(path)
+            .with_extension(extension)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+pub fn open(path: &str) -> Box<dyn TaskStore> {
+    match Kind::of(path) {
+        Kind::File => Box::new(FileStore::new(path.to_string())),
+        Kind::Sqlite => Box::new(SqliteStore::new(path.to_string())),
+    }
+}
+
+// Saves `tasks` to a new store at `path` and checks they read back unchanged
+pub fn copy(tasks: &HashMap<u32, Task>, path: &str) -> Result<(), String> {
+    let _lock = sync::lock(path)?;
+    let mut ids: Vec<&u32> = tasks.keys().collect();
+    ids.sort();
+    let changes: Vec<Entry> = ids
+        .into_iter()
+        .map(|id| Entry::Put(Box::new(tasks[id].clone())))
+        .collect();
+    open(path).write(&changes, tasks)?;
+
+    if records(&open(path).read()?.tasks) != records(tasks) {
+        return Err(format!("The tasks in {} don't match the originals", path));
+    }
+    Ok(())
+}
+
+pub struct FileStore {
+    path: String,
+    // Entries appended since the file was last rewritten
+    journal_entries: usize,
+}
+
+impl FileStore {
+    pub fn new(path: String) -> Self {
+        FileStore {
+            path,
+            journal_entries: 0,
+        }
+    }
+
+    // Rewrites the todo file with every task and empties the journal
+    fn compact(&mut self, tasks: &HashMap<u32, Task>) -> Result<(), String> {
+        let mut tasks: Vec<&Task> = tasks.values().collect();
+        tasks.sort_by_key(|t| t.id);
+
+        let mut content = record::header();
+        content.push('\n');
+        for task in tasks {
+            content.push_str(&task.to_file_format());
+            content.push('\n');
+        }
+
+        journal::write_atomic(&self.path, &content)
+            .map_err(|e| format!("Error saving to {}: {}", self.path, e))?;
+        let journal_path = journal::path(&self.path);
+        journal::remove(&journal_path)
+            .map_err(|e| format!("Error removing {}: {}", journal_path, e))?;
+        self.journal_entries = 0;
+        Ok(())
+    }
+}
+
+impl TaskStore for FileStore {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn exists(&self) -> bool {
+        Path::new(&self.path).exists() || Path::new(&journal::path(&self.path)).exists()
+    }
+
+    // Reads the todo file and replays its journal, backing up and reporting
+    // anything that had to be skipped
+    fn read(&mut self) -> Result<Loaded, String> {
+        let journal_path = journal::path(&self.path);
+        let parsed = if Path::new(&self.path).exists() {
+            let content = fs::read_to_string(&self.path)
+                .map_err(|e| format!("Error loading from file: {}", e))?;
+            record::parse_file(&content)
+                .map_err(|e| format!("Error loading {}: {}", self.path, e))?
+        } else {
+            ParsedFile {
+                version: FORMAT_VERSION,
+                tasks: Vec::new(),
+                errors: Vec::new(),
+            }
+        };
+
+        let mut tasks: HashMap<u32, Task> = parsed
+            .tasks
+            .into_iter()
+            .map(|task| (task.id, task))
+            .collect();
+        let mut notices = Vec::new();
+        let mut warn = |text: String| notices.push(Notice::Warning(text));
+
+        let upgrade = parsed.version < FORMAT_VERSION;
+        if upgrade || !parsed.errors.is_empty() {
+            // Keep the original around before anything gets rewritten
+            let backup = format!("{}.v{}.bak", self.path, parsed.version);
+            fs::copy(&self.path, &backup)
+                .map_err(|e| format!("Error backing up {}: {}", self.path, e))?;
+
+            for error in &parsed.errors {
+                warn(format!("Skipped {} {}", self.path, error));
+            }
+            if !parsed.errors.is_empty() {
+                warn(format!("The original file was saved to {}", backup));
+            }
+        }
+
+        // Changes saved since the file was last rewritten
+        let replay = journal::read(&journal_path)
+            .map_err(|e| format!("Error loading {}: {}", journal_path, e))?;
+        self.journal_entries = replay.entries.len();
+        for entry in replay.entries {
+            match entry {
+                Entry::Put(task) => tasks.insert(task.id, *task),
+                Entry::Delete(id) => tasks.remove(&id),
+            };
+        }
+        if !replay.errors.is_empty() {
+            let backup = format!("{}.bak", journal_path);
+            fs::copy(&journal_path, &backup)
+                .map_err(|e| format!("Error backing up {}: {}", journal_path, e))?;
+            for error in &replay.errors {
+                warn(format!("Skipped {} {}", journal_path, error));
+            }
+            warn(format!("The original journal was saved to {}", backup));
+        }
+        if replay.torn {
+            warn(format!(
+                "Dropped an incomplete change at the end of {} left by an interrupted save",
+                journal_path
+            ));
+        }
+
+        // Anything that was skipped or upgraded gets written out cleanly
+        if upgrade || !parsed.errors.is_empty() || !replay.errors.is_empty() || replay.torn {
+            self.compact(&tasks)?;
+        }
+        if upgrade {
+            notices.push(Notice::Upgraded(format!(
+                "Upgraded {} from format v{} to v{} (backup: {}.v{}.bak)",
+                self.path, parsed.version, FORMAT_VERSION, self.path, parsed.version
+            )));
+        }
+
+        Ok(Loaded { tasks, notices })
+    }
+
+    // Appends the changes to the journal, or rewrites the whole file when the
+    // journal is due for compaction (see journal.rs)
+    fn write(&mut self, changes: &[Entry], tasks: &HashMap<u32, Task>) -> Result<(), String> {
+        if !Path::new(&self.path).exists()
+            || self.journal_entries + changes.len() >= journal::COMPACT_AFTER
+        {
+            self.compact(tasks)
+        } else if !changes.is_empty() {
+            let journal_path = journal::path(&self.path);
+            journal::append(&journal_path, changes)
+                .map_err(|e| format!("Error saving to {}: {}", journal_path, e))?;
+            self.journal_entries += changes.len();
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_extension() {
+        assert_eq!(Kind::of("todos.txt"), Kind::File);
+        assert_eq!(Kind::of("todos"), Kind::File);
+        assert_eq!(Kind::of("/home/me/todos.DB"), Kind::Sqlite);
+        assert_eq!(Kind::of("todos.sqlite3"), Kind::Sqlite);
+        assert_eq!(Kind::Sqlite.rename("lists/todos.txt"), "lists/todos.db");
+        assert_eq!(Kind::File.rename("todos.db"), "todos.txt");
+    }
+
+    #[test]
+    fn test_legacy_file_notices() {
+        let path = std::env::temp_dir().join(format!("todo-legacy-{}.txt", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let content = "1|Buy milk||false|High|2024-01-01 12:00:00\n2|Broken|line\n";
+        fs::write(&path, content).unwrap();
+
+        let loaded = open(&path).read().unwrap();
+        assert_eq!(loaded.tasks.len(), 1);
+        assert!(matches!(&loaded.notices[0], Notice::Warning(text) if text.contains("line 2")));
+        assert!(matches!(loaded.notices.last(), Some(Notice::Upgraded(_))));
+        // Upgraded, so there's nothing more to say the next time
+        assert!(open(&path).read().unwrap().notices.is_empty());
+        for file in [
+            path.clone(),
+            format!("{}.v1.bak", path),
+            journal::path(&path),
+        ] {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Sharing the todo file between processes
+//
+// Each todo process keeps its own copy of the tasks, so two terminals working
+// on the same file have to coordinate:
+//
+// - A process holds `<file>.lock` while it reads or writes the todo file, its
+//   journal or its history, so it never sees another process's save half
+//   done. The lock is advisory (only todo honours it), and acquiring it gives
+//   up after LOCK_TIMEOUT rather than waiting on a stuck process forever.
+// - Before saving, a process rereads the file. If another process saved in
+//   the meantime, the two sets of changes are merged task by task against the
+//   tasks as they were last loaded. Tasks changed on only one side take that
+//   side's version, and a task both sides added under the same id is given a
+//   new id here. A task changed on both sides is a conflict, which can't be
+//   merged automatically.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Task;
+
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY: Duration = Duration::from_millis(20);
+
+// Released when dropped
+pub struct FileLock {
+    _file: File,
+}
+
+pub fn lock(file_path: &str) -> Result<FileLock, String> {
+    let path = format!("{}.lock", file_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Error opening {}: {}", path, e))?;
+
+    let start = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(FileLock { _file: file }),
+            Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => {
+                thread::sleep(LOCK_RETRY)
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(format!(
+                    "{} is locked by another todo process; try again once it has finished",
+                    file_path
+                ))
+            }
+            Err(TryLockError::Error(e)) => return Err(format!("Error locking {}: {}", path, e)),
+        }
+    }
+}
+
+pub struct Merged {
+    pub tasks: HashMap<u32, Task>,
+    // (old, new) ids of tasks added here that had to be renumbered
+    pub renumbered: Vec<(u32, u32)>,
+}
+
+// Combines our tasks with theirs, given the task records both started from.
+// Returns the ids changed on both sides if the two can't be combined.
+pub fn merge(
+    base: &HashMap<u32, String>,
+    ours: &HashMap<u32, Task>,
+    theirs: &HashMap<u32, Task>,
+) -> Result<Merged, Vec<u32>> {
+    let record = |task: Option<&Task>| task.map(|t| t.to_file_format());
+    let ids: BTreeSet<u32> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .copied()
+        .collect();
+
+    let mut tasks = theirs.clone();
+    let mut changed = Vec::new();
+    let mut added_on_both = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for id in ids {
+        let before = base.get(&id).cloned();
+        let mine = record(ours.get(&id));
+        let other = record(theirs.get(&id));
+
+        if mine == before || mine == other {
+            continue;
+        }
+        if other == before {
+            match ours.get(&id) {
+                Some(task) => tasks.insert(id, task.clone()),
+                None => tasks.remove(&id),
+            };
+            changed.push(id);
+        } else if before.is_none() {
+            added_on_both.push(id);
+        } else {
+            conflicts.push(id);
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+
+    let next_id = tasks.keys().chain(ours.keys()).max().map_or(1, |id| id + 1);
+    let renumbered: Vec<(u32, u32)> = added_on_both.into_iter().zip(next_id..).collect();
+    for (old, new) in &renumbered {
+        let mut task = ours[old].clone();
+        task.id = *new;
+        tasks.insert(*new, task);
+        changed.push(*new);
+    }
+
+    // Links from our own changes to a renumbered task follow it
+    for id in changed {
+        if let Some(task) = tasks.get_mut(&id) {
+            for (old, new) in &renumbered {
+                if task.parent == Some(*old) {
+                    task.parent = Some(*new);
+                }
+                for blocker in task.blocked_by.iter_mut().filter(|b| *b == old) {
+                    *blocker = *new;
+                }
+            }
+        }
+    }
+
+    Ok(Merged { tasks, renumbered })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{records, Priority};
+
+    fn tasks(titles: &[(u32, &str)]) -> HashMap<u32, Task> {
+        titles
+            .iter()
+            .map(|(id, title)| (*id, Task::new(*id, title.to_string(), Priority::Medium)))
+            .collect()
+    }
+
+    #[test]
+    fn test_changes_on_different_tasks_merge() {
+        let base = tasks(&[(1, "A"), (2, "B")]);
+        let mut ours = base.clone();
+        ours.get_mut(&1).unwrap().title = "A edited".to_string();
+        let mut theirs = base.clone();
+        theirs.remove(&2);
+
+        let merged = merge(&records(&base), &ours, &theirs).unwrap();
+        assert_eq!(merged.tasks.len(), 1);
+        assert_eq!(merged.tasks[&1].title, "A edited");
+    }
+
+    #[test]
+    fn test_same_task_changed_on_both_sides_conflicts() {
+        let base = tasks(&[(1, "A")]);
+        let mut ours = base.clone();
+        ours.get_mut(&1).unwrap().title = "Ours".to_string();
+        let mut theirs = base.clone();
+        theirs.get_mut(&1).unwrap().title = "Theirs".to_string();
+
+        assert_eq!(merge(&records(&base), &ours, &theirs).err(), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_tasks_added_on_both_sides_are_renumbered() {
+        let base = tasks(&[(1, "A")]);
+        let mut ours = base.clone();
+        ours.extend(tasks(&[(2, "Ours")]));
+        let mut child = Task::new(3, "Child".to_string(), Priority::Low);
+        child.parent = Some(2);
+        ours.insert(3, child);
+        let mut theirs = base.clone();
+        theirs.extend(tasks(&[(2, "Theirs")]));
+
+        let merged = merge(&records(&base), &ours, &theirs).unwrap();
+        assert_eq!(merged.tasks[&2].title, "Theirs");
+        assert_eq!(merged.renumbered, vec![(2, 4)]);
+        assert_eq!(merged.tasks[&4].title, "Ours");
+        assert_eq!(merged.tasks[&3].parent, Some(4));
+    }
+}
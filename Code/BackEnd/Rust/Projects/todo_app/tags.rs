@@ -0,0 +1,101 @@
+// `#tag` and `+project` markers
+//
+// Tags and projects are written inline in a task title ("Fix login #bug
+// +website") and stored lowercase. Markers made only of digits, like "#12"
+// or "+1", are left in the title since they're usually issue numbers or
+// counts rather than tags.
+
+fn parse_marker(word: &str, prefix: char) -> Option<String> {
+    let name = word.strip_prefix(prefix)?;
+    let valid = !name.is_empty()
+        && !name.chars().all(|c| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/' | '.'));
+
+    if valid {
+        Some(name.to_lowercase())
+    } else {
+        None
+    }
+}
+
+pub fn parse_tag(word: &str) -> Option<String> {
+    parse_marker(word, '#')
+}
+
+pub fn parse_project(word: &str) -> Option<String> {
+    parse_marker(word, '+')
+}
+
+pub struct Extracted {
+    pub title: String,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+}
+
+// Pulls tags and the project out of a title. If several projects are given
+// the last one wins. A title made only of markers is kept as written.
+pub fn extract(title: &str) -> Extracted {
+    let mut words = Vec::new();
+    let mut tags = Vec::new();
+    let mut project = None;
+
+    for word in title.split_whitespace() {
+        if let Some(tag) = parse_tag(word) {
+            add_tag(&mut tags, tag);
+        } else if let Some(name) = parse_project(word) {
+            project = Some(name);
+        } else {
+            words.push(word);
+        }
+    }
+
+    let title = if words.is_empty() {
+        title.trim().to_string()
+    } else {
+        words.join(" ")
+    };
+
+    Extracted {
+        title,
+        tags,
+        project,
+    }
+}
+
+// Inserts a tag keeping the list sorted and free of duplicates
+pub fn add_tag(tags: &mut Vec<String>, tag: String) {
+    if let Err(pos) = tags.binary_search(&tag) {
+        tags.insert(pos, tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let extracted = extract("Fix login #Work #bug +Website #work");
+        assert_eq!(extracted.title, "Fix login");
+        assert_eq!(extracted.tags, vec!["bug", "work"]);
+        assert_eq!(extracted.project.as_deref(), Some("website"));
+    }
+
+    #[test]
+    fn test_numbers_stay_in_title() {
+        let extracted = extract("Close issue #12 and add +1 test");
+        assert_eq!(extracted.title, "Close issue #12 and add +1 test");
+        assert!(extracted.tags.is_empty());
+        assert!(extracted.project.is_none());
+    }
+
+    #[test]
+    fn test_invalid_markers() {
+        assert_eq!(parse_tag("#"), None);
+        assert_eq!(parse_tag("#a,b"), None);
+        assert_eq!(parse_tag("work"), None);
+        assert_eq!(parse_project("+web-site"), Some("web-site".to_string()));
+    }
+}
@@ -0,0 +1,415 @@
+// todo.txt files
+//
+// Reads and writes the todo.txt convention used by many editors and phone
+// apps, one task per line:
+//
+//   x 2026-10-18 2026-10-01 Call the bank +bills @phone pri:A due:2026-10-20 id:3
+//
+//   x DATE        done or cancelled, on that date
+//   (A) (B) (C)   High, Medium and Low priority. Completed tasks carry theirs
+//                 as pri:A instead, as usual. Other letters import as Low, and
+//                 a line without a priority imports as Medium.
+//   DATE          creation date; the completion date or today if missing
+//   +project      the project; any further projects stay in the title
+//   @context      a tag
+//
+// and these key:value extensions for everything else a task holds:
+//
+//   status:STATUS      any status but todo and done (workflow.rs); a
+//                      line without one is todo, or done if it starts with x
+//   level:LEVEL        urgent or someday, written as (A) and (C); ignored
+//                      when the letter no longer matches
+//   due:DATE[THH:MM]   due date and time
+//   rec:RULE           repeat rule as accepted by `repeat`, e.g. rec:weekly:mon
+//   parent:ID          parent task
+//   blocked:ID[,ID]    tasks this one waits on
+//   desc:TEXT          description, with '%', spaces and line breaks written
+//                      as %25, %20, %0A and so on
+//   uid:TEXT           identifier from another app, encoded like desc
+//   id:ID              task id, so importing an exported file updates the same
+//                      tasks (see exchange.rs)
+//
+// Words that don't fit any of these, including other apps' key:value pairs,
+// stay in the title as written. A title word that would read as one of them,
+// say "@alice" or "id:7", is written with its first character or its colon
+// percent-encoded ("%40alice", "id%3A7"), as is one starting with '%', and
+// decoded again on import. Dates are local calendar days, so times of
+// creation and completion aren't kept, and tags are lowercase.
+
+use chrono::{NaiveDate, Utc};
+
+use crate::due::Due;
+use crate::exchange::{local_date, start_of, Imported};
+use crate::record::ParseError;
+use crate::recurrence::Recurrence;
+use crate::workflow::Status;
+use crate::{parse_id, tags, Priority, Task};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+// The key:value extensions read into a task
+const KEYS: [&str; 10] = [
+    "pri", "status", "level", "due", "rec", "parent", "blocked", "desc", "uid", "id",
+];
+
+fn letter(priority: &Priority) -> char {
+    match priority {
+        Priority::Urgent | Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low | Priority::Someday => 'C',
+    }
+}
+
+fn from_letter(word: &str) -> Option<Priority> {
+    match word {
+        "A" => Some(Priority::High),
+        "B" => Some(Priority::Medium),
+        _ if word.len() == 1 && word.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+// "(A)" and so on
+fn priority_word(word: &str) -> Option<Priority> {
+    from_letter(word.strip_prefix('(')?.strip_suffix(')')?)
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+pub fn export(tasks: &[&Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| format!("{}\n", to_line(task)))
+        .collect()
+}
+
+fn to_line(task: &Task) -> String {
+    let date = |time| local_date(time).format(DATE_FORMAT).to_string();
+    let mut words = Vec::new();
+
+    if task.status.is_closed() {
+        words.push("x".to_string());
+        words.push(date(task.completed_at.as_ref().unwrap_or(&task.updated_at)));
+    } else {
+        words.push(format!("({})", letter(&task.priority)));
+    }
+    words.push(date(&task.created_at));
+    words.extend(task.title.split_whitespace().map(escape));
+
+    if let Some(project) = &task.project {
+        words.push(format!("+{}", project));
+    }
+    words.extend(task.tags.iter().map(|tag| format!("@{}", tag)));
+    if task.status.is_closed() {
+        words.push(format!("pri:{}", letter(&task.priority)));
+    }
+    if !matches!(task.status, Status::Todo | Status::Done) {
+        words.push(format!("status:{}", task.status.name()));
+    }
+    if matches!(task.priority, Priority::Urgent | Priority::Someday) {
+        words.push(format!(
+            "level:{}",
+            task.priority.to_string().to_lowercase()
+        ));
+    }
+    if let Some(due) = task.due {
+        words.push(format!("due:{}", due.to_file_format().replace(' ', "T")));
+    }
+    if let Some(rule) = &task.recurrence {
+        words.push(format!("rec:{}", rule.to_file_format()));
+    }
+    if let Some(parent) = task.parent {
+        words.push(format!("parent:{}", parent));
+    }
+    if !task.blocked_by.is_empty() {
+        let ids: Vec<String> = task.blocked_by.iter().map(|id| id.to_string()).collect();
+        words.push(format!("blocked:{}", ids.join(",")));
+    }
+    if let Some(description) = &task.description {
+        words.push(format!("desc:{}", encode(description)));
+    }
+    if let Some(uid) = &task.uid {
+        words.push(format!("uid:{}", encode(uid)));
+    }
+    words.push(format!("id:{}", task.id));
+    words.join(" ")
+}
+
+pub fn parse(content: &str) -> Result<Vec<Imported>, ParseError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            from_line(line).map_err(|message| ParseError {
+                line: index + 1,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn from_line(line: &str) -> Result<Imported, String> {
+    let mut words: &[&str] = &line.split_whitespace().collect::<Vec<&str>>();
+    let mut next_if = |parse: &dyn Fn(&str) -> bool| match words.first() {
+        Some(word) if parse(word) => {
+            let word = *word;
+            words = &words[1..];
+            Some(word)
+        }
+        _ => None,
+    };
+
+    let completed = next_if(&|word| word == "x").is_some();
+    let completed_on = if completed {
+        next_if(&|word| parse_date(word).is_some()).and_then(parse_date)
+    } else {
+        None
+    };
+    let mut priority = match completed {
+        true => None,
+        false => next_if(&|word| priority_word(word).is_some()).and_then(priority_word),
+    };
+    let created_on = next_if(&|word| parse_date(word).is_some()).and_then(parse_date);
+
+    let mut id = None;
+    let mut status = None;
+    let mut level = None;
+    let mut title = Vec::new();
+    let mut task = Task::new(0, String::new(), Priority::Medium);
+    for &word in words {
+        let (key, value) = word.split_once(':').unwrap_or(("", ""));
+        let known = match key {
+            "pri" if completed && priority.is_none() => {
+                priority = from_letter(value);
+                priority.is_some()
+            }
+            "status" => {
+                status = Status::parse(value).ok();
+                status.is_some()
+            }
+            "level" => {
+                level = Priority::from_str(value)
+                    .filter(|p| matches!(p, Priority::Urgent | Priority::Someday));
+                level.is_some()
+            }
+            "due" => {
+                task.due = Due::from_file_format(&value.replace('T', " ")).ok();
+                task.due.is_some()
+            }
+            "rec" => {
+                task.recurrence = Recurrence::from_file_format(value).ok();
+                task.recurrence.is_some()
+            }
+            "parent" => {
+                task.parent = parse_id(value).ok();
+                task.parent.is_some()
+            }
+            "blocked" => match value.split(',').map(parse_id).collect() {
+                Ok(ids) => {
+                    task.blocked_by = ids;
+                    true
+                }
+                Err(_) => false,
+            },
+            "desc" => {
+                task.description = decode(value);
+                task.description.is_some()
+            }
+            "uid" => {
+                task.uid = decode(value);
+                task.uid.is_some()
+            }
+            "id" => {
+                id = parse_id(value).ok();
+                id.is_some()
+            }
+            _ => {
+                if let Some(project) = tags::parse_project(word).filter(|_| task.project.is_none())
+                {
+                    task.project = Some(project);
+                    true
+                } else if let Some(tag) = word
+                    .strip_prefix('@')
+                    .and_then(|name| tags::parse_tag(&format!("#{}", name)))
+                {
+                    tags::add_tag(&mut task.tags, tag);
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        if !known {
+            title.push(unescape(word));
+        }
+    }
+
+    if title.is_empty() {
+        return Err("missing title".to_string());
+    }
+    task.title = title.join(" ");
+    // Only trusted while the letter still agrees, in case another app changed it
+    let level = level.filter(|level| {
+        priority
+            .as_ref()
+            .is_some_and(|p| letter(p) == letter(level))
+    });
+    task.priority = level.or(priority).unwrap_or(Priority::Medium);
+    // A status that disagrees with the x is ignored
+    task.status = match status.filter(|status| status.is_closed() == completed) {
+        Some(status) => status,
+        None if completed => Status::Done,
+        None => Status::Todo,
+    };
+    task.created_at = created_on.or(completed_on).map_or_else(Utc::now, start_of);
+    if completed {
+        task.completed_at = Some(completed_on.map_or_else(Utc::now, start_of));
+    }
+    task.updated_at = task
+        .completed_at
+        .unwrap_or(task.created_at)
+        .max(task.created_at);
+
+    Ok(Imported::new(id, task))
+}
+
+// Whether a title word would be taken for a project, context or extension
+fn is_marker(word: &str) -> bool {
+    let key = word.split_once(':').map(|(key, _)| key);
+    tags::parse_project(word).is_some()
+        || word.strip_prefix('@').is_some_and(|name| !name.is_empty())
+        || key.is_some_and(|key| KEYS.contains(&key))
+}
+
+// A title word as written, encoded so it stays in the title
+fn escape(word: &str) -> String {
+    if let Some(rest) = word.strip_prefix(['+', '@', '%']) {
+        if word.starts_with('%') || is_marker(word) {
+            return format!("%{:02X}{}", word.as_bytes()[0], rest);
+        }
+    }
+    match word.split_once(':') {
+        Some((key, value)) if is_marker(word) => format!("{}%3A{}", key, value),
+        _ => word.to_string(),
+    }
+}
+
+fn unescape(word: &str) -> String {
+    match decode(word) {
+        Some(decoded) if decoded != word && (decoded.starts_with('%') || is_marker(&decoded)) => {
+            decoded
+        }
+        _ => word.to_string(),
+    }
+}
+
+// Percent-encodes what todo.txt can't hold inside a word
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for c in text.chars() {
+        if c == '%' || c.is_whitespace() || c.is_control() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+fn decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    #[test]
+    fn test_parse_todotxt_line() {
+        let line = "x 2026-10-18 2026-10-01 Call the +bills bank +later @Phone pri:A \
+                    due:2026-10-20T17:30 h:1 id:3";
+        let imported = parse(line).unwrap().pop().unwrap();
+        let task = imported.task;
+
+        assert_eq!(imported.id, Some(3));
+        assert_eq!(task.title, "Call the bank +later h:1");
+        assert!(task.is_done());
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.project.as_deref(), Some("bills"));
+        assert_eq!(task.tags, vec!["phone"]);
+        assert_eq!(task.due.unwrap().to_file_format(), "2026-10-20 17:30");
+        assert_eq!(local_date(&task.created_at).to_string(), "2026-10-01");
+        assert_eq!(
+            local_date(&task.completed_at.unwrap()).to_string(),
+            "2026-10-18"
+        );
+
+        let plain = parse("(D) Water plants").unwrap().pop().unwrap();
+        assert_eq!(plain.id, None);
+        assert_eq!(plain.task.priority, Priority::Low);
+        let waiting = parse("Water plants status:waiting").unwrap().pop().unwrap();
+        assert_eq!(waiting.task.status, Status::Waiting);
+        let closed = parse("x 2026-10-18 Water plants status:waiting")
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(closed.task.status, Status::Done);
+        let urgent = parse("(A) Water plants level:urgent")
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(urgent.task.priority, Priority::Urgent);
+        let changed = parse("(B) Water plants level:urgent")
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(changed.task.priority, Priority::Medium);
+        assert!(parse("x 2026-10-18").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_keeps_every_field() {
+        let today = Local::now().date_naive();
+        let title = "Pay rent: email @alice re +milk and +eggs then id:7 due:fri 100% %40 %";
+        let mut task = Task::new(4, title.to_string(), Priority::Low);
+        task.set_description("100% of it,\nby transfer".to_string());
+        task.set_recurrence(Some(Recurrence::parse("monthly", today).unwrap()), today);
+        task.add_tags(&["home".to_string()], Some("bills".to_string()));
+        task.parent = Some(1);
+        task.blocked_by = vec![2, 3];
+        task.uid = Some("event 7@example.com".to_string());
+        task.set_status(Status::Cancelled);
+
+        let exported = export(&[&task]);
+        assert!(exported
+            .contains(" %40alice re %2Bmilk and %2Beggs then id%3A7 due%3Afri 100% %2540 %25 "));
+        let imported = parse(&exported).unwrap().pop().unwrap();
+        assert_eq!(imported.id, Some(4));
+
+        let mut copy = imported.task;
+        copy.id = 4;
+        (copy.created_at, copy.updated_at) = (task.created_at, task.updated_at);
+        copy.completed_at = task.completed_at;
+        assert_eq!(copy.title, title);
+        assert_eq!(copy.to_file_format(), task.to_file_format());
+        assert_eq!(export(&[&copy]), exported);
+    }
+}
@@ -0,0 +1,223 @@
+// Time tracking
+//
+// `todo start ID` begins a work session on a task and `todo stop` ends it.
+// Only one session runs at a time: starting a task stops whichever session
+// was running, and completing a task stops its own. Sessions are kept on the
+// task in its `time` field as comma-separated START/END pairs of RFC 3339 UTC
+// timestamps, with END left empty while the session runs:
+//
+//   time=2026-10-18T09:00:00Z/2026-10-18T10:15:00Z,2026-10-18T13:00:00Z/
+//
+// `todo report --since DATE` adds up the time worked from the start of that
+// local day, per task, per tag and per day. A task with several tags counts
+// towards each of them, a session that runs past midnight is split between
+// the days, and a running session counts up to now. Time worked on tasks
+// since archived still counts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+
+use crate::exchange::{local_date, start_of};
+use crate::{format_timestamp, parse_timestamp, Task};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    // None while the session is running
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn start(now: DateTime<Utc>) -> Self {
+        Session {
+            start: now.trunc_subsecs(0),
+            end: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.end.is_none()
+    }
+
+    pub fn stop(&mut self, now: DateTime<Utc>) {
+        self.end = Some(now.trunc_subsecs(0).max(self.start));
+    }
+
+    // Seconds worked, counting a running session up to `now`
+    pub fn seconds(&self, now: DateTime<Utc>) -> i64 {
+        (self.end.unwrap_or(now) - self.start).num_seconds().max(0)
+    }
+}
+
+pub fn encode(sessions: &[Session]) -> String {
+    sessions
+        .iter()
+        .map(|session| {
+            let end = session.end.as_ref().map(format_timestamp);
+            format!(
+                "{}/{}",
+                format_timestamp(&session.start),
+                end.unwrap_or_default()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn decode(s: &str) -> Result<Vec<Session>, String> {
+    s.split(',')
+        .map(|pair| {
+            let (start, end) = pair
+                .split_once('/')
+                .ok_or_else(|| format!("invalid work session '{}'", pair))?;
+            Ok(Session {
+                start: parse_timestamp(start)?,
+                end: Some(end)
+                    .filter(|end| !end.is_empty())
+                    .map(parse_timestamp)
+                    .transpose()?,
+            })
+        })
+        .collect()
+}
+
+pub fn total_seconds(sessions: &[Session], now: DateTime<Utc>) -> i64 {
+    sessions.iter().map(|session| session.seconds(now)).sum()
+}
+
+// "2h 05m", or "12m" under an hour
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+// Seconds worked since a day; tasks and tags come with the most time first
+pub struct Report<'a> {
+    pub since: NaiveDate,
+    pub total: i64,
+    pub tasks: Vec<(&'a Task, i64)>,
+    // None collects the tasks without tags
+    pub tags: Vec<(Option<&'a str>, i64)>,
+    // In date order
+    pub days: Vec<(NaiveDate, i64)>,
+}
+
+pub fn report<'a>(tasks: &[&'a Task], since: NaiveDate, now: DateTime<Utc>) -> Report<'a> {
+    let from = start_of(since);
+    let mut by_task: HashMap<u32, (&Task, i64)> = HashMap::new();
+    let mut by_tag: HashMap<Option<&str>, i64> = HashMap::new();
+    let mut by_day: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+
+    for &task in tasks {
+        for session in &task.sessions {
+            let start = session.start.max(from);
+            let end = session.end.unwrap_or(now);
+            if end <= start {
+                continue;
+            }
+            let seconds = (end - start).num_seconds();
+            by_task.entry(task.id).or_insert((task, 0)).1 += seconds;
+            if task.tags.is_empty() {
+                *by_tag.entry(None).or_default() += seconds;
+            }
+            for tag in &task.tags {
+                *by_tag.entry(Some(tag)).or_default() += seconds;
+            }
+
+            let mut at = start;
+            while at < end {
+                let day = local_date(&at);
+                let midnight = day.succ_opt().map_or(end, start_of);
+                let until = if midnight > at {
+                    midnight.min(end)
+                } else {
+                    end
+                };
+                *by_day.entry(day).or_default() += (until - at).num_seconds();
+                at = until;
+            }
+        }
+    }
+
+    let mut tasks: Vec<(&Task, i64)> = by_task.into_values().collect();
+    tasks.sort_by_key(|(task, seconds)| (-seconds, task.id));
+    let mut tags: Vec<(Option<&str>, i64)> = by_tag.into_iter().collect();
+    tags.sort_by_key(|&(tag, seconds)| (-seconds, tag.is_none(), tag));
+
+    Report {
+        since,
+        total: tasks.iter().map(|(_, seconds)| seconds).sum(),
+        tasks,
+        tags,
+        days: by_day.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use chrono::Duration;
+
+    #[test]
+    fn test_sessions_round_trip() {
+        let start = parse_timestamp("2026-10-18T09:00:00Z").unwrap();
+        let mut done = Session::start(start);
+        done.stop(start + Duration::minutes(75));
+        let running = Session::start(start + Duration::hours(3));
+
+        let encoded = encode(&[done, running]);
+        assert_eq!(
+            encoded,
+            "2026-10-18T09:00:00Z/2026-10-18T10:15:00Z,2026-10-18T12:00:00Z/"
+        );
+        assert_eq!(decode(&encoded).unwrap(), vec![done, running]);
+        assert!(decode("2026-10-18T09:00:00Z").is_err());
+
+        let now = start + Duration::hours(4);
+        assert_eq!(total_seconds(&[done, running], now), 135 * 60);
+        assert_eq!(format_duration(135 * 60), "2h 15m");
+        assert_eq!(format_duration(59), "0m");
+    }
+
+    #[test]
+    fn test_report_splits_by_task_tag_and_day() {
+        let today = chrono::Local::now().date_naive();
+        let yesterday = today.pred_opt().unwrap();
+        let at = |day, hour| start_of(day) + Duration::hours(hour);
+        let session = |start, end| Session {
+            start,
+            end: Some(end),
+        };
+
+        let mut write = Task::new(1, "Write".to_string(), Priority::Medium);
+        write.tags = vec!["client".to_string(), "docs".to_string()];
+        // Two hours before midnight and one after
+        write.sessions = vec![session(at(yesterday, 22), at(today, 1))];
+        let mut call = Task::new(2, "Call".to_string(), Priority::Medium);
+        call.sessions = vec![
+            session(at(yesterday, 1), at(yesterday, 2)),
+            Session::start(at(today, 2)),
+        ];
+        let tasks = [&write, &call];
+
+        let report = report(&tasks, today, at(today, 4));
+        let ids: Vec<(u32, i64)> = report.tasks.iter().map(|(t, s)| (t.id, *s)).collect();
+        assert_eq!(ids, vec![(2, 7200), (1, 3600)]);
+        assert_eq!(report.total, 3 * 3600);
+        assert_eq!(
+            report.tags,
+            vec![(None, 7200), (Some("client"), 3600), (Some("docs"), 3600)]
+        );
+        assert_eq!(report.days, vec![(today, 3 * 3600)]);
+
+        let report = super::report(&tasks, yesterday, at(today, 4));
+        assert_eq!(report.total, 6 * 3600);
+        assert_eq!(report.days, vec![(yesterday, 3 * 3600), (today, 3 * 3600)]);
+    }
+}
@@ -0,0 +1,850 @@
+// Full-screen terminal UI
+//
+// `todo tui` (or `tui` at the prompt) shows the tasks as a scrollable list
+// next to the selected task's details, so nothing needs its id typed. Keys:
+//
+//   ↑ ↓ or k j          select the previous or next task
+//   PgUp PgDn Home End  move a page, or to the first or last task
+//   space               toggle done; T also completes a task with open subtasks
+//   m                   move to the next status the workflow allows
+//   p                   cycle the priority through the enabled levels, e.g.
+//                       low, medium, high
+//   e                   edit the title
+//   d                   edit the description (Alt+Enter starts a new line)
+//   a                   add a task
+//   x                   remove the task, after asking
+//   s                   start or stop timing the task
+//   /                   filter with a `list` query, applied as you type
+//   u U                 undo, redo
+//   r                   reload the tasks
+//   q                   quit (Esc clears the filter first)
+//
+// While editing, Enter saves, Esc cancels, and ← → Home End Backspace Delete
+// work as usual. Each change is made with the same TodoApp methods the
+// commands use, on the tasks as currently saved, and is recorded in the undo
+// history and saved straight away. The list is also reloaded every couple of
+// seconds, so the CLI and other todo processes can be used alongside it.
+
+use std::io;
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::cli::CliError;
+use crate::{format_ids, format_local, tags, tracking, Priority, Task, TaskError, TodoApp};
+
+// How often the tasks are reloaded while no key is pressed
+const REFRESH: Duration = Duration::from_secs(2);
+
+const HINTS: &str = "space toggle  m move  p priority  e edit  d description  a add  x remove  \
+                     s timer  / filter  u undo  q quit";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Title(u32),
+    Description(u32),
+    NewTask,
+    // The filter as it was, for Esc to put back
+    Filter(String),
+}
+
+// A line being edited, with the cursor as a character index
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Input {
+    text: String,
+    cursor: usize,
+}
+
+impl Input {
+    fn new(text: &str) -> Self {
+        Input {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+
+    fn offset(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, c: char) {
+        let at = self.offset(self.cursor);
+        self.text.insert(at, c);
+        self.cursor += 1;
+    }
+
+    // Applies an editing key; false for keys that don't edit
+    fn edit(&mut self, key: KeyEvent) -> bool {
+        let len = self.text.chars().count();
+        match key.code {
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.insert(c),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.offset(self.cursor);
+                self.text.remove(at);
+            }
+            KeyCode::Delete if self.cursor < len => {
+                let at = self.offset(self.cursor);
+                self.text.remove(at);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => return false,
+        }
+        true
+    }
+}
+
+enum Mode {
+    Browse,
+    Edit(Field, Input),
+    // Waiting for y to remove this task
+    ConfirmRemove(u32),
+}
+
+pub struct Tui {
+    mode: Mode,
+    filter: String,
+    // The listed tasks, in order
+    ids: Vec<u32>,
+    list: ListState,
+    // The latest message, and whether it's an error
+    message: Option<(String, bool)>,
+    // Rows in the list when it was last drawn, for PgUp and PgDn
+    page: usize,
+    quit: bool,
+}
+
+pub fn run(app: &mut TodoApp) -> Result<(), CliError> {
+    let mut terminal = ratatui::try_init()
+        .map_err(|e| CliError::Io(format!("Can't start the terminal UI: {}", e)))?;
+    let result = Tui::new(app).event_loop(app, &mut terminal);
+    ratatui::restore();
+    result.map_err(|e| CliError::Io(format!("Terminal error: {}", e)))
+}
+
+// The priority after this one when cycling with p: the next more important
+// of the enabled levels, or else the least important
+fn next_priority(priority: &Priority, enabled: &[Priority]) -> Priority {
+    let higher = enabled.iter().rev().find(|p| p.rank() < priority.rank());
+    higher.or(enabled.last()).unwrap_or(priority).clone()
+}
+
+impl Tui {
+    fn new(app: &TodoApp) -> Self {
+        let mut tui = Tui {
+            mode: Mode::Browse,
+            filter: String::new(),
+            ids: Vec::new(),
+            list: ListState::default(),
+            message: None,
+            page: 10,
+            quit: false,
+        };
+        tui.refresh(app);
+        tui
+    }
+
+    fn event_loop(&mut self, app: &mut TodoApp, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(app, frame))?;
+            if event::poll(REFRESH)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(app, key);
+                    }
+                }
+            } else if matches!(self.mode, Mode::Browse) {
+                self.reload(app);
+            }
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<u32> {
+        self.list.selected().and_then(|i| self.ids.get(i).copied())
+    }
+
+    fn info(&mut self, message: String) {
+        self.message = Some((message, false));
+    }
+
+    fn error(&mut self, message: String) {
+        self.message = Some((message, true));
+    }
+
+    // Lists the tasks matching the filter, keeping the selected task selected
+    // if it's still listed
+    fn refresh(&mut self, app: &TodoApp) {
+        let selected = self.selected();
+        let filter = Some(self.filter.as_str()).filter(|f| !f.trim().is_empty());
+        match app.find_tasks(filter) {
+            Ok(tasks) => self.ids = tasks.iter().map(|task| task.id).collect(),
+            Err(e) => self.error(format!("Invalid filter: {}", e)),
+        }
+
+        let index = selected
+            .and_then(|id| self.ids.iter().position(|&listed| listed == id))
+            .or_else(|| self.list.selected())
+            .map(|i| i.min(self.ids.len().saturating_sub(1)))
+            .or(Some(0))
+            .filter(|_| !self.ids.is_empty());
+        self.list.select(index);
+    }
+
+    fn select_id(&mut self, id: u32) {
+        if let Some(index) = self.ids.iter().position(|&listed| listed == id) {
+            self.list.select(Some(index));
+        }
+    }
+
+    fn move_by(&mut self, rows: isize) {
+        if self.ids.is_empty() {
+            return;
+        }
+        let last = self.ids.len() as isize - 1;
+        let current = self.list.selected().unwrap_or(0) as isize;
+        self.list
+            .select(Some((current + rows).clamp(0, last) as usize));
+    }
+
+    // Rereads the tasks, which another process may have changed
+    fn reload(&mut self, app: &mut TodoApp) {
+        if let Err(e) = app.load_from_file() {
+            self.error(e);
+        }
+        self.refresh(app);
+    }
+
+    // Makes a change to the latest saved tasks, reporting any error
+    fn apply<T>(
+        &mut self,
+        app: &mut TodoApp,
+        f: impl FnOnce(&mut TodoApp) -> Result<(T, String), CliError>,
+    ) -> Option<T> {
+        let result = app
+            .load_from_file()
+            .map_err(CliError::Io)
+            .and_then(|()| app.change(f));
+        let value = match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error(e.to_string());
+                None
+            }
+        };
+        self.refresh(app);
+        value
+    }
+
+    fn handle_key(&mut self, app: &mut TodoApp, key: KeyEvent) {
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => {
+                self.message = None;
+                self.browse_key(app, key);
+            }
+            Mode::Edit(field, input) => self.edit_key(app, key, field, input),
+            Mode::ConfirmRemove(id) => {
+                if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    self.remove(app, id);
+                } else {
+                    self.info("Kept the task".to_string());
+                }
+            }
+        }
+    }
+
+    fn browse_key(&mut self, app: &mut TodoApp, key: KeyEvent) {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let selected = self.selected();
+        let task = selected.and_then(|id| app.tasks.get(&id));
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('c') if control => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refresh(app);
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(self.page as isize),
+            KeyCode::PageUp => self.move_by(-(self.page as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(-(self.ids.len() as isize)),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(self.ids.len() as isize),
+            KeyCode::Char('a') => self.mode = Mode::Edit(Field::NewTask, Input::default()),
+            KeyCode::Char('/') => {
+                let before = self.filter.clone();
+                self.mode = Mode::Edit(Field::Filter(before), Input::new(&self.filter));
+            }
+            KeyCode::Char('u') => self.undo(app, true),
+            KeyCode::Char('U') => self.undo(app, false),
+            KeyCode::Char('r') if control => self.undo(app, false),
+            KeyCode::Char('r') => {
+                self.reload(app);
+                self.info("Reloaded the tasks".to_string());
+            }
+            _ => {
+                let (Some(id), Some(task)) = (selected, task) else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Char(' ') => self.toggle(app, id, false),
+                    KeyCode::Char('T') => self.toggle(app, id, true),
+                    KeyCode::Char('m') => self.move_on(app, id),
+                    KeyCode::Char('p') => self.cycle_priority(app, id),
+                    KeyCode::Char('e') => {
+                        self.mode = Mode::Edit(Field::Title(id), Input::new(&task.title))
+                    }
+                    KeyCode::Char('d') => {
+                        let description = task.description.as_deref().unwrap_or("");
+                        self.mode = Mode::Edit(Field::Description(id), Input::new(description));
+                    }
+                    KeyCode::Char('x') | KeyCode::Delete => {
+                        self.info(format!("Remove #{} {}? (y/n)", id, task.title));
+                        self.mode = Mode::ConfirmRemove(id);
+                    }
+                    KeyCode::Char('s') => self.toggle_timer(app, id),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn edit_key(&mut self, app: &mut TodoApp, key: KeyEvent, field: Field, mut input: Input) {
+        match key.code {
+            KeyCode::Esc => {
+                if let Field::Filter(before) = field {
+                    self.filter = before;
+                    self.refresh(app);
+                }
+            }
+            KeyCode::Enter
+                if key.modifiers.contains(KeyModifiers::ALT)
+                    && matches!(field, Field::Description(_)) =>
+            {
+                input.insert('\n');
+                self.mode = Mode::Edit(field, input);
+            }
+            KeyCode::Enter => self.save_edit(app, field, input.text),
+            _ => {
+                if input.edit(key) {
+                    if let Field::Filter(_) = field {
+                        self.filter = input.text.clone();
+                        self.message = None;
+                        self.refresh(app);
+                    }
+                }
+                self.mode = Mode::Edit(field, input);
+            }
+        }
+    }
+
+    fn save_edit(&mut self, app: &mut TodoApp, field: Field, text: String) {
+        let title_is_empty = tags::extract(&text).title.trim().is_empty();
+        match field {
+            Field::Filter(_) => {}
+            Field::Title(_) | Field::NewTask if title_is_empty => {
+                self.error("The title can't be empty".to_string());
+            }
+            Field::Title(id) => {
+                let renamed = self.apply(app, |app| {
+                    if !app.rename_task(id, &text) {
+                        return Err(TaskError::NotFound(id).into());
+                    }
+                    Ok(((), format!("edit #{}: {}", id, app.tasks[&id].title)))
+                });
+                if renamed.is_some() {
+                    self.info(app.renderer.prefixed("✏️", &format!("Renamed #{}", id)));
+                }
+            }
+            Field::Description(id) => {
+                let described = self.apply(app, |app| {
+                    if !app.update_description(id, text) {
+                        return Err(TaskError::NotFound(id).into());
+                    }
+                    Ok(((), format!("describe #{}: {}", id, app.tasks[&id].title)))
+                });
+                if described.is_some() {
+                    self.info(
+                        app.renderer
+                            .prefixed("📝", &format!("Updated the description of #{}", id)),
+                    );
+                }
+            }
+            Field::NewTask => {
+                let added = self.apply(app, |app| {
+                    let priority = app.config.default_priority.clone();
+                    let id = app.add_task(text, priority, None, None);
+                    Ok((id, format!("add #{}: {}", id, app.tasks[&id].title)))
+                });
+                if let Some(id) = added {
+                    self.select_id(id);
+                    self.info(app.renderer.prefixed("✅", &format!("Added task #{}", id)));
+                }
+            }
+        }
+    }
+
+    fn toggle(&mut self, app: &mut TodoApp, id: u32, force: bool) {
+        let toggled = self.apply(app, |app| {
+            let next = app.toggle_task(id, force).map_err(|e| match e {
+                TaskError::OpenSubtasks(id, open) => CliError::Invalid(format!(
+                    "Task #{} has open subtasks ({}); press T to complete it anyway",
+                    id,
+                    format_ids(&open)
+                )),
+                e => e.into(),
+            })?;
+            let task = &app.tasks[&id];
+            let verb = if task.is_done() { "complete" } else { "reopen" };
+            Ok((
+                (task.is_done(), next),
+                format!("{} #{}: {}", verb, id, task.title),
+            ))
+        });
+        match toggled {
+            Some((true, Some(next))) => self.info(app.renderer.prefixed(
+                "✅",
+                &format!("Completed #{}; the next occurrence is #{}", id, next),
+            )),
+            Some((true, None)) => {
+                self.info(app.renderer.prefixed("✅", &format!("Completed #{}", id)))
+            }
+            Some((false, _)) => {
+                self.info(app.renderer.prefixed("⏳", &format!("Reopened #{}", id)))
+            }
+            None => {}
+        }
+    }
+
+    // Moves a task to the first status after its own, in board order, that
+    // the workflow allows, wrapping around to the start
+    fn move_on(&mut self, app: &mut TodoApp, id: u32) {
+        let moved = self.apply(app, |app| {
+            let from = &app.tasks.get(&id).ok_or(TaskError::NotFound(id))?.status;
+            let order = app.workflow.statuses();
+            let position = |status| order.iter().position(|s| s == status);
+            let targets = app.workflow.targets(from);
+            let status = targets
+                .iter()
+                .find(|&to| position(to) > position(from))
+                .or(targets.first())
+                .cloned()
+                .ok_or_else(|| {
+                    CliError::Invalid(format!("The workflow has no moves from {}", from.name()))
+                })?;
+            app.move_task(id, status.clone(), false)?;
+            let label = format!(
+                "move #{}: {} to {}",
+                id,
+                app.tasks[&id].title,
+                status.name()
+            );
+            Ok((status, label))
+        });
+        if let Some(status) = moved {
+            let text = format!("Moved #{} to {}", id, app.workflow.label(&status));
+            self.info(app.renderer.prefixed(status.emoji(), &text));
+        }
+    }
+
+    fn cycle_priority(&mut self, app: &mut TodoApp, id: u32) {
+        let changed = self.apply(app, |app| {
+            let task = app.tasks.get(&id).ok_or(TaskError::NotFound(id))?;
+            let priority = next_priority(&task.priority, &app.config.priorities);
+            app.set_priority(id, priority.clone());
+            let label = format!("set priority of #{}: {}", id, app.tasks[&id].title);
+            Ok((priority, label))
+        });
+        if let Some(priority) = changed {
+            let text = format!("#{} is now {} priority", id, priority.to_string());
+            self.info(app.renderer.prefixed(priority.emoji(), &text));
+        }
+    }
+
+    fn toggle_timer(&mut self, app: &mut TodoApp, id: u32) {
+        let timing = app.tasks.get(&id).is_some_and(|task| task.is_timing());
+        let changed = self.apply(app, |app| {
+            if timing {
+                app.stop_timer();
+            } else {
+                app.start_timer(id)?;
+            }
+            let verb = if timing { "stop" } else { "start" };
+            Ok(((), format!("{} #{}: {}", verb, id, app.tasks[&id].title)))
+        });
+        if changed.is_some() {
+            let (emoji, verb) = if timing {
+                ("⏹️", "Stopped")
+            } else {
+                ("▶️", "Started")
+            };
+            let text = format!("{} timing #{}", verb, id);
+            self.info(app.renderer.prefixed(emoji, &text));
+        }
+    }
+
+    fn remove(&mut self, app: &mut TodoApp, id: u32) {
+        let removed = self.apply(app, |app| {
+            let title = app
+                .tasks
+                .get(&id)
+                .ok_or(TaskError::NotFound(id))?
+                .title
+                .clone();
+            app.remove_task(id);
+            Ok(((), format!("remove #{}: {}", id, title)))
+        });
+        if removed.is_some() {
+            self.info(
+                app.renderer
+                    .prefixed("🗑️", &format!("Removed task #{}", id)),
+            );
+        }
+    }
+
+    fn undo(&mut self, app: &mut TodoApp, undoing: bool) {
+        let result = app.load_from_file().and_then(|()| match undoing {
+            true => app.undo(),
+            false => app.redo(),
+        });
+        match result {
+            Ok(operation) => match app.save_to_file() {
+                Ok(_) if undoing => self.info(
+                    app.renderer
+                        .prefixed("↩️", &format!("Undid: {}", operation.label)),
+                ),
+                Ok(_) => self.info(
+                    app.renderer
+                        .prefixed("↪️", &format!("Redid: {}", operation.label)),
+                ),
+                Err(e) => self.error(e.to_string()),
+            },
+            Err(e) => self.error(e),
+        }
+        self.refresh(app);
+    }
+
+    fn draw(&mut self, app: &TodoApp, frame: &mut Frame) {
+        let input_height = match &self.mode {
+            Mode::Edit(_, input) => input.text.lines().count().clamp(1, 6) as u16 + 2,
+            _ => 0,
+        };
+        let [main, input_area, status] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(input_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+
+        self.page = list_area.height.saturating_sub(2).max(1) as usize;
+        self.draw_list(app, frame, list_area);
+        let details = self.selected().and_then(|id| app.tasks.get(&id));
+        frame.render_widget(details_paragraph(app, details), details_area);
+        if let Mode::Edit(field, input) = &self.mode {
+            draw_input(frame, input_area, field, input);
+        }
+
+        let status_line = match &self.message {
+            Some((message, true)) => Line::from(message.as_str()).red(),
+            Some((message, false)) => Line::from(message.as_str()).green(),
+            None => Line::from(HINTS).dark_gray(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_list(&mut self, app: &TodoApp, frame: &mut Frame, area: Rect) {
+        let now = Local::now().naive_local();
+        let items: Vec<ListItem> = self
+            .ids
+            .iter()
+            .filter_map(|id| app.tasks.get(id))
+            .map(|task| ListItem::new(task_line(app, task, now)))
+            .collect();
+
+        let title = if self.filter.trim().is_empty() {
+            format!(" Tasks ({}) ", items.len())
+        } else {
+            format!(" Tasks matching {} ({}) ", self.filter.trim(), items.len())
+        };
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+}
+
+fn task_line<'a>(app: &TodoApp, task: &'a Task, now: chrono::NaiveDateTime) -> Line<'a> {
+    let marker = |emoji, ascii| app.renderer.marker(emoji, ascii);
+    let status = if !task.status.is_closed() && !app.open_blockers(task).is_empty() {
+        marker("⛔", "[!]")
+    } else {
+        marker(task.status.emoji(), task.status.marker())
+    };
+    let lead = match app.renderer.emoji {
+        true => format!("{} {} ", status, task.priority.emoji()),
+        false => format!("{} ", status),
+    };
+    let title = Span::raw(task.title.as_str());
+    let mut spans = vec![
+        Span::raw(lead),
+        Span::raw(format!("#{} ", task.id)).dark_gray(),
+        if task.status.is_closed() {
+            title.dark_gray().crossed_out()
+        } else {
+            title
+        },
+    ];
+    for tag in &task.tags {
+        spans.push(Span::raw(format!(" #{}", tag)).cyan());
+    }
+    if let Some(project) = &task.project {
+        spans.push(Span::raw(format!(" +{}", project)).magenta());
+    }
+    if let Some(due) = &task.due {
+        let due = Span::raw(format!(" {}{}", marker("📅 ", "due "), due));
+        spans.push(if task.is_overdue(now) {
+            due.red()
+        } else {
+            due.yellow()
+        });
+    }
+    if task.is_timing() {
+        spans.push(Span::raw(format!(" {}", marker("⏱️", "timing"))));
+    }
+    Line::from(spans)
+}
+
+fn details_paragraph<'a>(app: &TodoApp, task: Option<&'a Task>) -> Paragraph<'a> {
+    let block = Block::bordered().title(" Details ");
+    let Some(task) = task else {
+        return Paragraph::new("No task selected").block(block);
+    };
+    let now = Local::now().naive_local();
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::raw(format!("{:<11}", name)).dark_gray(),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![
+        Line::from(format!("#{} {}", task.id, task.title)).bold(),
+        Line::default(),
+    ];
+    let blockers = app.open_blockers(task);
+    let renderer = &app.renderer;
+    let status = renderer.prefixed(task.status.emoji(), &app.workflow.label(&task.status));
+    let status = match task.status.is_closed() || blockers.is_empty() {
+        true => status,
+        false => format!(
+            "{}, {}",
+            status,
+            renderer.prefixed("⛔", &format!("blocked by {}", format_ids(&blockers)))
+        ),
+    };
+    lines.push(field("Status", status));
+    lines.push(field(
+        "Priority",
+        renderer.prefixed(task.priority.emoji(), task.priority.to_string()),
+    ));
+    if let Some(due) = &task.due {
+        let marker = if task.is_overdue(now) {
+            renderer.marker(" ⚠️ overdue", " overdue")
+        } else {
+            ""
+        };
+        lines.push(field(
+            "Due",
+            format!(
+                "{}{}",
+                due.describe(now.date(), &app.config.date_format),
+                marker
+            ),
+        ));
+    }
+    if let Some(rule) = &task.recurrence {
+        lines.push(field("Repeats", rule.describe()));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|t| format!("#{}", t)).collect();
+        lines.push(field("Tags", tags.join(" ")));
+    }
+    if let Some(project) = &task.project {
+        lines.push(field("Project", format!("+{}", project)));
+    }
+    if let Some(parent) = task.parent {
+        let title = app.tasks.get(&parent).map_or("", |t| t.title.as_str());
+        lines.push(field("Parent", format!("#{} {}", parent, title)));
+    }
+    let children = app.children(task.id);
+    if !children.is_empty() {
+        let done = children
+            .iter()
+            .filter(|id| app.tasks[id].status.is_closed())
+            .count();
+        lines.push(field(
+            "Subtasks",
+            format!("{}/{} done", done, children.len()),
+        ));
+    }
+    if !task.sessions.is_empty() {
+        let tracked = tracking::total_seconds(&task.sessions, Utc::now());
+        let marker = if task.is_timing() { " (running)" } else { "" };
+        let time = format!("{}{}", tracking::format_duration(tracked), marker);
+        lines.push(field("Time", time));
+    }
+    let time_format = app.config.time_format();
+    lines.push(field(
+        "Created",
+        format_local(&task.created_at, &time_format),
+    ));
+    if task.updated_at != task.created_at {
+        lines.push(field(
+            "Updated",
+            format_local(&task.updated_at, &time_format),
+        ));
+    }
+    if let Some(completed_at) = &task.completed_at {
+        lines.push(field("Completed", format_local(completed_at, &time_format)));
+    }
+    if let Some(description) = &task.description {
+        lines.push(Line::default());
+        lines.extend(description.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+}
+
+fn draw_input(frame: &mut Frame, area: Rect, field: &Field, input: &Input) {
+    let title = match field {
+        Field::Title(id) => format!(" Title of #{} ", id),
+        Field::Description(id) => format!(" Description of #{} (Alt+Enter for a new line) ", id),
+        Field::NewTask => " New task ".to_string(),
+        Field::Filter(_) => " Filter (a list query) ".to_string(),
+    };
+    let block = Block::bordered()
+        .title(title)
+        .title_bottom(" Enter saves, Esc cancels ")
+        .border_style(Style::new().fg(Color::Yellow));
+
+    // Keep the cursor in view, scrolling sideways and down as needed
+    let before = &input.text[..input.offset(input.cursor)];
+    let row = before.matches('\n').count() as u16;
+    let column = Span::raw(before.rsplit('\n').next().unwrap_or("")).width() as u16;
+    let width = area.width.saturating_sub(2).max(1);
+    let height = area.height.saturating_sub(2).max(1);
+    let scroll = (
+        row.saturating_sub(height - 1),
+        column.saturating_sub(width - 1),
+    );
+    frame.render_widget(
+        Paragraph::new(input.text.as_str())
+            .block(block)
+            .scroll(scroll),
+        area,
+    );
+    frame.set_cursor_position((area.x + 1 + column - scroll.1, area.y + 1 + row - scroll.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_app;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn press(tui: &mut Tui, app: &mut TodoApp, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\u{1b}' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            tui.handle_key(app, KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn test_keys_make_changes() {
+        let mut app = temp_app("tui-keys");
+        let a = app.add_task("Draft".to_string(), Priority::High, None, None);
+        let b = app.add_task("Review".to_string(), Priority::Low, None, None);
+        app.save_to_file().unwrap();
+        let mut tui = Tui::new(&app);
+        assert_eq!(tui.ids, vec![a, b]);
+
+        press(&mut tui, &mut app, "j p");
+        assert_eq!(app.tasks[&b].priority, Priority::Medium);
+        assert!(app.tasks[&b].is_done());
+
+        press(&mut tui, &mut app, "e");
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        for _ in 0.."Review".len() {
+            tui.handle_key(&mut app, backspace);
+        }
+        press(&mut tui, &mut app, "Proofread #docs\n");
+        assert_eq!(app.tasks[&b].title, "Proofread");
+        assert_eq!(app.tasks[&b].tags, vec!["docs"]);
+        assert_eq!(app.history.recent(1)[0].0.label, "edit #2: Proofread");
+
+        // The filter applies as it's typed, and Esc puts the old one back
+        press(&mut tui, &mut app, "/done");
+        assert_eq!(tui.ids, vec![b]);
+        press(&mut tui, &mut app, "\u{1b}");
+        assert_eq!(tui.ids.len(), 2);
+
+        press(&mut tui, &mut app, "aShip it\n");
+        assert_eq!(tui.selected(), Some(3));
+        press(&mut tui, &mut app, "xy");
+        assert!(!app.tasks.contains_key(&3));
+        press(&mut tui, &mut app, "u");
+        assert_eq!(app.tasks[&3].title, "Ship it");
+
+        let reloaded = TodoApp::new(app.file_path().to_string()).unwrap();
+        assert_eq!(reloaded.tasks.len(), 3);
+        let _ = std::fs::remove_file(app.history_path());
+        let _ = std::fs::remove_file(app.file_path());
+    }
+
+    #[test]
+    fn test_draw_list_and_details() {
+        let mut app = temp_app("tui-draw");
+        let id = app.add_task("Pay rent +bills".to_string(), Priority::High, None, None);
+        app.update_description(id, "by transfer".to_string());
+        let mut tui = Tui::new(&app);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| tui.draw(&app, frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Tasks (1)"));
+        assert!(screen.contains("Pay rent"));
+        assert!(screen.contains("Project    +bills"));
+        assert!(screen.contains("by transfer"));
+        assert!(screen.contains("space toggle"));
+    }
+}
@@ -0,0 +1,317 @@
+// Task statuses and the workflow between them
+//
+// The workflow defines the statuses a task can have and the moves between
+// them. These five are built in:
+//
+//   todo         not started; new tasks begin here
+//   in-progress  being worked on
+//   waiting      on hold, e.g. for someone else
+//   done         completed
+//   cancelled    dropped without being done
+//
+// and a workflow can name statuses of its own, such as review or blocked,
+// made of lowercase letters, digits and dashes. Done and cancelled tasks are
+// closed: they aren't pending, overdue or blocking anything. Every other
+// status, a workflow's own included, is open.
+//
+// `todo move ID STATUS` changes a task's status, but only along the
+// workflow's transitions. The workflow can be replaced with --workflow, the
+// TODO_WORKFLOW environment variable or the config's workflow setting, as
+// `;`-separated rules naming a status, `>`, and the statuses a task can move
+// to from it:
+//
+//   todo>in-progress,done; in-progress>review,todo; review>done; done>todo
+//
+// Statuses appear on the board in the order they're first named, and ones
+// the workflow never names can't be moved to. Every workflow needs todo and
+// done, which `add` and `toggle` use; toggle always completes an open task
+// or reopens a closed one, whatever the transitions say. The config's
+// [statuses] table (config.rs) gives any status the label shown for it;
+// otherwise a status the workflow adds is shown by its name, "code-review"
+// as "Code review".
+//
+// Files written before statuses existed stored a done flag instead, which is
+// read as done or todo.
+
+use std::collections::HashMap;
+
+use crate::Task;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Status {
+    #[default]
+    Todo,
+    InProgress,
+    Waiting,
+    Done,
+    Cancelled,
+    // One a workflow adds, by its name
+    Other(String),
+}
+
+impl Status {
+    pub fn parse(s: &str) -> Result<Status, String> {
+        let name = s.trim().to_lowercase().replace('_', "-");
+        let status = match name.as_str() {
+            "todo" => Status::Todo,
+            "in-progress" | "doing" => Status::InProgress,
+            "waiting" => Status::Waiting,
+            "done" | "completed" => Status::Done,
+            "cancelled" | "canceled" => Status::Cancelled,
+            _ if name.starts_with(|c: char| c.is_ascii_lowercase())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') =>
+            {
+                Status::Other(name)
+            }
+            _ => return Err(format!("invalid status '{}'", s.trim())),
+        };
+        Ok(status)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Status::Todo => "todo",
+            Status::InProgress => "in-progress",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+            Status::Other(name) => name,
+        }
+    }
+
+    // The label unless the config gives another, see Workflow::label
+    fn default_label(&self) -> String {
+        match self {
+            Status::Todo => "To do".to_string(),
+            Status::InProgress => "In progress".to_string(),
+            Status::Waiting => "Waiting".to_string(),
+            Status::Done => "Done".to_string(),
+            Status::Cancelled => "Cancelled".to_string(),
+            Status::Other(name) => {
+                let words = name.replace('-', " ");
+                let mut chars = words.chars();
+                chars.next().map_or(String::new(), |first| {
+                    first.to_uppercase().chain(chars).collect()
+                })
+            }
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Status::Todo => "⏳",
+            Status::InProgress => "🚧",
+            Status::Waiting => "💤",
+            Status::Done => "✅",
+            Status::Cancelled => "🚫",
+            Status::Other(_) => "🔸",
+        }
+    }
+
+    // For listings without emoji
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Status::Todo => "[ ]",
+            Status::InProgress => "[>]",
+            Status::Waiting => "[w]",
+            Status::Done => "[x]",
+            Status::Cancelled => "[-]",
+            Status::Other(_) => "[~]",
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+const DEFAULT: &str = "todo>in-progress,waiting,done,cancelled; \
+                       in-progress>todo,waiting,done,cancelled; \
+                       waiting>todo,in-progress,done,cancelled; \
+                       done>todo; cancelled>todo";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workflow {
+    // In board order
+    statuses: Vec<Status>,
+    transitions: Vec<(Status, Status)>,
+    // From the config's [statuses]
+    labels: HashMap<Status, String>,
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        Workflow::parse(DEFAULT).unwrap()
+    }
+}
+
+impl Workflow {
+    pub fn parse(spec: &str) -> Result<Workflow, String> {
+        let mut workflow = Workflow {
+            statuses: Vec::new(),
+            transitions: Vec::new(),
+            labels: HashMap::new(),
+        };
+        let name = |status: &Status, workflow: &mut Workflow| {
+            if !workflow.statuses.contains(status) {
+                workflow.statuses.push(status.clone());
+            }
+        };
+
+        for rule in spec
+            .split(';')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+        {
+            let (from, targets) = rule.split_once('>').unwrap_or((rule, ""));
+            let from = Status::parse(from)?;
+            name(&from, &mut workflow);
+            for target in targets.split(',').filter(|t| !t.trim().is_empty()) {
+                let to = Status::parse(target)?;
+                name(&to, &mut workflow);
+                if to != from && !workflow.allows(&from, &to) {
+                    workflow.transitions.push((from.clone(), to));
+                }
+            }
+        }
+
+        for required in [Status::Todo, Status::Done] {
+            if !workflow.statuses.contains(&required) {
+                return Err(format!("the workflow needs a {} status", required.name()));
+            }
+        }
+        Ok(workflow)
+    }
+
+    pub fn statuses(&self) -> &[Status] {
+        &self.statuses
+    }
+
+    pub fn allows(&self, from: &Status, to: &Status) -> bool {
+        self.transitions.iter().any(|(a, b)| a == from && b == to)
+    }
+
+    // The statuses a task can move to from `from`, in board order
+    pub fn targets(&self, from: &Status) -> Vec<Status> {
+        self.statuses
+            .iter()
+            .filter(|to| self.allows(from, to))
+            .cloned()
+            .collect()
+    }
+
+    // Labels for statuses, replacing their own
+    pub fn set_labels(&mut self, labels: &[(Status, String)]) {
+        self.labels.extend(labels.iter().cloned());
+    }
+
+    // How a status is shown, e.g. "In progress"
+    pub fn label(&self, status: &Status) -> String {
+        match self.labels.get(status) {
+            Some(label) => label.clone(),
+            None => status.default_label(),
+        }
+    }
+
+    // The tasks in a column per status, keeping their order. Tasks in a
+    // status the workflow doesn't have get columns of their own at the end.
+    pub fn board<'a>(&self, tasks: &[&'a Task]) -> Vec<(Status, Vec<&'a Task>)> {
+        let mut statuses = self.statuses.clone();
+        for task in tasks {
+            if !statuses.contains(&task.status) {
+                statuses.push(task.status.clone());
+            }
+        }
+        statuses
+            .into_iter()
+            .map(|status| {
+                let column = tasks
+                    .iter()
+                    .copied()
+                    .filter(|t| t.status == status)
+                    .collect();
+                (status, column)
+            })
+            .collect()
+    }
+
+    // Why a task can't move from one status to another
+    pub fn refusal(&self, id: u32, from: &Status, to: &Status) -> String {
+        if !self.statuses.contains(to) {
+            return format!("The workflow has no {} status", to.name());
+        }
+        let targets = self.targets(from);
+        let targets: Vec<&str> = targets.iter().map(|s| s.name()).collect();
+        let allowed = match targets.is_empty() {
+            true => "none".to_string(),
+            false => targets.join(", "),
+        };
+        format!(
+            "Task #{} can't move from {} to {} (allowed: {})",
+            id,
+            from.name(),
+            to.name(),
+            allowed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_statuses() {
+        assert_eq!(Status::parse("In_Progress"), Ok(Status::InProgress));
+        assert_eq!(Status::parse("doing"), Ok(Status::InProgress));
+        assert_eq!(Status::parse("canceled"), Ok(Status::Cancelled));
+        assert_eq!(
+            Status::parse("Code-Review"),
+            Ok(Status::Other("code-review".to_string()))
+        );
+        assert!(Status::parse("later?").is_err());
+        assert!(Status::parse("2nd").is_err());
+        assert!(Status::Cancelled.is_closed() && !Status::Waiting.is_closed());
+        assert!(!Status::parse("review").unwrap().is_closed());
+    }
+
+    #[test]
+    fn test_workflow_transitions() {
+        let workflow = Workflow::parse("todo>doing,done; doing>done,todo; done").unwrap();
+        assert_eq!(
+            workflow.statuses(),
+            [Status::Todo, Status::InProgress, Status::Done]
+        );
+        assert!(workflow.allows(&Status::Todo, &Status::InProgress));
+        assert!(!workflow.allows(&Status::Done, &Status::Todo));
+        assert_eq!(
+            workflow.refusal(4, &Status::Done, &Status::Todo),
+            "Task #4 can't move from done to todo (allowed: none)"
+        );
+        assert!(workflow
+            .refusal(4, &Status::Todo, &Status::Waiting)
+            .contains("no waiting"));
+        assert!(Workflow::parse("todo>in-progress").is_err());
+
+        let default = Workflow::default();
+        assert_eq!(default.statuses().len(), 5);
+        assert_eq!(default.targets(&Status::Cancelled), [Status::Todo]);
+    }
+
+    #[test]
+    fn test_workflow_statuses_and_labels() {
+        let mut workflow =
+            Workflow::parse("todo>code-review; code-review>done,todo; done").unwrap();
+        let review = Status::Other("code-review".to_string());
+        assert_eq!(workflow.statuses()[1], review);
+        assert_eq!(workflow.targets(&review), [Status::Todo, Status::Done]);
+        assert_eq!(workflow.label(&review), "Code review");
+        assert_eq!(workflow.label(&Status::Todo), "To do");
+
+        workflow.set_labels(&[(Status::Todo, "Backlog".to_string())]);
+        assert_eq!(workflow.label(&Status::Todo), "Backlog");
+        assert_eq!(workflow.label(&Status::Done), "Done");
+    }
+}